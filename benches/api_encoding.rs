@@ -0,0 +1,61 @@
+use cocos::controllers::api::ipc_requests::{
+    ApiIpcRequest, ApiIpcVelRequestBody, ValidatesApiIpcBody,
+};
+use cocos::controllers::api::ipc_responses::{ApiIpcPosResponseBody, ApiResponse, ApiStatus};
+use cocos::controllers::api::protocol::{
+    ApiEncoding, API_LEGACY_PROTOCOL_VERSION, API_PROTOCOL_VERSION,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_json::json;
+
+/// The ways a session can be set up, from the oldest to the newest.
+const SESSIONS: [(&str, u16, ApiEncoding); 3] = [
+    ("json_v1", API_LEGACY_PROTOCOL_VERSION, ApiEncoding::Json),
+    ("json_v2", API_PROTOCOL_VERSION, ApiEncoding::Json),
+    ("msgpack_v2", API_PROTOCOL_VERSION, ApiEncoding::MessagePack),
+];
+
+/// Encodes a velocity request as the API would in the given session.
+fn vel_request(version: u16, encoding: ApiEncoding) -> Vec<u8> {
+    let body = json!({"l": 40, "r": -40});
+    let request = if version == API_LEGACY_PROTOCOL_VERSION {
+        json!({"request_type": 1, "body": body.to_string()})
+    } else {
+        json!({"request_type": 1, "body": body})
+    };
+    match encoding {
+        ApiEncoding::Json => serde_json::to_vec(&request).unwrap(),
+        ApiEncoding::MessagePack => rmp_serde::to_vec_named(&request).unwrap(),
+    }
+}
+
+fn decode_requests(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_vel_request");
+    for (name, version, encoding) in SESSIONS {
+        let message = vel_request(version, encoding);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &message, |b, message| {
+            b.iter(|| {
+                let request = ApiIpcRequest::decode(black_box(message), encoding).unwrap();
+                let body: ApiIpcVelRequestBody = request.decode_body(version).unwrap();
+                black_box(body.validate())
+            })
+        });
+    }
+    group.finish();
+}
+
+fn encode_responses(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_pos_response");
+    for (name, version, encoding) in SESSIONS {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let body = ApiIpcPosResponseBody { x: 0.3127, y: -1.2549, theta: 2.0944 };
+                ApiResponse::new(ApiStatus::Success, black_box(body)).encode(version, encoding)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode_requests, encode_responses);
+criterion_main!(benches);
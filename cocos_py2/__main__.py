@@ -27,10 +27,11 @@ class App:
     def run(self):
         # type: () -> None
         # The first argument must be the pipe through which to communicate with
-        # cocos.
+        # cocos, the second one is the id of the robot.
         cocos = CocosCommunicator(sys.argv[1])
         cocos.begin()
-        coachbot = Coachbot(cocos)
+        robot_id = int(sys.argv[2]) if len(sys.argv) > 2 else -1
+        coachbot = Coachbot(cocos, robot_id)
         # Read the user script from stdin. Cocos will inject one.
         user_script = self.__class__.format_script(sys.stdin.read())
         exec(user_script, { '_bot': coachbot })
@@ -14,11 +14,16 @@ class Coachbot:
     to the Coachbot available.
     """
 
-    def __init__(self, communicator):
-        # type: (cocos.CocosCommunicator) -> None
-        self._id = -1  # type: int
+    def __init__(self, communicator, robot_id=-1):
+        # type: (cocos.CocosCommunicator, int) -> None
+        self._id = robot_id  # type: int
         self.__cocos = communicator
-        logging.basicConfig()  # TODO: Remove and replace with IPC logging
+        self.__logger = logging.getLogger('api.v1.user')
+        self.__logger.setLevel(logging.DEBUG)
+        self.__logger.propagate = False
+        handler = cocos.IPCLogHandler(communicator)
+        handler.setFormatter(logging.Formatter('%(name)s: %(message)s'))
+        self.__logger.addHandler(handler)
 
     @property
     def id(self):  # pylint: disable=invalid-name
@@ -34,13 +39,17 @@ class Coachbot:
             robot.set_led(*((100, 0, 0) if robot.id == 3 else (0, 100, 0)))
 
         Returns:
-            int: The id number of self.
+            int: The id number of self, ie. the ``robot_id`` cocos was
+            configured with.
         """
         return self._id
 
     @property
     def logger(self):
-        return logging.getLogger('api.v1.user')
+        # type: () -> logging.Logger
+        """The logger of the user script. Records are forwarded to cocos,
+        which logs them under the ``user.log`` target."""
+        return self.__logger
 
     @id.setter
     def id(self, new):  # pylint: disable=invalid-name
@@ -96,7 +105,11 @@ class Coachbot:
             msg (str): The message to attempt to transmit. This message must be
             of size ``coach_os.custom_net.MSG_LEN - 8`` or shorter. Longer
             messages are trimmed. The ``-8`` is here due to being a legacy bug.
+
+        Returns:
+            bool: Whether the message was queued for transmission.
         """
+        return self.__cocos.send_msg(msg)
 
     def recv_msg(self, clear=False):
         # type: (bool) -> list[str]
@@ -112,6 +125,7 @@ class Coachbot:
             list[str]: Up to ``custom_net.MAX_MSG_NUM`` messages since last
             invokation.
         """
+        return self.__cocos.recv_msg(clear)
 
     def get_pose(self):
         # type: () -> tuple[float, float, float]
@@ -129,6 +143,20 @@ class Coachbot:
         except ValueError as v_err:
             self.logger.exception(v_err)
 
+    def get_status(self):
+        # type: () -> dict[str, bool]
+        """
+        Retrieves the status of the motors. The motors are braked by cocos if
+        no velocity was set within the watchdog timeout (5 seconds by default)
+        and are released by the next call to :meth:`set_vel`.
+
+        Returns:
+            dict[str, bool] | None: ``motors_locked`` tells whether the motors
+            are braked and ``watchdog_tripped`` whether that is due to the
+            watchdog. None if cocos could not be reached.
+        """
+        return self.__cocos.send_get_status()
+
     def delay(self, millis=200):
         # type: (float) -> None
         """Waits some miliseconds (default 200).
@@ -3,21 +3,63 @@
 from __future__ import absolute_import, print_function
 from abc import abstractmethod, ABCMeta
 import json
+import logging
 import zmq
 from enum import IntEnum
 from timeit import default_timer
 
+try:
+    import msgpack
+except ImportError:
+    # Without msgpack, only JSON is offered in the handshake.
+    msgpack = None
+
 
 MESSAGE_ENCODING = 'ascii'
 
+# The maximum length of a message sent to other coachbots. Must match
+# NET_MSG_LEN in cocos.
+NET_MSG_LEN = 56
+
+# The protocol version spoken by this API. Must match API_PROTOCOL_VERSION in
+# cocos.
+PROTOCOL_VERSION = 2
+
+# The protocol version spoken with cocos versions that predate the handshake.
+# From version 2 on, bodies are sent as JSON objects.
+LEGACY_PROTOCOL_VERSION = 1
+
+# The encodings offered in the handshake, most preferred first. The handshake
+# itself is always encoded in JSON.
+ENCODINGS = (['msgpack'] if msgpack is not None else []) + ['json']
+
 
 # For whatever reason I could not get IntEnum to behave.
 IPC_MESSAGE_TYPES = {
     'LED': 0,
     'VEL': 1,
-    'POS': 2
+    'POS': 2,
+    'MSG_SEND': 3,
+    'MSG_RECV': 4,
+    'LOG': 5,
+    'STATUS': 6,
+    'STATS': 7,
+    'HANDSHAKE': 8
 }
 
+# The maximum length of a log message in bytes of UTF-8. Must match
+# API_LOG_MESSAGE_MAX_LEN in cocos.
+LOG_MESSAGE_MAX_LEN = 4096
+
+
+def trim_utf8(text, max_len):
+    # type: (Union[str, unicode], int) -> unicode
+    """Trims ``text`` to at most ``max_len`` bytes of UTF-8 without splitting
+    a character. Byte strings are taken to be UTF-8 already."""
+    if isinstance(text, unicode):
+        text = text.encode('utf-8')
+    return text[:max_len].decode('utf-8', 'ignore')
+
 
 class IPCMessage(object):
     """A message object that can be sent over the network."""
@@ -25,17 +67,23 @@ class IPCMessage(object):
     __TYPE__ = -1
 
     @abstractmethod
-    def serialize(self):
-        """Serializes the message data."""
-        # type: () -> bytes
+    def body(self):
+        """Returns the message body."""
+        # type: () -> Dict[str, Any]
         pass
 
-    def serialize_total(self):
-        # type: () -> bytes
-        return json.dumps({
+    def serialize_total(self, version, encoding='json'):
+        # type: (int, str) -> bytes
+        body = self.body()
+        request = {
             'request_type': self.__class__.__TYPE__,
-            'body': self.serialize()
-        }).encode(MESSAGE_ENCODING)
+            'body': body if version > LEGACY_PROTOCOL_VERSION
+                    else json.dumps(body)
+        }
+        if encoding == 'msgpack':
+            # Python 2 strings are bytes, which cocos expects as strings.
+            return msgpack.packb(request, use_bin_type=False)
+        return json.dumps(request).encode(MESSAGE_ENCODING)
 
     @staticmethod
     def unpack_response(response):
@@ -55,13 +103,13 @@ class IPCSendLedMessage(IPCMessage):
                 raise ValueError('Colors must be 0 <= color <= 100')
         self._rgb = rgb
 
-    def serialize(self):
-        # type: () -> bytes
-        return json.dumps({
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {
             'r': self._rgb[0],
             'g': self._rgb[1],
             'b': self._rgb[2]
-        }).encode(MESSAGE_ENCODING)
+        }
 
 
 class IPCSendVelocityMessage(IPCMessage):
@@ -77,12 +125,12 @@ class IPCSendVelocityMessage(IPCMessage):
                 raise ValueError('Velocities must be 0 <= color <= 100')
         self._vels = velocities
 
-    def serialize(self):
-        # type: () -> bytes
-        return json.dumps({
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {
             'l': self._vels[0],
             'r': self._vels[1]
-        }).encode(MESSAGE_ENCODING)
+        }
 
 
 class IPCSendPosRequestMessage(IPCMessage):
@@ -91,28 +139,129 @@ class IPCSendPosRequestMessage(IPCMessage):
         # type: () -> None
         super(IPCSendPosRequestMessage, self).__init__()
 
-    def serialize(self):
-        return json.dumps({}).encode(MESSAGE_ENCODING)
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {}
 
     @staticmethod
     def unpack_response(response):
         # type: (IPCResponse) -> Tuple[float, float, float]
-        body = json.loads(response.deserialized['body'])
+        body = response.body
         return (body['x'], body['y'], body['theta'])
 
 
+class IPCSendMsgMessage(IPCMessage):
+    __TYPE__ = IPC_MESSAGE_TYPES['MSG_SEND']
+
+    def __init__(self, msg):
+        # type: (str) -> None
+        self._msg = msg[:NET_MSG_LEN]
+
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {
+            'msg': self._msg
+        }
+
+    @staticmethod
+    def unpack_response(response):
+        # type: (IPCResponse) -> bool
+        return True
+
+
+class IPCRecvMsgMessage(IPCMessage):
+    __TYPE__ = IPC_MESSAGE_TYPES['MSG_RECV']
+
+    def __init__(self, clear):
+        # type: (bool) -> None
+        self._clear = bool(clear)
+
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {
+            'clear': self._clear
+        }
+
+    @staticmethod
+    def unpack_response(response):
+        # type: (IPCResponse) -> List[str]
+        body = response.body
+        return list(body['messages'])
+
+
+class IPCLogMessage(IPCMessage):
+    __TYPE__ = IPC_MESSAGE_TYPES['LOG']
+
+    def __init__(self, level, message):
+        # type: (int, str) -> None
+        self._level = max(0, min(int(level), 255))
+        self._message = trim_utf8(message, LOG_MESSAGE_MAX_LEN)
+
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {
+            'level': self._level,
+            'message': self._message
+        }
+
+
+class IPCStatusRequestMessage(IPCMessage):
+    __TYPE__ = IPC_MESSAGE_TYPES['STATUS']
+
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {}
+
+    @staticmethod
+    def unpack_response(response):
+        # type: (IPCResponse) -> Dict[str, bool]
+        body = response.body
+        return {
+            'motors_locked': bool(body['motors_locked']),
+            'watchdog_tripped': bool(body['watchdog_tripped'])
+        }
+
+
+class IPCStatsRequestMessage(IPCMessage):
+    __TYPE__ = IPC_MESSAGE_TYPES['STATS']
+
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {}
+
+    @staticmethod
+    def unpack_response(response):
+        # type: (IPCResponse) -> Dict[str, Any]
+        return response.body
+
+
+class IPCHandshakeMessage(IPCMessage):
+    __TYPE__ = IPC_MESSAGE_TYPES['HANDSHAKE']
+
+    def body(self):
+        # type: () -> Dict[str, Any]
+        return {'version': PROTOCOL_VERSION, 'encodings': ENCODINGS}
+
+    @staticmethod
+    def unpack_response(response):
+        # type: (IPCResponse) -> Dict[str, Any]
+        return response.body
+
+
 class IPCStatus(IntEnum):
     SUCCESS = 0
     INVALID_ENCODING = 1,
     INVALID_REQUEST_HEAD = 2,
     INVALID_REQUEST_BODY = 3,
-    INVALID_REQUEST_ARGS = 4
+    INVALID_REQUEST_ARGS = 4,
+    INCOMPATIBLE_VERSION = 5
 
 
 class IPCResponse(object):
-    def __init__(self, as_bytes):
-        # type: (bytes) -> None
+    def __init__(self, as_bytes, encoding='json'):
+        # type: (bytes, str) -> None
         self._data = as_bytes
+        self._encoding = encoding
         self.__deserialized = None
 
     @property
@@ -121,7 +270,10 @@ class IPCResponse(object):
         if self.__deserialized is not None:
             return self.__deserialized
 
-        result = json.loads(self._data)
+        if self._encoding == 'msgpack':
+            result = msgpack.unpackb(self._data, raw=False)
+        else:
+            result = json.loads(self._data)
         if result.get('status') is None:
             raise ValueError('The Response from Cocos does not contain a '
                              'status')
@@ -129,36 +281,34 @@ class IPCResponse(object):
         self.__deserialized = result
         return self.__deserialized
 
-
-class IPCResponseError(IPCResponse):
-    def __init__(self, as_bytes):
-        # type: (bytes) -> None
-        IPCResponse.__init__(self, as_bytes)
-        self.__error_deserialized = None
-
     @property
-    def deserialized(self):
-        # type: () -> Dict[str, Union[bytes, int, str]]
-        if self.__error_deserialized is not None:
-            return self.__error_deserialized
-
-        parent_result = super(IPCResponseError, self).deserialized
-        assert isinstance(parent_result['body'], bytes)
-        parent_result['body'] = parent_result['body'].decode('ascii')
-        self.__error_deserialized = parent_result
-        return self.__error_deserialized
+    def body(self):
+        # type: () -> Dict[str, Any]
+        """The body of the response, which legacy versions of the protocol
+        encode into a string."""
+        body = self.deserialized['body']
+        return json.loads(body) if isinstance(body, basestring) else body
 
 
 class IPCError(Exception):
     pass
 
 class IPCInvalidResponse(IPCError):
-    pass
+    def __init__(self, message, status):
+        # type: (str, IPCStatus) -> None
+        IPCError.__init__(self, message)
+        self.status = status
+
+
+class IPCIncompatibleVersion(IPCInvalidResponse):
+    """Raised when cocos does not speak the protocol version of the API."""
 
 
 class IPCMessager(object):
     __RESPONSE_TIMEOUT = 3  # The maximum acceptable timeout in ms before a
                             # no-response is asserted.
+    # The handshake is sent while cocos may still be starting up.
+    __HANDSHAKE_TIMEOUT = 1000
 
     def __init__(self, tx_addr):
         # type: (str) -> None
@@ -166,6 +316,12 @@ class IPCMessager(object):
         self._tx_ctx = zmq.Context()
         self._tx_sock = self._tx_ctx.socket(zmq.REQ)
         self._time_since_last_send = default_timer()
+        self._version = LEGACY_PROTOCOL_VERSION
+        self._encoding = 'json'
+        # Whether the handshake must be sent again before the next message.
+        # Cocos switches to the negotiated encoding once it replied to the
+        # handshake, so a lost reply leaves the API speaking the wrong one.
+        self._handshake_pending = False
 
     def begin(self):
         # type: () -> None
@@ -177,24 +333,73 @@ class IPCMessager(object):
         self._tx_sock.close()
         self._tx_sock = self._tx_ctx.socket(zmq.REQ)
         self._tx_sock.connect(self._tx_addr)
+        # The lost reply may have been the one to a handshake.
+        self._handshake_pending = True
+
+    def handshake(self, timeout=None):
+        # type: (Optional[int]) -> Optional[Dict[str, Any]]
+        """Declares the protocol version and the encodings of the API to cocos,
+        returning the capabilities of cocos. Later messages are sent in the
+        encoding picked by cocos. Returns None if cocos did not answer or
+        predates the handshake, in which case the legacy version is spoken.
+        If cocos did not answer, the handshake is sent again before the next
+        message.
 
-    def tx(self, message):
-        # type: (IPCMessage) -> None
+        Raises:
+            IPCIncompatibleVersion: If cocos does not speak PROTOCOL_VERSION.
+        """
+        if timeout is None:
+            timeout = self.__class__.__HANDSHAKE_TIMEOUT
+        self._handshake_pending = False
+        try:
+            # Cocos accepts a handshake in JSON and the legacy version at any
+            # time, whatever was negotiated before.
+            capabilities = self._tx(IPCHandshakeMessage(), timeout,
+                                    LEGACY_PROTOCOL_VERSION, 'json')
+        except IPCInvalidResponse as err:
+            if err.status == IPCStatus.INVALID_REQUEST_HEAD:
+                return None
+            raise
+        if capabilities is not None:
+            self._version = PROTOCOL_VERSION
+            self._encoding = capabilities.get('encoding', 'json')
+        return capabilities
+
+    def tx(self, message, timeout=None):
+        # type: (IPCMessage, Optional[int]) -> Any
+        if timeout is None:
+            timeout = self.__class__.__RESPONSE_TIMEOUT
+        if self._handshake_pending:
+            self.handshake(timeout)
+        try:
+            return self._tx(message, timeout, self._version, self._encoding)
+        except IPCInvalidResponse as err:
+            if err.status != IPCStatus.INVALID_ENCODING:
+                raise
+            # Cocos negotiated another encoding in a handshake whose reply was
+            # lost. Negotiate again and retry once.
+            self.handshake(timeout)
+            return self._tx(message, timeout, self._version, self._encoding)
+
+    def _tx(self, message, timeout, version, encoding):
+        # type: (IPCMessage, int, int, str) -> Any
         try:
             # Send a message
-            self._tx_sock.send(message.serialize_total())
+            self._tx_sock.send(message.serialize_total(version, encoding))
 
             # Wait for response.
-            if not self._tx_sock.poll(self.__class__.__RESPONSE_TIMEOUT):
+            if not self._tx_sock.poll(timeout):
                 raise zmq.ZMQError(zmq.EAGAIN,
                                    'Timed out waiting for response')
             msg = self._tx_sock.recv()
-            response = IPCResponse(bytes(msg))
+            response = IPCResponse(bytes(msg), encoding)
 
             # Ensure that the status is successful.
-            if response.deserialized['status'] != IPCStatus.SUCCESS:
-                response = IPCResponseError(bytes(msg))
-                raise IPCInvalidResponse(response.deserialized['body'])
+            status = response.deserialized['status']
+            if status == IPCStatus.INCOMPATIBLE_VERSION:
+                raise IPCIncompatibleVersion(response.body['message'], status)
+            if status != IPCStatus.SUCCESS:
+                raise IPCInvalidResponse(response.body['message'], status)
             return message.__class__.unpack_response(response)
         except (zmq.ZMQError, AssertionError) as err:
             # TODO: Warn user
@@ -207,10 +412,25 @@ class CocosCommunicator(object):
     def __init__(self, tx_addr):
         # type: (str) -> None
         self._messager = IPCMessager(tx_addr)
+        self._capabilities = None  # type: Optional[Dict[str, Any]]
 
     def begin(self):
         # type: () -> None
+        """Connects to cocos and negotiates the protocol version.
+
+        Raises:
+            IPCIncompatibleVersion: If cocos does not speak the protocol
+                version of this API.
+        """
         self._messager.begin()
+        self._capabilities = self._messager.handshake()
+
+    @property
+    def capabilities(self):
+        # type: () -> Optional[Dict[str, Any]]
+        """The protocol versions, robot id, request types and limits reported
+        by cocos, or None if cocos predates the handshake."""
+        return self._capabilities
 
     def send_led(self, rgb):
         # type: (Tuple[int, int, int]) -> None
@@ -237,3 +457,51 @@ class CocosCommunicator(object):
         """Sends a position request, returning the data."""
         message = IPCSendPosRequestMessage()
         return self._messager.tx(message)
+
+    def send_msg(self, msg):
+        # type: (str) -> bool
+        """Broadcasts a message to the other coachbots, returning whether it
+        was queued."""
+        message = IPCSendMsgMessage(msg)
+        return bool(self._messager.tx(message))
+
+    def recv_msg(self, clear):
+        # type: (bool) -> List[str]
+        """Reads the messages received from other coachbots."""
+        message = IPCRecvMsgMessage(clear)
+        return self._messager.tx(message) or []
+
+    def send_log(self, level, msg):
+        # type: (int, str) -> None
+        """Emits a log record through cocos."""
+        message = IPCLogMessage(level, msg)
+        self._messager.tx(message)
+
+    def send_get_status(self):
+        # type: () -> Dict[str, bool]
+        """Queries the status of the motion output."""
+        message = IPCStatusRequestMessage()
+        return self._messager.tx(message)
+
+    def send_get_stats(self):
+        # type: () -> Dict[str, Any]
+        """Queries the timing statistics of the periodic tasks of cocos."""
+        message = IPCStatsRequestMessage()
+        return self._messager.tx(message)
+
+
+class IPCLogHandler(logging.Handler):
+    """A logging handler that forwards records to cocos, which logs them
+    tagged with the robot id."""
+
+    def __init__(self, communicator):
+        # type: (CocosCommunicator) -> None
+        logging.Handler.__init__(self)
+        self._cocos = communicator
+
+    def emit(self, record):
+        # type: (logging.LogRecord) -> None
+        try:
+            self._cocos.send_log(record.levelno, self.format(record))
+        except Exception:  # pylint: disable=broad-except
+            self.handleError(record)
@@ -0,0 +1,43 @@
+#!/usr/bin/env python3
+
+import sys
+from cocos_py3.api import Coachbot
+from cocos_py3.cocos import CocosCommunicator
+
+USER_CODE_TEMPLATE = \
+"""
+import sys
+
+{user_script}
+
+usr(_bot)
+"""
+
+
+class App:
+    def __init__(self) -> None:
+        self.run_script: str = ''
+
+    @staticmethod
+    def format_script(script: str) -> str:
+        return USER_CODE_TEMPLATE.format(user_script=script)
+
+    def run(self) -> None:
+        # The first argument must be the pipe through which to communicate with
+        # cocos, the second one is the id of the robot.
+        cocos = CocosCommunicator(sys.argv[1])
+        cocos.begin()
+        robot_id = int(sys.argv[2]) if len(sys.argv) > 2 else -1
+        coachbot = Coachbot(cocos, robot_id)
+        # Read the user script from stdin. Cocos will inject one.
+        user_script = self.__class__.format_script(sys.stdin.read())
+        exec(user_script, {'_bot': coachbot})
+
+
+def main() -> int:
+    app = App()
+    app.run()
+    return 0
+
+
+sys.exit(main())
@@ -0,0 +1,3 @@
+from .coachbot import Coachbot
+
+__all__ = ['Coachbot']
@@ -0,0 +1,161 @@
+#!/usr/bin/env python3
+
+"""This module defines the API class coachbot which is exposed to the
+usercode.
+"""
+
+import logging
+import time
+from typing import Dict, List, Optional, Tuple, Union
+
+from cocos_py3 import cocos
+
+
+class Coachbot:
+    """Represents the base Coachbot. Currently, there is no simple way to
+    either compose this class nor inherit from it. This is the only interface
+    to the Coachbot available.
+    """
+
+    def __init__(self, communicator: cocos.CocosCommunicator,
+                 robot_id: int = -1) -> None:
+        self._id: int = robot_id
+        self.__cocos = communicator
+        self.__start_time = time.monotonic()
+        self.__logger = logging.getLogger('api.v1.user')
+        self.__logger.setLevel(logging.DEBUG)
+        self.__logger.propagate = False
+        handler = cocos.IPCLogHandler(communicator)
+        handler.setFormatter(logging.Formatter('%(name)s: %(message)s'))
+        self.__logger.addHandler(handler)
+
+    @property
+    def id(self) -> int:  # pylint: disable=invalid-name
+        """
+        Facility for fetching the identification number of the current robot.
+
+        You can use this property to return the current robot id, for example:
+
+        .. code-block:: python
+
+            # Set the color of bot 3 to red and others to green.
+            robot.set_led(*((100, 0, 0) if robot.id == 3 else (0, 100, 0)))
+
+        Returns:
+            int: The id number of self, ie. the ``robot_id`` cocos was
+            configured with.
+        """
+        return self._id
+
+    @id.setter
+    def id(self, new: int) -> None:  # pylint: disable=invalid-name
+        self.logger.warning('Attempting to set id to %d. This is unsupported '
+                            'behavior. Ignored.', new)
+
+    @property
+    def logger(self) -> logging.Logger:
+        """The logger of the user script. Records are forwarded to cocos,
+        which logs them under the ``user.log`` target."""
+        return self.__logger
+
+    def set_led(self, r: int, g: int, b: int) -> None:  # pylint: disable=invalid-name
+        """Sets the color of the onboard LED.
+
+        Note:
+            This function **does not accept values between 0-255**. Allowable
+            values are between 0 - 100.
+
+        Parameters:
+            r (int): red value (0 - 100).
+            g (int): green value (0 - 100).
+            b (int): blue value (0 - 100).
+        """
+        try:
+            self.__cocos.send_led((r, g, b))
+        except ValueError as v_err:
+            self.logger.exception(v_err)
+
+    def set_vel(self, left: Union[int, float], right: Union[int, float]) -> None:
+        """
+        Sets the speed for the left and right wheel in percentage values.
+
+        Parameters:
+            left (int): The left motor speed (-100 - 100)
+            right (int): The right motor speed (-100 - 100)
+        """
+        try:
+            self.__cocos.send_vel((int(left), int(right)))
+        except ValueError as v_err:
+            self.logger.exception(v_err)
+
+    def get_clock(self) -> float:
+        """
+        Returns:
+            float: The time elapsed since the program started in seconds.
+        """
+        return time.monotonic() - self.__start_time
+
+    def send_msg(self, msg: str) -> bool:
+        """Attempts to transmit the given message returning whether it was
+        successful.
+
+        Parameters:
+            msg (str): The message to attempt to transmit. This message must be
+            of size ``cocos.NET_MSG_LEN`` or shorter. Longer messages are
+            trimmed.
+
+        Returns:
+            bool: Whether the message was queued for transmission.
+        """
+        return self.__cocos.send_msg(msg)
+
+    def recv_msg(self, clear: bool = False) -> List[str]:
+        """
+        Reads the messages received since the last invokation. If this
+        function does not have any new updates to send, it will return an
+        empty list.
+
+        Parameters:
+            clear (bool): Whether to clear the message buffer after reading.
+
+        Returns:
+            list[str]: The messages received since last invokation.
+        """
+        return self.__cocos.recv_msg(clear)
+
+    def get_pose(self) -> Optional[Tuple[float, float, float]]:
+        """
+        This function retrieves the pose of the robot, if it can. If it can't
+        it returns None.
+
+        Returns:
+            tuple[float, float, float] | None: The global pose as a tuple (x,
+            y, theta) if new data available since last invokation, None
+            otherwise.
+        """
+        try:
+            return self.__cocos.send_get_position()
+        except ValueError as v_err:
+            self.logger.exception(v_err)
+            return None
+
+    def get_status(self) -> Optional[Dict[str, bool]]:
+        """
+        Retrieves the status of the motors. The motors are braked by cocos if
+        no velocity was set within the watchdog timeout (5 seconds by default)
+        and are released by the next call to :meth:`set_vel`.
+
+        Returns:
+            dict[str, bool] | None: ``motors_locked`` tells whether the motors
+            are braked and ``watchdog_tripped`` whether that is due to the
+            watchdog. None if cocos could not be reached.
+        """
+        return self.__cocos.send_get_status()
+
+    def delay(self, millis: float = 200) -> None:
+        """Waits some miliseconds (default 200).
+
+        Parameters:
+            millis: The amount of time to wait.
+        """
+        time.sleep(float(millis) / 1000.0)
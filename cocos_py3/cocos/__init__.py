@@ -0,0 +1,439 @@
+#!/usr/bin/env python3
+
+"""Implements the ZMQ IPC protocol spoken with cocos. The wire format is
+identical to that of ``cocos_py2``.
+
+The API declares the protocol version it speaks in a handshake. Cocos versions
+that predate the handshake reject it as an invalid request head, in which case
+the legacy version, with bodies encoded into strings, is spoken. If the
+``msgpack`` package is installed, MessagePack is offered in the handshake too.
+"""
+
+from abc import ABC, abstractmethod
+from enum import IntEnum
+import json
+import logging
+from typing import Any, Dict, List, Optional, Tuple
+
+import zmq
+
+try:
+    import msgpack
+except ImportError:
+    # Without msgpack, only JSON is offered in the handshake.
+    msgpack = None
+
+
+MESSAGE_ENCODING = 'ascii'
+
+# The maximum length of a message sent to other coachbots. Must match
+# NET_MSG_LEN in cocos.
+NET_MSG_LEN = 56
+
+# The protocol version spoken by this API. Must match API_PROTOCOL_VERSION in
+# cocos.
+PROTOCOL_VERSION = 2
+
+# The protocol version spoken with cocos versions that predate the handshake.
+# From version 2 on, bodies are sent as JSON objects.
+LEGACY_PROTOCOL_VERSION = 1
+
+# The encodings offered in the handshake, most preferred first. The handshake
+# itself is always encoded in JSON.
+ENCODINGS = (['msgpack'] if msgpack is not None else []) + ['json']
+
+
+class IPCMessageType(IntEnum):
+    LED = 0
+    VEL = 1
+    POS = 2
+    MSG_SEND = 3
+    MSG_RECV = 4
+    LOG = 5
+    STATUS = 6
+    STATS = 7
+    HANDSHAKE = 8
+
+
+# The maximum length of a log message in bytes of UTF-8. Must match
+# API_LOG_MESSAGE_MAX_LEN in cocos.
+LOG_MESSAGE_MAX_LEN = 4096
+
+
+def trim_utf8(text: str, max_len: int) -> str:
+    """Trims ``text`` to at most ``max_len`` bytes of UTF-8 without splitting
+    a character."""
+    return text.encode('utf-8')[:max_len].decode('utf-8', 'ignore')
+
+
+class IPCMessage(ABC):
+    """A message object that can be sent over the network."""
+    __TYPE__: IPCMessageType
+
+    @abstractmethod
+    def body(self) -> Dict[str, Any]:
+        """Returns the message body."""
+
+    def serialize_total(self, version: int, encoding: str = 'json') -> bytes:
+        body = self.body()
+        request = {
+            'request_type': int(self.__TYPE__),
+            'body': body if version > LEGACY_PROTOCOL_VERSION else json.dumps(body)
+        }
+        if encoding == 'msgpack':
+            return msgpack.packb(request, use_bin_type=True)
+        return json.dumps(request).encode(MESSAGE_ENCODING)
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> Any:
+        return None
+
+
+class IPCSendLedMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.LED
+    _MINIMUM_COLOR = 0
+    _MAXIMUM_COLOR = 100
+
+    def __init__(self, rgb: Tuple[int, int, int]) -> None:
+        for val in rgb:
+            if not self._MINIMUM_COLOR <= val <= self._MAXIMUM_COLOR:
+                raise ValueError('Colors must be 0 <= color <= 100')
+        self._rgb = rgb
+
+    def body(self) -> Dict[str, Any]:
+        return {'r': self._rgb[0], 'g': self._rgb[1], 'b': self._rgb[2]}
+
+
+class IPCSendVelocityMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.VEL
+    _MINIMUM_VEL = -100
+    _MAXIMUM_VEL = 100
+
+    def __init__(self, velocities: Tuple[int, int]) -> None:
+        for val in velocities:
+            if not self._MINIMUM_VEL <= val <= self._MAXIMUM_VEL:
+                raise ValueError('Velocities must be -100 <= velocity <= 100')
+        self._vels = velocities
+
+    def body(self) -> Dict[str, Any]:
+        return {'l': self._vels[0], 'r': self._vels[1]}
+
+
+class IPCSendPosRequestMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.POS
+
+    def body(self) -> Dict[str, Any]:
+        return {}
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> Tuple[float, float, float]:
+        body = response.body
+        return (body['x'], body['y'], body['theta'])
+
+
+class IPCSendMsgMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.MSG_SEND
+
+    def __init__(self, msg: str) -> None:
+        self._msg = msg[:NET_MSG_LEN]
+
+    def body(self) -> Dict[str, Any]:
+        return {'msg': self._msg}
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> bool:
+        return True
+
+
+class IPCRecvMsgMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.MSG_RECV
+
+    def __init__(self, clear: bool) -> None:
+        self._clear = bool(clear)
+
+    def body(self) -> Dict[str, Any]:
+        return {'clear': self._clear}
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> List[str]:
+        body = response.body
+        return list(body['messages'])
+
+
+class IPCLogMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.LOG
+
+    def __init__(self, level: int, message: str) -> None:
+        self._level = max(0, min(int(level), 255))
+        self._message = trim_utf8(message, LOG_MESSAGE_MAX_LEN)
+
+    def body(self) -> Dict[str, Any]:
+        return {'level': self._level, 'message': self._message}
+
+
+class IPCStatusRequestMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.STATUS
+
+    def body(self) -> Dict[str, Any]:
+        return {}
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> Dict[str, bool]:
+        body = response.body
+        return {
+            'motors_locked': bool(body['motors_locked']),
+            'watchdog_tripped': bool(body['watchdog_tripped']),
+        }
+
+
+class IPCStatsRequestMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.STATS
+
+    def body(self) -> Dict[str, Any]:
+        return {}
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> Dict[str, Any]:
+        return response.body
+
+
+class IPCHandshakeMessage(IPCMessage):
+    __TYPE__ = IPCMessageType.HANDSHAKE
+
+    def body(self) -> Dict[str, Any]:
+        return {'version': PROTOCOL_VERSION, 'encodings': ENCODINGS}
+
+    @staticmethod
+    def unpack_response(response: 'IPCResponse') -> Dict[str, Any]:
+        return response.body
+
+
+class IPCStatus(IntEnum):
+    SUCCESS = 0
+    INVALID_ENCODING = 1
+    INVALID_REQUEST_HEAD = 2
+    INVALID_REQUEST_BODY = 3
+    INVALID_REQUEST_ARGS = 4
+    INCOMPATIBLE_VERSION = 5
+
+
+class IPCResponse:
+    def __init__(self, as_bytes: bytes, encoding: str = 'json') -> None:
+        self._data = as_bytes
+        self._encoding = encoding
+        self.__deserialized: Optional[Dict[str, Any]] = None
+
+    @property
+    def deserialized(self) -> Dict[str, Any]:
+        if self.__deserialized is not None:
+            return self.__deserialized
+
+        if self._encoding == 'msgpack':
+            result = msgpack.unpackb(self._data, raw=False)
+        else:
+            result = json.loads(self._data)
+        if result.get('status') is None:
+            raise ValueError('The Response from Cocos does not contain a '
+                             'status')
+        result['status'] = IPCStatus(result['status'])
+        self.__deserialized = result
+        return self.__deserialized
+
+    @property
+    def body(self) -> Dict[str, Any]:
+        """The body of the response, which legacy versions of the protocol
+        encode into a string."""
+        body = self.deserialized['body']
+        return json.loads(body) if isinstance(body, str) else body
+
+
+class IPCError(Exception):
+    pass
+
+
+class IPCInvalidResponse(IPCError):
+    def __init__(self, message: str, status: IPCStatus) -> None:
+        super().__init__(message)
+        self.status = status
+
+
+class IPCIncompatibleVersion(IPCInvalidResponse):
+    """Raised when cocos does not speak the protocol version of the API."""
+
+
+class IPCMessager:
+    # The maximum acceptable timeout in ms before a no-response is asserted.
+    __RESPONSE_TIMEOUT = 3
+    # The handshake is sent while cocos may still be starting up.
+    __HANDSHAKE_TIMEOUT = 1000
+
+    def __init__(self, tx_addr: str) -> None:
+        self._tx_addr = tx_addr
+        self._tx_ctx = zmq.Context()
+        self._tx_sock = self._tx_ctx.socket(zmq.REQ)
+        self._version = LEGACY_PROTOCOL_VERSION
+        self._encoding = 'json'
+        # Whether the handshake must be sent again before the next message.
+        # Cocos switches to the negotiated encoding once it replied to the
+        # handshake, so a lost reply leaves the API speaking the wrong one.
+        self._handshake_pending = False
+
+    def begin(self) -> None:
+        self._tx_sock.connect(self._tx_addr)
+
+    def _restart(self) -> None:
+        self._tx_sock.setsockopt(zmq.LINGER, 0)
+        self._tx_sock.close()
+        self._tx_sock = self._tx_ctx.socket(zmq.REQ)
+        self._tx_sock.connect(self._tx_addr)
+        # The lost reply may have been the one to a handshake.
+        self._handshake_pending = True
+
+    def handshake(self,
+                  timeout: Optional[int] = None) -> Optional[Dict[str, Any]]:
+        """Declares the protocol version and the encodings of the API to cocos,
+        returning the capabilities of cocos. Later messages are sent in the
+        encoding picked by cocos. Returns None if cocos did not answer or
+        predates the handshake, in which case the legacy version is spoken.
+        If cocos did not answer, the handshake is sent again before the next
+        message.
+
+        Raises:
+            IPCIncompatibleVersion: If cocos does not speak PROTOCOL_VERSION.
+        """
+        if timeout is None:
+            timeout = self.__HANDSHAKE_TIMEOUT
+        self._handshake_pending = False
+        try:
+            # Cocos accepts a handshake in JSON and the legacy version at any
+            # time, whatever was negotiated before.
+            capabilities = self._tx(IPCHandshakeMessage(), timeout,
+                                    LEGACY_PROTOCOL_VERSION, 'json')
+        except IPCInvalidResponse as err:
+            if err.status == IPCStatus.INVALID_REQUEST_HEAD:
+                return None
+            raise
+        if capabilities is not None:
+            self._version = PROTOCOL_VERSION
+            self._encoding = capabilities.get('encoding', 'json')
+        return capabilities
+
+    def tx(self, message: IPCMessage,
+           timeout: Optional[int] = None) -> Any:
+        if timeout is None:
+            timeout = self.__RESPONSE_TIMEOUT
+        if self._handshake_pending:
+            self.handshake(timeout)
+        try:
+            return self._tx(message, timeout, self._version, self._encoding)
+        except IPCInvalidResponse as err:
+            if err.status != IPCStatus.INVALID_ENCODING:
+                raise
+            # Cocos negotiated another encoding in a handshake whose reply was
+            # lost. Negotiate again and retry once.
+            self.handshake(timeout)
+            return self._tx(message, timeout, self._version, self._encoding)
+
+    def _tx(self, message: IPCMessage, timeout: int, version: int,
+            encoding: str) -> Any:
+        try:
+            # Send a message
+            self._tx_sock.send(message.serialize_total(version, encoding))
+
+            # Wait for response.
+            if not self._tx_sock.poll(timeout):
+                raise zmq.ZMQError(zmq.EAGAIN,
+                                   'Timed out waiting for response')
+            response = IPCResponse(self._tx_sock.recv(), encoding)
+
+            # Ensure that the status is successful.
+            status = response.deserialized['status']
+            if status == IPCStatus.INCOMPATIBLE_VERSION:
+                raise IPCIncompatibleVersion(response.body['message'], status)
+            if status != IPCStatus.SUCCESS:
+                raise IPCInvalidResponse(response.body['message'], status)
+            return message.unpack_response(response)
+        except zmq.ZMQError:
+            # TODO: Warn user
+            # Error ocurred, so the best we can do is recreate the socket in
+            # hopes it will work this time around.
+            self._restart()
+            return None
+
+
+class CocosCommunicator:
+    def __init__(self, tx_addr: str) -> None:
+        self._messager = IPCMessager(tx_addr)
+        self._capabilities: Optional[Dict[str, Any]] = None
+
+    def begin(self) -> None:
+        """Connects to cocos and negotiates the protocol version.
+
+        Raises:
+            IPCIncompatibleVersion: If cocos does not speak the protocol
+                version of this API.
+        """
+        self._messager.begin()
+        self._capabilities = self._messager.handshake()
+
+    @property
+    def capabilities(self) -> Optional[Dict[str, Any]]:
+        """The protocol versions, robot id, request types and limits reported
+        by cocos, or None if cocos predates the handshake."""
+        return self._capabilities
+
+    def send_led(self, rgb: Tuple[int, int, int]) -> None:
+        """Sends the LED update message.
+
+        Raises:
+            ValueError: Upon the RGB values being invalid.
+        """
+        self._messager.tx(IPCSendLedMessage(rgb))
+
+    def send_vel(self, velocities: Tuple[int, int]) -> None:
+        """Sends the velocity update message.
+
+        Raises:
+            ValueError: Upon the velocity values being invalid.
+        """
+        self._messager.tx(IPCSendVelocityMessage(velocities))
+
+    def send_get_position(self) -> Optional[Tuple[float, float, float]]:
+        """Sends a position request, returning the data."""
+        return self._messager.tx(IPCSendPosRequestMessage())
+
+    def send_msg(self, msg: str) -> bool:
+        """Broadcasts a message to the other coachbots, returning whether it
+        was queued."""
+        return bool(self._messager.tx(IPCSendMsgMessage(msg)))
+
+    def recv_msg(self, clear: bool) -> List[str]:
+        """Reads the messages received from other coachbots."""
+        return self._messager.tx(IPCRecvMsgMessage(clear)) or []
+
+    def send_log(self, level: int, msg: str) -> None:
+        """Emits a log record through cocos."""
+        self._messager.tx(IPCLogMessage(level, msg))
+
+    def send_get_status(self) -> Optional[Dict[str, bool]]:
+        """Queries the status of the motion output."""
+        return self._messager.tx(IPCStatusRequestMessage())
+
+    def send_get_stats(self) -> Optional[Dict[str, Any]]:
+        """Queries the timing statistics of the periodic tasks of cocos."""
+        return self._messager.tx(IPCStatsRequestMessage())
+
+
+class IPCLogHandler(logging.Handler):
+    """A logging handler that forwards records to cocos, which logs them
+    tagged with the robot id."""
+
+    def __init__(self, communicator: CocosCommunicator) -> None:
+        super().__init__()
+        self._cocos = communicator
+
+    def emit(self, record: logging.LogRecord) -> None:
+        try:
+            self._cocos.send_log(record.levelno, self.format(record))
+        except Exception:  # pylint: disable=broad-except
+            self.handleError(record)
@@ -0,0 +1,163 @@
+"""Tests the IPC messager against a fake cocos, which needs neither a ZMQ
+socket nor a running cocos. Run from the repository root with
+``python3 -m unittest discover cocos_py3/tests``.
+"""
+
+import json
+import sys
+import types
+import unittest
+from typing import Any, Dict, List, Optional
+
+
+class FakeCocos:
+    """Answers every request with the next of the given replies. A reply of
+    None is lost, as if the socket had failed."""
+
+    def __init__(self, replies: List[Optional[Dict[str, Any]]]) -> None:
+        self.replies = list(replies)
+        self.requests: List[Dict[str, Any]] = []
+
+
+class FakeSocket:
+    def __init__(self, cocos: FakeCocos) -> None:
+        self._cocos = cocos
+        self._reply: Optional[Dict[str, Any]] = None
+
+    def connect(self, addr: str) -> None:
+        pass
+
+    def setsockopt(self, option: int, value: int) -> None:
+        pass
+
+    def close(self) -> None:
+        pass
+
+    def send(self, data: bytes) -> None:
+        self._cocos.requests.append(json.loads(data))
+        self._reply = self._cocos.replies.pop(0)
+
+    def poll(self, timeout: int) -> bool:
+        return self._reply is not None
+
+    def recv(self) -> bytes:
+        return json.dumps(self._reply).encode('ascii')
+
+
+class FakeZMQError(Exception):
+    def __init__(self, errno: int, message: str) -> None:
+        super().__init__(message)
+        self.errno = errno
+
+
+FAKE_ZMQ = types.ModuleType('zmq')
+FAKE_ZMQ.REQ = 3
+FAKE_ZMQ.LINGER = 17
+FAKE_ZMQ.EAGAIN = 11
+FAKE_ZMQ.ZMQError = FakeZMQError
+sys.modules['zmq'] = FAKE_ZMQ
+
+# pylint: disable=wrong-import-position
+from cocos_py3.cocos import (IPCMessageType, IPCLogMessage, IPCMessager,
+                             IPCSendPosRequestMessage, IPCStatus,
+                             LEGACY_PROTOCOL_VERSION, LOG_MESSAGE_MAX_LEN,
+                             PROTOCOL_VERSION)
+
+CAPABILITIES = {
+    'status': IPCStatus.SUCCESS,
+    'body': {'version': PROTOCOL_VERSION,
+             'min_version': LEGACY_PROTOCOL_VERSION, 'encoding': 'json'},
+}
+POSITION = {
+    'status': IPCStatus.SUCCESS,
+    'body': {'x': 1.0, 'y': 2.0, 'theta': 0.5},
+}
+INVALID_ENCODING = {
+    'status': IPCStatus.INVALID_ENCODING,
+    'body': {'message': 'received a request in Json, but MessagePack was '
+                        'negotiated'},
+}
+
+
+def connect(cocos: FakeCocos) -> IPCMessager:
+    FAKE_ZMQ.Context = lambda: types.SimpleNamespace(
+        socket=lambda kind: FakeSocket(cocos))
+    messager = IPCMessager('ipc:///tmp/cocos-api')
+    messager.begin()
+    return messager
+
+
+def request_types(cocos: FakeCocos) -> List[int]:
+    return [request['request_type'] for request in cocos.requests]
+
+
+class IPCMessagerTest(unittest.TestCase):
+    def test_a_lost_handshake_reply_is_followed_by_another_handshake(self):
+        cocos = FakeCocos([None, CAPABILITIES, POSITION])
+        messager = connect(cocos)
+
+        self.assertIsNone(messager.handshake())
+        self.assertEqual(messager.tx(IPCSendPosRequestMessage()),
+                         (1.0, 2.0, 0.5))
+
+        self.assertEqual(request_types(cocos), [IPCMessageType.HANDSHAKE,
+                                                IPCMessageType.HANDSHAKE,
+                                                IPCMessageType.POS])
+        # The request is sent in the negotiated version, with an object body.
+        self.assertEqual(cocos.requests[2]['body'], {})
+
+    def test_a_lost_reply_is_followed_by_another_handshake(self):
+        cocos = FakeCocos([CAPABILITIES, None, CAPABILITIES, POSITION])
+        messager = connect(cocos)
+        messager.handshake()
+
+        self.assertIsNone(messager.tx(IPCSendPosRequestMessage()))
+        self.assertEqual(messager.tx(IPCSendPosRequestMessage()),
+                         (1.0, 2.0, 0.5))
+
+        self.assertEqual(request_types(cocos), [IPCMessageType.HANDSHAKE,
+                                                IPCMessageType.POS,
+                                                IPCMessageType.HANDSHAKE,
+                                                IPCMessageType.POS])
+
+    def test_an_invalid_encoding_renegotiates_and_retries(self):
+        cocos = FakeCocos([INVALID_ENCODING, CAPABILITIES, POSITION])
+        messager = connect(cocos)
+
+        self.assertEqual(messager.tx(IPCSendPosRequestMessage()),
+                         (1.0, 2.0, 0.5))
+
+        self.assertEqual(request_types(cocos), [IPCMessageType.POS,
+                                                IPCMessageType.HANDSHAKE,
+                                                IPCMessageType.POS])
+
+    def test_the_handshake_is_sent_in_the_legacy_version(self):
+        cocos = FakeCocos([CAPABILITIES, None, CAPABILITIES])
+        messager = connect(cocos)
+        messager.handshake()
+        messager.tx(IPCSendPosRequestMessage())
+        messager.handshake()
+
+        # Cocos accepts string bodies in JSON at any time.
+        self.assertIsInstance(cocos.requests[0]['body'], str)
+        self.assertIsInstance(cocos.requests[2]['body'], str)
+
+
+class IPCLogMessageTest(unittest.TestCase):
+    def test_the_message_is_trimmed_to_bytes(self):
+        # Three bytes each, so the limit falls within a character.
+        message = IPCLogMessage(20, '\u20ac' * LOG_MESSAGE_MAX_LEN)
+        body = json.loads(message.serialize_total(PROTOCOL_VERSION))['body']
+
+        self.assertEqual(body['message'],
+                         '\u20ac' * (LOG_MESSAGE_MAX_LEN // 3))
+
+    def test_a_short_message_is_kept(self):
+        message = IPCLogMessage(20, 'caf\u00e9')
+        body = json.loads(message.serialize_total(PROTOCOL_VERSION))['body']
+
+        self.assertEqual(body['message'], 'caf\u00e9')
+
+
+if __name__ == '__main__':
+    unittest.main()
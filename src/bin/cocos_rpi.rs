@@ -1,10 +1,17 @@
-use cocos::config::APP_CONFIG;
+use cocos::cli::{load_config, read_user_script, ConfigArgs, RobotArgs};
+use cocos::config::AppConfig;
 use cocos::controllers::master::MasterController;
+use cocos::controllers::shutdown::{on_shutdown_signal, signal_exit_code};
+use cocos::io::interface::{gpio::DrivesGpio, pwm::DrivesPwm};
+use cocos::io::linux::{gpio::CdevGpioDriver, pwm::SysfsPwmDriver};
 use cocos::io::rpi::gpio::RpiGpioDriver;
+use cocos::io::rpi::pins::RpiPinRegistry;
 use cocos::io::rpi::pwm::RpiPwmDriver;
 use cocos::io::rpi::uart::RpiUartDriver;
-use clap::Parser;
-use std::{io::{stdin, Read}, fs::File};
+use cocos::io::udp::UdpBroadcastDriver;
+use cocos::io::IoBackend;
+use clap::{Parser, ValueEnum};
+use std::{fmt::Display, process};
 
 #[derive(Parser, Debug)]
 struct CliArgs {
@@ -18,41 +25,114 @@ struct CliArgs {
     /// cocos -u=my_user_script.py # Loads my_user_script.py as the user script.
     #[arg(short, long)]
     user_script: Option<String>,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    #[command(flatten)]
+    robot: RobotArgs,
+
+    /// The hardware backend to drive GPIO and PWM with. Overrides
+    /// `io.backend` of the configuration.
+    #[arg(long, value_enum)]
+    io: Option<Backend>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Backend {
+    /// The Raspberry Pi peripherals.
+    Rpi,
+    /// The Linux GPIO character device and sysfs PWM, for other boards.
+    Linux,
+}
+
+/// Logs an error that prevents cocos from starting and exits.
+fn fatal(what: &str, err: impl Display) -> ! {
+    log::error!("Could not {}. {}", what, err);
+    process::exit(1);
 }
 
 fn main() {
     env_logger::init();
     let args = CliArgs::parse();
 
+    let app_cfg = load_config(&args.config, |app_cfg| {
+        args.robot.apply(app_cfg);
+        if let Some(io) = args.io {
+            app_cfg.io.backend = match io {
+                Backend::Rpi => IoBackend::Rpi,
+                Backend::Linux => IoBackend::Linux,
+            };
+        }
+    });
+
+    let exit_code = match app_cfg.io.backend {
+        IoBackend::Rpi => {
+            let pins = RpiPinRegistry::new()
+                .unwrap_or_else(|err| fatal("open the GPIO peripheral", err));
+            run(&app_cfg, args.user_script, RpiGpioDriver::new(pins.clone()),
+                RpiPwmDriver::new(pins))
+        }
+        IoBackend::Linux => {
+            let gpio_driver = CdevGpioDriver::new(&app_cfg.io.gpio_chip)
+                .unwrap_or_else(|err| fatal("open the GPIO chip", err));
+            let pwm_driver = SysfsPwmDriver::new(&app_cfg.io.pwm_chip,
+                                                 app_cfg.io.pwm_channels.clone())
+                .unwrap_or_else(|err| fatal("open the PWM chip", err));
+            run(&app_cfg, args.user_script, gpio_driver, pwm_driver)
+        }
+    };
+    process::exit(exit_code);
+}
+
+/// Runs cocos on the given GPIO and PWM drivers until it shuts down and
+/// returns the exit code.
+fn run<GpioDriver, PwmDriver>(
+    app_cfg: &AppConfig,
+    user_script: Option<String>,
+    gpio_driver: GpioDriver,
+    pwm_driver: PwmDriver,
+) -> i32
+where
+    GpioDriver: DrivesGpio + Send + 'static,
+    PwmDriver: DrivesPwm + Send + 'static,
+{
+    // Both backends read Nucifera from the primary UART of the Pi.
+    let uart_driver = RpiUartDriver::new(app_cfg.nucifera.to_uart_descriptor())
+        .unwrap_or_else(|err| fatal("open the UART driver", err));
+    let net_driver = UdpBroadcastDriver::new(app_cfg.net)
+        .unwrap_or_else(|err| fatal("bind the network socket", err));
+
     let mut master_controller = MasterController::new(
-        &APP_CONFIG,
-        RpiGpioDriver::new(),
-        RpiPwmDriver::new(),
-        RpiUartDriver::new(APP_CONFIG.nucifera.to_uart_descriptor())
+        app_cfg,
+        gpio_driver,
+        pwm_driver,
+        uart_driver,
+        net_driver,
     );
 
-    match args.user_script {
-        None => {
-            master_controller.run();
+    // The first signal shuts cocos down gracefully, a second one exits
+    // immediately.
+    let shutdown = master_controller.shutdown_handle();
+    if let Err(err) = on_shutdown_signal(move |signal| {
+        if shutdown.request(signal) {
+            process::exit(signal_exit_code(signal));
         }
+        log::info!("Shutting down.");
+    }) {
+        log::error!("Could not install the signal handler. {:}", err);
+    }
+
+    let exit_code = match user_script {
+        None => master_controller.run(),
         Some(script) => {
-            let mut user_script = String::new();
-            if script == String::from("--") {
-                stdin().read_to_string(&mut user_script).unwrap();
-            } else {
-                match File::open(script) {
-                    Ok(mut file) => {
-                        if let Err(err) = file.read_to_string(&mut user_script) {
-                            log::error!("Could not read user_script. {:}", err);
-                        }
-                    }
-                    Err(err) => {
-                        log::error!("Could not open user_script. {:}", err);
-                    }
-                }
-            }
+            let user_script = read_user_script(&script)
+                .unwrap_or_else(|err| fatal("read user_script", err));
             master_controller.run_with_script(user_script)
         }
-    }
+    };
 
+    // Drop the drivers before exiting so that they can release the hardware.
+    drop(master_controller);
+    exit_code
 }
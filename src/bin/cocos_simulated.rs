@@ -1,12 +1,30 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::{fs::File, io::{stdin, Read}, process, time::Instant};
+use std::{
+    net::Ipv4Addr,
+    process,
+    thread,
+    time::Instant,
+};
 
-use cocos::config::APP_CONFIG;
+use cocos::cli::{load_config, read_user_script, ConfigArgs, RobotArgs, TraceEncoding};
 use cocos::controllers::master::MasterController;
-use env_logger;
-use cocos::io::sim_print::{gpio::PrintGpioDriver, pwm::PrintPwmDriver, uart::PrintUartDriver};
+use cocos::controllers::scheduler::{RealTimeClock, Scheduler};
+use cocos::controllers::shutdown::{on_shutdown_signal, signal_exit_code};
+use cocos::io::interface::net::NetDescriptor;
+use cocos::io::sim_print::{
+    gpio::PrintGpioDriver,
+    pwm::PrintPwmDriver,
+    sink::TraceSink,
+    trace::TraceFormat,
+    uart::PrintUartDriver,
+};
+use cocos::io::udp::UdpBroadcastDriver;
+use cocos::sim::{
+    physics::World,
+    robot::{PhysicsTask, SimRobot},
+};
 use clap::Parser;
 
 #[derive(Parser, Debug)]
@@ -22,9 +40,25 @@ struct CliArgs {
     #[arg(short, long)]
     user_script: Option<String>,
 
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    #[command(flatten)]
+    robot: RobotArgs,
+
     /// Defines the GPIO IO communication file. Used exclusively with the SIL.
     #[arg(short, long)]
-    gpio_file: String
+    gpio_file: String,
+
+    /// The encoding of the GPIO IO communication file.
+    #[arg(long, value_enum, default_value = "csv")]
+    trace_format: TraceEncoding,
+
+    /// A file or FIFO whose bytes are replayed as the data received on the
+    /// Nucifera UART. If not given, the position is reported by the physics
+    /// simulation instead.
+    #[arg(long)]
+    uart_file: Option<String>,
 }
 
 lazy_static! {
@@ -35,38 +69,110 @@ fn main() {
     env_logger::init();
     let args = CliArgs::parse();
 
-    // TODO: Yikes, remove this... Killing the point of rust...
-    let gpio_file1 = File::create(String::clone(&args.gpio_file)).unwrap();
-    let gpio_file2 = File::create(args.gpio_file).unwrap();
+    let app_cfg = load_config(&args.config, |app_cfg| args.robot.apply(app_cfg));
 
-    let mut master_controller = MasterController::new(
-        &APP_CONFIG,
-        PrintGpioDriver::new(gpio_file1, *BEGIN_TIME),
-        PrintPwmDriver::new(gpio_file2, *BEGIN_TIME),
-        PrintUartDriver::new()
-    );
+    let trace_format = TraceFormat::from(args.trace_format);
+    let trace = match TraceSink::create(&args.gpio_file, trace_format, *BEGIN_TIME) {
+        Ok(trace) => trace,
+        Err(err) => {
+            log::error!("Could not create gpio_file. {:}", err);
+            process::exit(1);
+        }
+    };
 
-    match args.user_script {
-        None => { master_controller.run() },
-        Some(script) => {
-            let mut user_script = String::new();
-            if script == String::from("--") {
-                stdin().read_to_string(&mut user_script).unwrap();
-            } else {
-                match File::open(script) {
-                    Ok(mut file) => {
-                        if let Err(err) = file.read_to_string(&mut user_script) {
-                            log::error!("Could not read user_script. {:}", err);
-                            process::exit(1);
-                        }
-                    }
-                    Err(err) => {
-                        log::error!("Could not open user_script. {:}", err);
-                        process::exit(1);
-                    }
+    let simulate_physics = args.uart_file.is_none();
+    let uart_driver = match args.uart_file {
+        None => PrintUartDriver::new(),
+        Some(uart_file) => {
+            match PrintUartDriver::from_path(&uart_file, app_cfg.nucifera.to_uart_descriptor()) {
+                Ok(driver) => driver,
+                Err(err) => {
+                    log::error!("Could not open uart_file. {:}", err);
+                    process::exit(1);
                 }
             }
-            master_controller.run_with_script(user_script)
         }
+    }.traced(trace.clone());
+
+    // Simulated coachbots talk to each other over the loopback interface so
+    // that several instances can run on one host.
+    let net_driver = match UdpBroadcastDriver::new(NetDescriptor {
+        broadcast_addr: Ipv4Addr::new(127, 255, 255, 255),
+        ..app_cfg.net
+    }) {
+        Ok(driver) => driver,
+        Err(err) => {
+            log::error!("Could not bind the network socket. {:}", err);
+            process::exit(1);
+        }
+    };
+
+    let gpio_driver = PrintGpioDriver::new(trace.clone());
+    let pwm_driver = PrintPwmDriver::new(trace.clone());
+    let physics_task = if simulate_physics {
+        let mut world = World::new(app_cfg.sim);
+        world.add_robot(app_cfg.sim.start.into());
+        let robot = SimRobot::new(app_cfg.robot_id, app_cfg.mot_left, app_cfg.mot_right,
+                                  gpio_driver.inputs(), pwm_driver.outputs(),
+                                  uart_driver.feed());
+        Some(PhysicsTask::new(world, vec![robot]))
+    } else {
+        None
+    };
+
+    let mut master_controller = MasterController::new(
+        &app_cfg,
+        gpio_driver,
+        pwm_driver,
+        uart_driver,
+        net_driver
+    );
+
+    // The first signal shuts cocos down gracefully, a second one exits
+    // immediately.
+    let shutdown = master_controller.shutdown_handle();
+    let signal_trace = trace.clone();
+    if let Err(err) = on_shutdown_signal(move |signal| {
+        if shutdown.request(signal) {
+            let _ = signal_trace.flush();
+            process::exit(signal_exit_code(signal));
+        }
+        log::info!("Shutting down.");
+    }) {
+        log::error!("Could not install the signal handler. {:}", err);
+    }
+
+    // The physics runs on its own scheduler so that it keeps moving the robot
+    // while cocos is busy.
+    let physics_thread = physics_task.map(|physics_task| {
+        let shutdown = master_controller.shutdown_flag();
+        thread::spawn(move || {
+            let mut scheduler = Scheduler::new(RealTimeClock::new());
+            scheduler.add_tick10("physics", physics_task);
+            scheduler.run_until(&shutdown);
+        })
+    });
+
+    let exit_code = match args.user_script {
+        None => master_controller.run(),
+        Some(script) => match read_user_script(&script) {
+            Ok(user_script) => master_controller.run_with_script(user_script),
+            Err(err) => {
+                log::error!("Could not read user_script. {:}", err);
+                process::exit(1);
+            }
+        },
+    };
+
+    // The master controller sets the shutdown flag once it stops.
+    if let Some(physics_thread) = physics_thread {
+        let _ = physics_thread.join();
+    }
+
+    // Drop the drivers before exiting so that they can release the hardware.
+    drop(master_controller);
+    if let Err(err) = trace.flush() {
+        log::error!("Could not write gpio_file. {:}", err);
     }
+    process::exit(exit_code);
 }
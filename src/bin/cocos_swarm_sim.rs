@@ -0,0 +1,230 @@
+use std::{
+    fs,
+    io,
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+use clap::Parser;
+use cocos::cli::{load_config, read_user_script, ConfigArgs, TraceEncoding};
+use cocos::controllers::master::MasterController;
+use cocos::controllers::scheduler::{RealTimeClock, Scheduler};
+use cocos::controllers::shutdown::{on_shutdown_signal, signal_exit_code, ShutdownHandle};
+use cocos::io::sim_print::{
+    gpio::PrintGpioDriver,
+    pwm::PrintPwmDriver,
+    sink::TraceSink,
+    trace::TraceFormat,
+    uart::PrintUartDriver,
+};
+use cocos::sim::{
+    network::SimNetwork,
+    physics::{grid_poses, World},
+    robot::{PhysicsTask, SimRobot},
+    trajectory::TrajectoryLog,
+};
+
+/// Runs a swarm of simulated coachbots in one process. The coachbots share
+/// one configuration, one physics world and one broadcast network, and each
+/// runs its own copy of the user script.
+#[derive(Parser, Debug)]
+struct CliArgs {
+    /// The user script every coachbot runs. You may specify stdin via `--`.
+    #[arg(short, long)]
+    user_script: Option<String>,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// The number of coachbots.
+    #[arg(short = 'n', long, default_value_t = 4)]
+    robots: u32,
+
+    /// The id of the first coachbot, the others are numbered consecutively.
+    /// Defaults to the `robot_id` of the configuration.
+    #[arg(long)]
+    first_id: Option<u32>,
+
+    /// The distance between neighbouring coachbots on the start grid, which
+    /// is centered at `sim.start`.
+    #[arg(long, default_value_t = 0.25)]
+    spacing_m: f32,
+
+    /// The directory the IPC sockets of the coachbots are created in. The
+    /// coachbot with id N uses `cocos-api-N`.
+    #[arg(long, default_value = "/tmp")]
+    ipc_dir: String,
+
+    /// The CSV file the poses of all coachbots are logged to.
+    #[arg(short, long)]
+    trajectory_file: String,
+
+    /// A directory to write the GPIO trace of every coachbot to, as
+    /// `robot-N.trace`. No traces are written if not given.
+    #[arg(long)]
+    trace_dir: Option<String>,
+
+    /// The encoding of the GPIO traces.
+    #[arg(long, value_enum, default_value = "csv")]
+    trace_format: TraceEncoding,
+}
+
+/// Creates the GPIO trace of the coachbot `robot_id`.
+fn create_trace(trace_dir: &Option<String>, robot_id: u32, format: TraceFormat,
+                begin_time: Instant) -> io::Result<TraceSink> {
+    match trace_dir {
+        None => TraceSink::new(io::sink(), format, begin_time),
+        Some(trace_dir) => {
+            let path = Path::new(trace_dir).join(format!("robot-{}.trace", robot_id));
+            TraceSink::create(path, format, begin_time)
+        }
+    }
+}
+
+fn main() {
+    let begin_time = Instant::now();
+    env_logger::init();
+    let args = CliArgs::parse();
+
+    let mut app_cfg = load_config(&args.config, |_| {});
+    if args.robots == 0 {
+        log::error!("The swarm needs at least one coachbot.");
+        process::exit(1);
+    }
+    if !(args.spacing_m.is_finite() && args.spacing_m > 0.0) {
+        log::error!("The spacing must be positive, not {}.", args.spacing_m);
+        process::exit(1);
+    }
+
+    let user_script = args.user_script.as_deref().map(|script| {
+        read_user_script(script).unwrap_or_else(|err| {
+            log::error!("Could not read user_script. {:}", err);
+            process::exit(1);
+        })
+    });
+
+    let trace_format = TraceFormat::from(args.trace_format);
+    if let Some(trace_dir) = &args.trace_dir {
+        if let Err(err) = fs::create_dir_all(trace_dir) {
+            log::error!("Could not create trace_dir. {:}", err);
+            process::exit(1);
+        }
+    }
+    let trajectory = match TrajectoryLog::create(&args.trajectory_file) {
+        Ok(trajectory) => trajectory,
+        Err(err) => {
+            log::error!("Could not create trajectory_file. {:}", err);
+            process::exit(1);
+        }
+    };
+
+    let first_id = args.first_id.unwrap_or(app_cfg.robot_id);
+    let mut world = World::new(app_cfg.sim);
+    let network = SimNetwork::new();
+    let mut robots = Vec::new();
+    let mut controllers = Vec::new();
+    let mut traces = Vec::new();
+    let poses = grid_poses(app_cfg.sim.start.into(), args.robots as usize, args.spacing_m);
+    for (robot_id, pose) in (first_id..).zip(poses) {
+        let trace = match create_trace(&args.trace_dir, robot_id, trace_format, begin_time) {
+            Ok(trace) => trace,
+            Err(err) => {
+                log::error!("Could not create the trace of robot {}. {:}", robot_id, err);
+                process::exit(1);
+            }
+        };
+        let gpio_driver = PrintGpioDriver::new(trace.clone());
+        let pwm_driver = PrintPwmDriver::new(trace.clone());
+        let uart_driver = PrintUartDriver::new().traced(trace.clone());
+
+        world.add_robot(pose);
+        robots.push(SimRobot::new(robot_id, app_cfg.mot_left, app_cfg.mot_right,
+                                  gpio_driver.inputs(), pwm_driver.outputs(),
+                                  uart_driver.feed()));
+
+        app_cfg.robot_id = robot_id;
+        app_cfg.api.endpoint = format!("ipc://{}/cocos-api-{}",
+                                       args.ipc_dir.trim_end_matches('/'), robot_id);
+        if let Err(err) = app_cfg.validate() {
+            log::error!("Could not configure robot {}. {}", robot_id, err);
+            process::exit(1);
+        }
+        controllers.push(MasterController::new(
+            &app_cfg,
+            gpio_driver,
+            pwm_driver,
+            uart_driver,
+            network.attach(),
+        ));
+        traces.push(trace);
+    }
+
+    // The first signal shuts all coachbots down gracefully, a second one
+    // exits immediately.
+    let shutdown_handles: Vec<ShutdownHandle> = controllers
+        .iter()
+        .map(|controller| controller.shutdown_handle())
+        .collect();
+    let signal_traces = traces.clone();
+    if let Err(err) = on_shutdown_signal(move |signal| {
+        let mut repeated = false;
+        for shutdown in &shutdown_handles {
+            repeated |= shutdown.request(signal);
+        }
+        if repeated {
+            for trace in &signal_traces {
+                let _ = trace.flush();
+            }
+            process::exit(signal_exit_code(signal));
+        }
+        log::info!("Shutting down.");
+    }) {
+        log::error!("Could not install the signal handler. {:}", err);
+    }
+
+    let physics_shutdown = Arc::new(AtomicBool::new(false));
+    let physics_thread = {
+        let physics_task = PhysicsTask::new(world, robots).logged(trajectory);
+        let shutdown = Arc::clone(&physics_shutdown);
+        thread::spawn(move || {
+            let mut scheduler = Scheduler::new(RealTimeClock::new());
+            scheduler.add_tick10("physics", physics_task);
+            scheduler.run_until(&shutdown);
+        })
+    };
+
+    let controller_threads: Vec<_> = controllers
+        .into_iter()
+        .map(|mut controller| {
+            let user_script = user_script.clone();
+            thread::spawn(move || match user_script {
+                None => controller.run(),
+                Some(user_script) => controller.run_with_script(user_script),
+            })
+        })
+        .collect();
+
+    // The swarm exits with the first non-zero exit code of its coachbots.
+    let mut exit_code = 0;
+    for controller_thread in controller_threads {
+        let code = controller_thread.join().unwrap_or(1);
+        if exit_code == 0 {
+            exit_code = code;
+        }
+    }
+
+    physics_shutdown.store(true, Ordering::Relaxed);
+    let _ = physics_thread.join();
+    for trace in traces {
+        if let Err(err) = trace.flush() {
+            log::error!("Could not write a trace. {:}", err);
+        }
+    }
+    process::exit(exit_code);
+}
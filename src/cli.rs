@@ -0,0 +1,142 @@
+//! This module holds the command line pieces shared by the cocos binaries.
+use std::{
+    fs,
+    io::{self, stdin, Read},
+    process,
+};
+
+use clap::{Args, ValueEnum};
+
+use crate::config::AppConfig;
+use crate::controllers::api::ApiDescriptor;
+use crate::io::sim_print::trace::TraceFormat;
+
+#[derive(Args, Debug)]
+/// The arguments selecting the configuration and overriding its `api`
+/// section, shared by all binaries.
+// Keeps the documentation above out of the help of the binaries.
+#[command(about = None, long_about = None)]
+pub struct ConfigArgs {
+    /// A TOML or JSON file overriding the default hardware configuration.
+    /// Files ending in `.json` are read as JSON, all others as TOML.
+    #[arg(short, long)]
+    pub config: Option<String>,
+
+    /// The python API runtime to launch the user script with. Overrides the
+    /// `api` section of the configuration.
+    #[arg(long, value_enum)]
+    pub api: Option<ApiRuntime>,
+
+    /// The interpreter executable to launch the API with, for example the
+    /// python of a virtualenv. Overrides the interpreter of `--api`.
+    #[arg(long)]
+    pub api_interpreter: Option<String>,
+}
+
+#[derive(Args, Debug)]
+/// The arguments overriding the configuration of a single robot.
+// Keeps the documentation above out of the help of the binaries.
+#[command(about = None, long_about = None)]
+pub struct RobotArgs {
+    /// The ZMQ endpoint the API communicates through, `ipc://<path>` or
+    /// `tcp://<host>:<port>`. Overrides `api.endpoint` of the configuration.
+    #[arg(long)]
+    pub api_endpoint: Option<String>,
+
+    /// The id of this robot. Overrides `robot_id` of the configuration.
+    #[arg(long)]
+    pub robot_id: Option<u32>,
+}
+
+impl RobotArgs {
+    /// Applies the overrides to `app_cfg`.
+    pub fn apply(&self, app_cfg: &mut AppConfig) {
+        if let Some(endpoint) = &self.api_endpoint {
+            app_cfg.api.endpoint = endpoint.clone();
+        }
+        if let Some(robot_id) = self.robot_id {
+            app_cfg.robot_id = robot_id;
+        }
+    }
+}
+
+/// Loads the configuration selected by `args` and applies its overrides,
+/// followed by those of `override_with`. The result must hold up to the same
+/// checks as the configuration file. Logs the error and exits if the
+/// configuration cannot be loaded or is invalid.
+pub fn load_config(args: &ConfigArgs, override_with: impl FnOnce(&mut AppConfig)) -> AppConfig {
+    let mut app_cfg = match &args.config {
+        None => AppConfig::default(),
+        Some(config) => match AppConfig::from_file(config) {
+            Ok(app_cfg) => app_cfg,
+            Err(err) => {
+                log::error!("Could not load config {}. {}", config, err);
+                process::exit(1);
+            }
+        },
+    };
+    if let Some(api) = args.api {
+        api.apply(&mut app_cfg.api);
+    }
+    if let Some(interpreter) = &args.api_interpreter {
+        app_cfg.api.interpreter = interpreter.clone();
+    }
+    override_with(&mut app_cfg);
+
+    if let Err(err) = app_cfg.validate() {
+        log::error!("Could not use the configuration. {}", err);
+        process::exit(1);
+    }
+    app_cfg
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+/// Enumerates the python API runtimes a user script can be launched with.
+pub enum ApiRuntime {
+    /// The legacy `cocos_py2` package run by `python2`.
+    Py2,
+    /// The `cocos_py3` package run by `python3`.
+    Py3,
+}
+
+impl ApiRuntime {
+    /// Points `api` at the interpreter and package of this runtime.
+    pub fn apply(self, api: &mut ApiDescriptor) {
+        let runtime = match self {
+            ApiRuntime::Py2 => ApiDescriptor::python2(),
+            ApiRuntime::Py3 => ApiDescriptor::python3(),
+        };
+        api.interpreter = runtime.interpreter;
+        api.package = runtime.package;
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+/// Enumerates the encodings a GPIO trace can be written in.
+pub enum TraceEncoding {
+    /// One record per line.
+    Csv,
+    /// A compact binary encoding of the same records.
+    Binary,
+}
+
+impl From<TraceEncoding> for TraceFormat {
+    fn from(encoding: TraceEncoding) -> Self {
+        match encoding {
+            TraceEncoding::Csv => TraceFormat::Csv,
+            TraceEncoding::Binary => TraceFormat::Binary,
+        }
+    }
+}
+
+/// Reads the user script at `path`, or from stdin until EOF if `path` is
+/// `--`.
+pub fn read_user_script(path: &str) -> io::Result<String> {
+    if path == "--" {
+        let mut user_script = String::new();
+        stdin().read_to_string(&mut user_script)?;
+        Ok(user_script)
+    } else {
+        fs::read_to_string(path)
+    }
+}
@@ -1,44 +1,464 @@
+use std::{cmp::Ordering, fmt, fs, io, net::Ipv4Addr, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uom::si::{f32::Frequency, frequency::hertz};
 
+use crate::controllers::{api::ApiDescriptor, watchdog::WatchdogDescriptor};
 use crate::drivers::{
     led_driver::LedDescriptor,
     motor_driver::MotorDescriptor,
     nucifera_driver::NuciferaDescriptor,
 };
-use crate::io::interface::uart::UartParity;
+use crate::sim::{physics::PhysicsDescriptor, robot::MAX_FRAME_RATE_HZ};
+use crate::io::{
+    interface::{net::NetDescriptor, uart::UartParity},
+    IoBackend,
+    IoDescriptor,
+};
+
+/// The highest BCM pin number exposed on the Raspberry Pi header.
+const MAX_PIN_BCM: u8 = 27;
 
+/// The longest path of a UNIX domain socket, as limited by `sun_path`.
+const MAX_IPC_PATH_LEN: usize = 107;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Represents the runtime configuration of cocos.
+///
+/// The configuration is loaded from a TOML or JSON file via
+/// [AppConfig::from_file]. Every section and every key is optional, missing
+/// values fall back to those of [AppConfig::default].
 pub struct AppConfig {
+    /// The id of this robot. Tags everything logged by the user script.
+    pub robot_id: u32,
     pub mot_left: MotorDescriptor,
     pub mot_right: MotorDescriptor,
     pub nucifera: NuciferaDescriptor,
     pub led: LedDescriptor,
+    pub net: NetDescriptor,
+    pub api: ApiDescriptor,
+    pub watchdog: WatchdogDescriptor,
+    /// The hardware backend of `cocos_rpi`.
+    pub io: IoDescriptor,
+    /// The physics simulation of `cocos_simulated`.
+    pub sim: PhysicsDescriptor,
+}
+
+#[derive(Debug)]
+/// Represents the errors that can occur while loading an [AppConfig].
+pub enum ConfigError {
+    /// Thrown when the configuration file cannot be read.
+    IO(io::Error),
+    /// Thrown when the configuration file is malformed, contains unknown keys
+    /// or values of the wrong type.
+    Parse(String),
+    /// Thrown when a value is outside of its permitted range.
+    OutOfRange { key: &'static str, value: String, expected: &'static str },
+    /// Thrown when two peripherals are assigned the same BCM pin.
+    PinConflict { pin_bcm: u8, first: &'static str, second: &'static str },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::IO(err) => write!(f, "could not read the configuration: {}", err),
+            ConfigError::Parse(msg) => write!(f, "invalid configuration: {}", msg),
+            ConfigError::OutOfRange { key, value, expected } => {
+                write!(f, "`{}` is {}, expected {}", key, value, expected)
+            }
+            ConfigError::PinConflict { pin_bcm, first, second } => {
+                write!(f, "BCM pin {} is used by both `{}` and `{}`", pin_bcm, first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            robot_id: 0,
+            mot_left: MotorDescriptor {
+                pin_in1: 19u8,
+                pin_in2: 16u8,
+                pin_pwm: 26u8,
+                pin_stdby: 20u8,
+                inverted: true,
+                pwm_frequency: Frequency::new::<hertz>(600f32)
+            },
+            mot_right: MotorDescriptor {
+                pin_in1: 5u8,
+                pin_in2: 6u8,
+                pin_pwm: 12u8,
+                pin_stdby: 20u8,
+                inverted: false,
+                pwm_frequency: Frequency::new::<hertz>(600f32)
+            },
+            led: LedDescriptor {
+                pin_r_bcm: 22u8,
+                pin_g_bcm: 23u8,
+                pin_b_bcm: 24u8,
+                frequency: Frequency::new::<hertz>(120f32)
+            },
+            nucifera: NuciferaDescriptor {
+                baud_rate: 19200u32,
+                parity: UartParity::Even,
+                data_bits: 8,
+                stop_bits: 1,
+                stale_timeout: Duration::from_millis(500)
+            },
+            net: NetDescriptor {
+                port: 47474u16,
+                broadcast_addr: Ipv4Addr::BROADCAST
+            },
+            api: ApiDescriptor::python2(),
+            watchdog: WatchdogDescriptor {
+                enabled: true,
+                timeout: Duration::from_millis(5000)
+            },
+            io: IoDescriptor::default(),
+            sim: PhysicsDescriptor::default(),
+        }
+    }
 }
 
-lazy_static! {
-    pub static ref APP_CONFIG: AppConfig = AppConfig {
-        mot_left: MotorDescriptor {
-            pin_in1: 19u8,
-            pin_in2: 16u8,
-            pin_pwm: 26u8,
-            pin_stdby: 20u8
-        },
-        mot_right: MotorDescriptor {
-            pin_in1: 5u8,
-            pin_in2: 6u8,
-            pin_pwm: 12u8,
-            pin_stdby: 20u8
-        },
-        led: LedDescriptor {
-            pin_r_bcm: 22u8,
-            pin_g_bcm: 23u8,
-            pin_b_bcm: 24u8,
-            frequency: Frequency::new::<hertz>(120f32)
-        },
-        nucifera: NuciferaDescriptor {
-            baud_rate: 19200u32,
-            parity: UartParity::Even,
-            data_bits: 8,
-            stop_bits: 1
-        },
-    };
+impl AppConfig {
+    /// Loads and validates the configuration at `path`. Files ending in
+    /// `.json` are parsed as JSON, all others as TOML.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(ConfigError::IO)?;
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json_str(&contents)
+        } else {
+            Self::from_toml_str(&contents)
+        }
+    }
+
+    /// Parses and validates a TOML configuration.
+    pub fn from_toml_str(contents: &str) -> Result<Self, ConfigError> {
+        let overrides: toml::Value = toml::from_str(contents)
+            .map_err(|err| ConfigError::Parse(err.to_string()))?;
+        Self::from_overrides(serde_json::to_value(overrides)
+            .map_err(|err| ConfigError::Parse(err.to_string()))?)
+    }
+
+    /// Parses and validates a JSON configuration.
+    pub fn from_json_str(contents: &str) -> Result<Self, ConfigError> {
+        Self::from_overrides(serde_json::from_str(contents)
+            .map_err(|err| ConfigError::Parse(err.to_string()))?)
+    }
+
+    /// Applies `overrides` on top of the default configuration.
+    fn from_overrides(overrides: Value) -> Result<Self, ConfigError> {
+        let mut merged = serde_json::to_value(AppConfig::default())
+            .map_err(|err| ConfigError::Parse(err.to_string()))?;
+        merge_values(&mut merged, overrides);
+
+        let config: AppConfig = serde_path_to_error::deserialize(merged)
+            .map_err(|err| ConfigError::Parse(format!("{}: {}", err.path(), err.inner())))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Ensures that all values are within range and that no two peripherals
+    /// share a BCM pin. The standby pin of both motors is deliberately shared
+    /// since both motors sit on the same driver chip.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let pins: [(&'static str, u8); 11] = [
+            ("mot_left.pin_in1", self.mot_left.pin_in1),
+            ("mot_left.pin_in2", self.mot_left.pin_in2),
+            ("mot_left.pin_pwm", self.mot_left.pin_pwm),
+            ("mot_left.pin_stdby", self.mot_left.pin_stdby),
+            ("mot_right.pin_in1", self.mot_right.pin_in1),
+            ("mot_right.pin_in2", self.mot_right.pin_in2),
+            ("mot_right.pin_pwm", self.mot_right.pin_pwm),
+            ("mot_right.pin_stdby", self.mot_right.pin_stdby),
+            ("led.pin_r_bcm", self.led.pin_r_bcm),
+            ("led.pin_g_bcm", self.led.pin_g_bcm),
+            ("led.pin_b_bcm", self.led.pin_b_bcm),
+        ];
+
+        for (i, &(first, pin_bcm)) in pins.iter().enumerate() {
+            // The Linux backend addresses line offsets, which may exceed the
+            // header of the Raspberry Pi.
+            if self.io.backend != IoBackend::Linux && pin_bcm > MAX_PIN_BCM {
+                return Err(ConfigError::OutOfRange {
+                    key: first,
+                    value: pin_bcm.to_string(),
+                    expected: "a BCM pin between 0 and 27",
+                });
+            }
+
+            for &(second, other_pin_bcm) in &pins[i + 1..] {
+                let shared_stdby = first == "mot_left.pin_stdby"
+                    && second == "mot_right.pin_stdby";
+                if pin_bcm == other_pin_bcm && !shared_stdby {
+                    return Err(ConfigError::PinConflict { pin_bcm, first, second });
+                }
+            }
+        }
+
+        let frequencies = [
+            ("mot_left.pwm_frequency_hz", self.mot_left.pwm_frequency),
+            ("mot_right.pwm_frequency_hz", self.mot_right.pwm_frequency),
+            ("led.frequency_hz", self.led.frequency),
+        ];
+        for (key, frequency) in frequencies {
+            let frequency = frequency.get::<hertz>();
+            if !(frequency.is_finite() && frequency > 0.0) {
+                return Err(ConfigError::OutOfRange {
+                    key,
+                    value: frequency.to_string(),
+                    expected: "a positive frequency",
+                });
+            }
+        }
+        if self.nucifera.baud_rate == 0 {
+            return Err(ConfigError::OutOfRange {
+                key: "nucifera.baud_rate",
+                value: self.nucifera.baud_rate.to_string(),
+                expected: "a positive baud rate",
+            });
+        }
+        if !(5..=8).contains(&self.nucifera.data_bits) {
+            return Err(ConfigError::OutOfRange {
+                key: "nucifera.data_bits",
+                value: self.nucifera.data_bits.to_string(),
+                expected: "between 5 and 8",
+            });
+        }
+        if !(1..=2).contains(&self.nucifera.stop_bits) {
+            return Err(ConfigError::OutOfRange {
+                key: "nucifera.stop_bits",
+                value: self.nucifera.stop_bits.to_string(),
+                expected: "1 or 2",
+            });
+        }
+        if self.nucifera.stale_timeout.is_zero() {
+            return Err(ConfigError::OutOfRange {
+                key: "nucifera.stale_timeout_ms",
+                value: self.nucifera.stale_timeout.as_millis().to_string(),
+                expected: "a positive timeout",
+            });
+        }
+        if self.api.interpreter.is_empty() {
+            return Err(ConfigError::OutOfRange {
+                key: "api.interpreter",
+                value: String::from("empty"),
+                expected: "an interpreter executable",
+            });
+        }
+        if self.api.package.is_empty() {
+            return Err(ConfigError::OutOfRange {
+                key: "api.package",
+                value: String::from("empty"),
+                expected: "a python package",
+            });
+        }
+        if let Err(expected) = check_endpoint(&self.api.endpoint) {
+            return Err(ConfigError::OutOfRange {
+                key: "api.endpoint",
+                value: self.api.endpoint.clone(),
+                expected,
+            });
+        }
+        if self.watchdog.enabled && self.watchdog.timeout.is_zero() {
+            return Err(ConfigError::OutOfRange {
+                key: "watchdog.timeout_ms",
+                value: self.watchdog.timeout.as_millis().to_string(),
+                expected: "a positive timeout",
+            });
+        }
+        if self.io.backend == IoBackend::Linux {
+            self.validate_pwm_channels()?;
+        }
+        if self.net.port == 0 {
+            return Err(ConfigError::OutOfRange {
+                key: "net.port",
+                value: self.net.port.to_string(),
+                expected: "a non-zero port",
+            });
+        }
+        self.validate_sim()?;
+
+        Ok(())
+    }
+
+    /// Ensures that the physics simulation is well defined.
+    fn validate_sim(&self) -> Result<(), ConfigError> {
+        let positive = [
+            ("sim.wheel_base_m", self.sim.wheel_base_m),
+            ("sim.max_speed_mps", self.sim.max_speed_mps),
+            ("sim.frame_rate_hz", self.sim.frame_rate_hz),
+        ];
+        for (key, value) in positive {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(ConfigError::OutOfRange {
+                    key,
+                    value: value.to_string(),
+                    expected: "a positive number",
+                });
+            }
+        }
+        // The physics advances in steps of 10 ms, and each step reports the
+        // position at most once.
+        if self.sim.frame_rate_hz > MAX_FRAME_RATE_HZ {
+            return Err(ConfigError::OutOfRange {
+                key: "sim.frame_rate_hz",
+                value: self.sim.frame_rate_hz.to_string(),
+                expected: "a frame rate of at most 100 Hz",
+            });
+        }
+        let noises = [
+            ("sim.speed_noise", self.sim.speed_noise),
+            ("sim.position_noise_m", self.sim.position_noise_m),
+        ];
+        for (key, value) in noises {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(ConfigError::OutOfRange {
+                    key,
+                    value: value.to_string(),
+                    expected: "a non-negative standard deviation",
+                });
+            }
+        }
+        let arena = &self.sim.arena;
+        if arena.min_x_m.partial_cmp(&arena.max_x_m) != Some(Ordering::Less) {
+            return Err(ConfigError::OutOfRange {
+                key: "sim.arena.max_x_m",
+                value: arena.max_x_m.to_string(),
+                expected: "greater than `sim.arena.min_x_m`",
+            });
+        }
+        if arena.min_y_m.partial_cmp(&arena.max_y_m) != Some(Ordering::Less) {
+            return Err(ConfigError::OutOfRange {
+                key: "sim.arena.max_y_m",
+                value: arena.max_y_m.to_string(),
+                expected: "greater than `sim.arena.min_y_m`",
+            });
+        }
+        Ok(())
+    }
+
+    /// Ensures that every pin driven with PWM is mapped to a channel of the
+    /// sysfs PWM chip, and that no two of them share a channel.
+    fn validate_pwm_channels(&self) -> Result<(), ConfigError> {
+        let pwm_pins = [self.mot_left.pin_pwm, self.mot_right.pin_pwm,
+                        self.led.pin_r_bcm, self.led.pin_g_bcm, self.led.pin_b_bcm];
+        for (i, pin_bcm) in pwm_pins.iter().enumerate() {
+            let channel = match self.io.pwm_channels.get(pin_bcm) {
+                Some(channel) => channel,
+                None => {
+                    return Err(ConfigError::OutOfRange {
+                        key: "io.pwm_channels",
+                        value: format!("missing pin {}", pin_bcm),
+                        expected: "a channel for every pin driven with PWM",
+                    });
+                }
+            };
+            for other_pin_bcm in &pwm_pins[i + 1..] {
+                if self.io.pwm_channels.get(other_pin_bcm) == Some(channel) {
+                    return Err(ConfigError::OutOfRange {
+                        key: "io.pwm_channels",
+                        value: format!("channel {} for pins {} and {}", channel, pin_bcm,
+                                       other_pin_bcm),
+                        expected: "a separate channel for every pin",
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `endpoint` is a ZMQ endpoint both cocos can bind and the API
+/// can connect to, returning what was expected otherwise.
+fn check_endpoint(endpoint: &str) -> Result<(), &'static str> {
+    if let Some(path) = endpoint.strip_prefix("ipc://") {
+        if path.is_empty() || path.len() > MAX_IPC_PATH_LEN {
+            return Err("a socket path of 1 to 107 bytes after `ipc://`");
+        }
+        return Ok(());
+    }
+    if let Some(address) = endpoint.strip_prefix("tcp://") {
+        let (host, port) = address.rsplit_once(':').ok_or("a `tcp://<host>:<port>` endpoint")?;
+        // The API connects to the same endpoint, so it cannot be a wildcard.
+        if host.is_empty() || host == "*" {
+            return Err("a host the API can connect to");
+        }
+        return match port.parse::<u16>() {
+            Ok(port) if port != 0 => Ok(()),
+            _ => Err("a non-zero TCP port"),
+        };
+    }
+    Err("an `ipc://` or `tcp://` endpoint")
+}
+
+/// The tables that map keys of their own rather than naming fields. An
+/// override replaces them as a whole, so that a configuration never inherits
+/// entries it did not list.
+const REPLACED_TABLES: [&str; 1] = ["io.pwm_channels"];
+
+/// Recursively overwrites the values in `base` with those in `overrides`.
+/// Tables are merged key by key so that a partial section keeps the default
+/// values of the keys it omits, except for the [REPLACED_TABLES]. Unknown
+/// keys are carried over so that they are reported when deserializing.
+fn merge_values(base: &mut Value, overrides: Value) {
+    merge_values_at("", base, overrides);
+}
+
+/// Merges `overrides` into `base`, which is found at the dotted `path`.
+fn merge_values_at(path: &str, base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides))
+            if !REPLACED_TABLES.contains(&path) => {
+            for (key, value) in overrides {
+                let key_path = match path {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", path, key),
+                };
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values_at(&key_path, existing, value),
+                    None => { base.insert(key, value); }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// (De)serializes a [Frequency] as a plain number of hertz.
+pub(crate) mod serde_hertz {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use uom::si::{f32::Frequency, frequency::hertz};
+
+    pub fn serialize<S: Serializer>(frequency: &Frequency,
+                                    serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f32(frequency.get::<hertz>())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Frequency, D::Error> {
+        Ok(Frequency::new::<hertz>(f32::deserialize(deserializer)?))
+    }
+}
+
+/// (De)serializes a [Duration] as a plain number of milliseconds.
+pub(crate) mod serde_millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration,
+                                    serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(deserializer)?))
+    }
 }
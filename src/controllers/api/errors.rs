@@ -1,7 +1,11 @@
 /// This module exposes the [ApiError] enum which is used for all api-related
 /// errors.
+use std::fmt;
+
 use zmq;
 
+use super::protocol::{ApiEncoding, API_SUPPORTED_VERSIONS};
+
 #[derive(Debug)]
 /// Represents errors that occur during API transactions.
 pub enum ApiError {
@@ -25,4 +29,47 @@ pub enum ApiError {
     /// Raised when a message is received correctly, but the body could not be
     /// decoded according to the given type.
     InvalidRequestBody,
+    /// Raised when the API declares a protocol version in its handshake that
+    /// cocos does not speak.
+    IncompatibleVersion(u16),
+    /// Raised when a request other than the handshake is not encoded in the
+    /// encoding negotiated with the API.
+    UnexpectedEncoding { received: ApiEncoding, negotiated: ApiEncoding },
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::General => write!(f, "API error"),
+            ApiError::ProcessError => write!(f, "could not manage the API process"),
+            ApiError::IO => write!(f, "API IO error"),
+            ApiError::TimeoutError => write!(f, "timed out sending a message"),
+            ApiError::SockNotReady => write!(f, "the socket is not ready"),
+            ApiError::ZMQError(err) => write!(f, "zmq error: {}", err),
+            ApiError::DecodeError => write!(f, "received a message that is not UTF-8"),
+            ApiError::InvalidRequestHead => write!(f, "received a request with an invalid header"),
+            ApiError::InvalidRequestBody => write!(f, "received a request with an invalid body"),
+            ApiError::IncompatibleVersion(version) => write!(
+                f,
+                "the API speaks protocol version {}, but cocos only speaks versions {} to {}",
+                version,
+                API_SUPPORTED_VERSIONS.start(),
+                API_SUPPORTED_VERSIONS.end()
+            ),
+            ApiError::UnexpectedEncoding { received, negotiated } => write!(
+                f,
+                "received a request in {:?}, but {:?} was negotiated",
+                received, negotiated
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::ZMQError(err) => Some(err),
+            _ => None,
+        }
+    }
 }
@@ -1,9 +1,15 @@
 /// This module defines request types as well as their serialization and
 /// validation functions.
-use serde::Deserialize;
-use serde_repr::Deserialize_repr;
+use std::{fmt, marker::PhantomData};
 
-#[derive(Deserialize_repr, Debug)]
+use serde::de::{self, value::MapAccessDeserializer, DeserializeOwned, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::errors::ApiError;
+use super::protocol::{ApiEncoding, API_OBJECT_BODY_VERSION};
+
+#[derive(Deserialize_repr, Serialize_repr, Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u16)]
 /// Represents a request type that the API can make.
 pub enum ApiIpcRequestType {
@@ -13,15 +19,168 @@ pub enum ApiIpcRequestType {
     Vel = 1,
     /// Represents a position read request.
     Pos = 2,
+    /// Represents a request to broadcast a message to other coachbots.
+    MsgSend = 3,
+    /// Represents a request to read the messages received from other
+    /// coachbots.
+    MsgRecv = 4,
+    /// Represents a log record emitted by the user script.
+    Log = 5,
+    /// Represents a request for the status of the motion output.
+    Status = 6,
+    /// Represents a request for the timing statistics of the periodic tasks.
+    Stats = 7,
+    /// Represents the request with which the API declares the protocol
+    /// version it speaks.
+    Handshake = 8,
 }
 
-#[derive(Deserialize, Debug)]
+impl ApiIpcRequestType {
+    /// Every request type cocos handles.
+    pub const ALL: [ApiIpcRequestType; 9] = [
+        ApiIpcRequestType::Led,
+        ApiIpcRequestType::Vel,
+        ApiIpcRequestType::Pos,
+        ApiIpcRequestType::MsgSend,
+        ApiIpcRequestType::MsgRecv,
+        ApiIpcRequestType::Log,
+        ApiIpcRequestType::Status,
+        ApiIpcRequestType::Stats,
+        ApiIpcRequestType::Handshake,
+    ];
+}
+
+#[derive(Debug)]
 /// Represents a request that the API can make. This structure wraps around the
-/// request, presenting metadata and a body as a string. Further structures
-/// deserialize the body as required.
-pub struct ApiIpcRequest {
+/// request, presenting metadata and a body. Further structures deserialize the
+/// body as required, see [ApiIpcRequest::decode_body].
+pub struct ApiIpcRequest<'a> {
     pub request_type: ApiIpcRequestType,
-    pub body: String,
+    /// The whole request as received. The body is only decoded once its type
+    /// is known.
+    message: &'a [u8],
+    encoding: ApiEncoding,
+}
+
+#[derive(Deserialize)]
+/// The fields of a request read before its body.
+struct ApiIpcRequestHead {
+    request_type: ApiIpcRequestType,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "BodyType: DeserializeOwned")]
+/// The fields of a request read once the type of its body is known.
+struct ApiIpcRequestTail<BodyType> {
+    body: ApiIpcBody<BodyType>,
+}
+
+/// Represents a body as sent by the API.
+enum ApiIpcBody<BodyType> {
+    /// A JSON document encoded into a string, as sent in every version.
+    Encoded(BodyType),
+    /// The object itself, as sent from [API_OBJECT_BODY_VERSION] on.
+    Object(BodyType),
+}
+
+impl<'de, BodyType: DeserializeOwned> Deserialize<'de> for ApiIpcBody<BodyType> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ApiIpcBodyVisitor(PhantomData))
+    }
+}
+
+struct ApiIpcBodyVisitor<BodyType>(PhantomData<BodyType>);
+
+impl<'de, BodyType: DeserializeOwned> Visitor<'de> for ApiIpcBodyVisitor<BodyType> {
+    type Value = ApiIpcBody<BodyType>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object or a string holding a JSON object")
+    }
+
+    fn visit_str<E: de::Error>(self, body: &str) -> Result<Self::Value, E> {
+        serde_json::from_str(body).map(ApiIpcBody::Encoded).map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        BodyType::deserialize(MapAccessDeserializer::new(map)).map(ApiIpcBody::Object)
+    }
+}
+
+impl<'a> ApiIpcRequest<'a> {
+    /// Decodes the head of a request received in `encoding`, raising an
+    /// [ApiError::DecodeError] if a JSON request is not valid UTF-8 and an
+    /// [ApiError::InvalidRequestHead] if it could not be decoded otherwise.
+    pub fn decode(message: &'a [u8], encoding: ApiEncoding) -> Result<Self, ApiError> {
+        let head: ApiIpcRequestHead = match encoding {
+            ApiEncoding::Json => {
+                let message = std::str::from_utf8(message).map_err(|_| ApiError::DecodeError)?;
+                serde_json::from_str(message).map_err(|_| ApiError::InvalidRequestHead)?
+            }
+            ApiEncoding::MessagePack => {
+                rmp_serde::from_slice(message).map_err(|_| ApiError::InvalidRequestHead)?
+            }
+        };
+        Ok(Self { request_type: head.request_type, message, encoding })
+    }
+
+    /// Deserializes the body of the request, as spoken in the protocol
+    /// `version`. String bodies are accepted in every version.
+    pub fn decode_body<BodyType: DeserializeOwned>(
+        &self,
+        version: u16,
+    ) -> Result<BodyType, ApiIpcBodyError> {
+        let tail: ApiIpcRequestTail<BodyType> = match self.encoding {
+            ApiEncoding::Json => {
+                serde_json::from_slice(self.message).map_err(ApiIpcBodyError::Json)?
+            }
+            ApiEncoding::MessagePack => {
+                rmp_serde::from_slice(self.message).map_err(ApiIpcBodyError::MessagePack)?
+            }
+        };
+        match tail.body {
+            ApiIpcBody::Encoded(body) => Ok(body),
+            ApiIpcBody::Object(body) if version >= API_OBJECT_BODY_VERSION => Ok(body),
+            ApiIpcBody::Object(_) => Err(ApiIpcBodyError::ObjectBody { version }),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Represents the reasons the body of a request could not be decoded, see
+/// [ApiIpcRequest::decode_body].
+pub enum ApiIpcBodyError {
+    /// The JSON request does not hold a body of the expected type.
+    Json(serde_json::Error),
+    /// The MessagePack request does not hold a body of the expected type.
+    MessagePack(rmp_serde::decode::Error),
+    /// The body is an object, but the protocol `version` only accepts strings.
+    ObjectBody { version: u16 },
+}
+
+impl fmt::Display for ApiIpcBodyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiIpcBodyError::Json(err) => err.fmt(f),
+            ApiIpcBodyError::MessagePack(err) => err.fmt(f),
+            ApiIpcBodyError::ObjectBody { version } => write!(
+                f,
+                "the body must be a string in protocol version {}, send a handshake for \
+                 version {} to send it as an object",
+                version, API_OBJECT_BODY_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApiIpcBodyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiIpcBodyError::Json(err) => Some(err),
+            ApiIpcBodyError::MessagePack(err) => Some(err),
+            ApiIpcBodyError::ObjectBody { .. } => None,
+        }
+    }
 }
 
 /// This trait ensures that ApiIpc*RequestBody functions can be validated.
@@ -39,6 +198,7 @@ pub struct ApiIpcLedRequestBody {
     pub b: u8,
 }
 
+#[allow(clippy::needless_return)]
 impl ValidatesApiIpcBody for ApiIpcLedRequestBody {
     fn validate(&self) -> bool {
         let valid_range = 0..=255;
@@ -56,6 +216,7 @@ pub struct ApiIpcVelRequestBody {
     pub r: i8,
 }
 
+#[allow(clippy::needless_return)]
 impl ValidatesApiIpcBody for ApiIpcVelRequestBody {
     fn validate(&self) -> bool {
         let valid_range = -100..=100;
@@ -67,8 +228,90 @@ impl ValidatesApiIpcBody for ApiIpcVelRequestBody {
 /// Represents a request body for [ApiIpcPosRequestType::Pos].
 pub struct ApiIpcPosRequestBody {}
 
+#[allow(clippy::needless_return)]
 impl ValidatesApiIpcBody for ApiIpcPosRequestBody {
     fn validate(&self) -> bool {
         return true;
     }
 }
+
+#[derive(Deserialize, Debug)]
+/// Represents a request body for [ApiIpcRequestType::MsgSend]. Messages longer
+/// than the network message length are trimmed on a character boundary.
+pub struct ApiIpcMsgSendRequestBody {
+    pub msg: String,
+}
+
+impl ValidatesApiIpcBody for ApiIpcMsgSendRequestBody {
+    fn validate(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize, Debug)]
+/// Represents a request body for [ApiIpcRequestType::MsgRecv]. If `clear` is
+/// set, the returned messages are removed from the inbox.
+pub struct ApiIpcMsgRecvRequestBody {
+    pub clear: bool,
+}
+
+impl ValidatesApiIpcBody for ApiIpcMsgRecvRequestBody {
+    fn validate(&self) -> bool {
+        true
+    }
+}
+
+/// The maximum length of a single log message in bytes.
+pub const API_LOG_MESSAGE_MAX_LEN: usize = 4096;
+
+#[derive(Deserialize, Debug)]
+/// Represents a request body for [ApiIpcRequestType::Log]. The `level` is a
+/// python `logging` level (for example 20 for `INFO`).
+pub struct ApiIpcLogRequestBody {
+    pub level: u8,
+    pub message: String,
+}
+
+impl ValidatesApiIpcBody for ApiIpcLogRequestBody {
+    fn validate(&self) -> bool {
+        self.message.len() <= API_LOG_MESSAGE_MAX_LEN
+    }
+}
+
+#[derive(Deserialize, Debug)]
+/// Represents a request body for [ApiIpcRequestType::Status].
+pub struct ApiIpcStatusRequestBody {}
+
+impl ValidatesApiIpcBody for ApiIpcStatusRequestBody {
+    fn validate(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize, Debug)]
+/// Represents a request body for [ApiIpcRequestType::Stats].
+pub struct ApiIpcStatsRequestBody {}
+
+impl ValidatesApiIpcBody for ApiIpcStatsRequestBody {
+    fn validate(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize, Debug)]
+/// Represents a request body for [ApiIpcRequestType::Handshake]. Whether cocos
+/// speaks `version` is checked by the handshake itself, so that it can be
+/// rejected with a clear error.
+pub struct ApiIpcHandshakeRequestBody {
+    pub version: u16,
+    /// The names of the encodings the API speaks, most preferred first, see
+    /// [ApiEncoding::negotiate]. Only JSON is spoken if none are given.
+    #[serde(default)]
+    pub encodings: Vec<String>,
+}
+
+impl ValidatesApiIpcBody for ApiIpcHandshakeRequestBody {
+    fn validate(&self) -> bool {
+        true
+    }
+}
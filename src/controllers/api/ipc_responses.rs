@@ -2,6 +2,12 @@
 use serde::Serialize;
 use serde_repr::Serialize_repr;
 
+use super::ipc_requests::ApiIpcRequestType;
+use super::protocol::{ApiEncoding, API_OBJECT_BODY_VERSION};
+
+/// The initial capacity of the buffer a response is encoded into.
+const RESPONSE_CAPACITY: usize = 128;
+
 #[derive(Serialize_repr)]
 #[repr(u16)]
 /// Represents a status code that is sent back to the API.
@@ -11,17 +17,51 @@ pub enum ApiStatus {
     InvalidRequestHead = 2,
     InvalidRequestBody = 3,
     InvalidRequestArgs = 4,
+    /// The API speaks a protocol version cocos does not support.
+    IncompatibleVersion = 5,
 }
 
 #[derive(Serialize)]
 /// Represents a response that is sent to the API. This structure wraps header
 /// values and the body.
-pub struct ApiResponse {
+pub struct ApiResponse<BodyType: Serialize> {
     /// The response error code to the given request.
     pub status: ApiStatus,
 
     /// The body of the error code.
-    pub body: String,
+    pub body: BodyType,
+}
+
+impl<BodyType: Serialize> ApiResponse<BodyType> {
+    /// Creates a response carrying `body`.
+    pub fn new(status: ApiStatus, body: BodyType) -> Self {
+        Self { status, body }
+    }
+
+    /// Encodes the response as spoken in the protocol `version`, in
+    /// `encoding`. Before [API_OBJECT_BODY_VERSION], the body is sent encoded
+    /// into a JSON string.
+    pub fn encode(self, version: u16, encoding: ApiEncoding) -> Vec<u8> {
+        if version < API_OBJECT_BODY_VERSION {
+            let body = serde_json::to_string(&self.body).unwrap();
+            return ApiResponse { status: self.status, body }.encode_as(encoding);
+        }
+        self.encode_as(encoding)
+    }
+
+    fn encode_as(&self, encoding: ApiEncoding) -> Vec<u8> {
+        match encoding {
+            ApiEncoding::Json => serde_json::to_vec(self).unwrap(),
+            ApiEncoding::MessagePack => {
+                // Growing the buffer from empty takes longer than encoding a
+                // small response.
+                let mut buffer = Vec::with_capacity(RESPONSE_CAPACITY);
+                self.serialize(&mut rmp_serde::Serializer::new(&mut buffer).with_struct_map())
+                    .unwrap();
+                buffer
+            }
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -45,3 +85,82 @@ pub struct ApiIpcPosResponseBody {
     pub y: f32,
     pub theta: f32,
 }
+
+#[derive(Serialize)]
+/// Represents a body returned upon successfully queueing a message.
+pub struct ApiIpcMsgSendResponseBody {}
+
+#[derive(Serialize)]
+/// Represents a body returned upon a message inbox query.
+pub struct ApiIpcMsgRecvResponseBody {
+    /// The received messages, oldest first.
+    pub messages: Vec<String>,
+}
+
+#[derive(Serialize)]
+/// Represents a body returned upon successfully logging a record.
+pub struct ApiIpcLogResponseBody {}
+
+#[derive(Serialize)]
+/// Represents a body returned upon a status query.
+pub struct ApiIpcStatusResponseBody {
+    /// Whether the motors are currently braked.
+    pub motors_locked: bool,
+    /// Whether the motors are braked because no velocity command arrived
+    /// within the watchdog timeout. Sending a velocity command releases them.
+    pub watchdog_tripped: bool,
+}
+
+#[derive(Serialize)]
+/// Represents the timing statistics of a single periodic task. All times are
+/// in microseconds.
+pub struct ApiIpcTaskStatsBody {
+    pub name: String,
+    pub period_us: u64,
+    pub runs: u64,
+    /// How many runs took longer than the period.
+    pub overruns: u64,
+    pub exec_mean_us: u64,
+    pub exec_worst_us: u64,
+    /// The delay between a run being due and it starting.
+    pub jitter_mean_us: u64,
+    pub jitter_max_us: u64,
+    /// The number of runs per execution time bucket, see
+    /// [ApiIpcStatsResponseBody::histogram_bounds_us].
+    pub histogram: Vec<u64>,
+}
+
+#[derive(Serialize)]
+/// Represents a body returned upon a task statistics query.
+pub struct ApiIpcStatsResponseBody {
+    /// The upper bounds of the histogram buckets. The last bucket of every
+    /// histogram holds the runs above the last bound.
+    pub histogram_bounds_us: Vec<u64>,
+    pub tasks: Vec<ApiIpcTaskStatsBody>,
+}
+
+#[derive(Serialize)]
+/// Represents the limits the API must respect in its requests.
+pub struct ApiIpcLimitsBody {
+    /// The maximum length of a message sent to other coachbots in bytes.
+    /// Longer messages are trimmed.
+    pub net_msg_len: usize,
+    /// The maximum length of a log message in bytes.
+    pub log_message_max_len: usize,
+    /// The maximum number of requests handled per tick.
+    pub max_requests_per_tick: usize,
+}
+
+#[derive(Serialize)]
+/// Represents a body returned upon a successful handshake.
+pub struct ApiIpcHandshakeResponseBody {
+    /// The newest protocol version cocos speaks.
+    pub version: u16,
+    /// The oldest protocol version cocos speaks.
+    pub min_version: u16,
+    pub robot_id: u32,
+    pub request_types: Vec<ApiIpcRequestType>,
+    pub limits: ApiIpcLimitsBody,
+    /// The encoding of every following message.
+    pub encoding: ApiEncoding,
+}
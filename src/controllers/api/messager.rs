@@ -1,29 +1,68 @@
 use std::str::from_utf8;
+use std::time::Duration;
 
-use crate::controllers::api::ipc_responses::{ApiIpcErrorResponseBody, ApiIpcPosResponseBody};
+use crate::controllers::api::ipc_responses::{
+    ApiIpcErrorResponseBody, ApiIpcHandshakeResponseBody, ApiIpcLimitsBody,
+    ApiIpcLogResponseBody, ApiIpcMsgRecvResponseBody, ApiIpcMsgSendResponseBody,
+    ApiIpcPosResponseBody, ApiIpcStatsResponseBody, ApiIpcStatusResponseBody,
+    ApiIpcTaskStatsBody,
+};
+use crate::controllers::network::{trim_message, NET_MSG_LEN};
+use crate::controllers::task_stats::EXEC_TIME_BUCKETS_US;
 use crate::models::api::{ApiTickInputMessage, ApiTickOutputMessage};
 use crate::models::led_color::LedColor;
 use crate::models::motor_power::MotorPower;
 use log::debug;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Serialize};
 /// This module exposes the [ApiMessager] class which is responsible for
 /// communication with the API.
 use zmq;
 
 use super::errors::ApiError;
 use super::ipc_requests::{
-    ApiIpcLedRequestBody, ApiIpcPosRequestBody, ApiIpcRequest, ApiIpcRequestType,
-    ApiIpcVelRequestBody, ValidatesApiIpcBody,
+    ApiIpcHandshakeRequestBody, ApiIpcLedRequestBody, ApiIpcLogRequestBody,
+    ApiIpcMsgRecvRequestBody, ApiIpcMsgSendRequestBody, ApiIpcPosRequestBody, ApiIpcRequest,
+    ApiIpcRequestType, ApiIpcStatsRequestBody, ApiIpcStatusRequestBody, ApiIpcVelRequestBody,
+    ValidatesApiIpcBody, API_LOG_MESSAGE_MAX_LEN,
 };
 use super::ipc_responses::{ApiIpcLedResponseBody, ApiIpcVelResponseBody, ApiResponse, ApiStatus};
+use super::protocol::{
+    self, ApiEncoding, API_LEGACY_PROTOCOL_VERSION, API_OBJECT_BODY_VERSION, API_PROTOCOL_VERSION,
+    API_SUPPORTED_VERSIONS,
+};
+use super::user_log::{self, USER_LOG_TARGET};
+
+/// The maximum number of requests handled in a single tick. This prevents a
+/// chatty script from starving the caller of [ApiMessager::run_tick].
+const MAX_REQUESTS_PER_TICK: usize = 64;
+
+/// Handles a validated request body, returning the response to send and the
+/// outputs of the tick.
+type BodyHandler<BodyType, ResponseType> = dyn FnMut(
+    &mut ApiMessager,
+    BodyType,
+    &ApiTickInputMessage,
+) -> (ApiResponse<ResponseType>, ApiTickOutputMessage);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Represents the state of the [ApiMessager] socket.
+pub enum ApiMessagerState {
+    /// The messager was not started or was stopped.
+    Stopped,
+    /// The socket is bound and waiting for requests.
+    Ready,
+    /// The socket is in an unknown state (for example a REQ/REP mismatch) and
+    /// is rebuilt on the next tick.
+    Faulted,
+}
 
 /// Represents the main class used for communication with the API.
 ///
 /// This object is responsible for the underlying ZMQ transactions as well as
 /// parsing requests and giving responses.
 pub struct ApiMessager {
-    /// The UNIX file used for IPC communication.
-    pub comm_file: &'static str,
+    /// The ZMQ endpoint used for communication.
+    pub comm_file: String,
 
     /// The zmq context used for communication.
     context: zmq::Context,
@@ -31,141 +70,262 @@ pub struct ApiMessager {
     /// The zmq socket used for communication. A [None] value represents either
     /// an unusable socket and/or an unconstructed socket.
     socket: Option<zmq::Socket>,
+
+    /// The current state of the socket.
+    state: ApiMessagerState,
+
+    /// Whether a request was received that has not been replied to yet.
+    awaiting_reply: bool,
+
+    /// How long a tick waits for the first request to arrive.
+    poll_timeout: Duration,
+
+    /// The id of the robot, used to tag the records logged by the API.
+    robot_id: u32,
+
+    /// The protocol version negotiated with the API, see
+    /// [ApiIpcRequestType::Handshake].
+    protocol_version: u16,
+
+    /// The encoding negotiated with the API.
+    encoding: ApiEncoding,
+
+    /// The encoding of the last request, which its response is sent in.
+    reply_encoding: ApiEncoding,
 }
 
 impl ApiMessager {
     /// Constructs a new ApiMessager given the communication file uri.
     ///
-    /// * `comm_file` - A static string of the form `ipc:///path/to/sock`
-    ///                 through which communication is done.
-    pub fn new(comm_file: &'static str) -> ApiMessager {
+    /// * `comm_file` - A ZMQ endpoint of the form `ipc:///path/to/sock`
+    ///   through which communication is done.
+    /// * `poll_timeout` - How long a tick waits for the first request.
+    /// * `robot_id` - The id of the robot, used to tag logged records.
+    pub fn new(comm_file: String, poll_timeout: Duration, robot_id: u32) -> ApiMessager {
         ApiMessager {
             comm_file,
             context: zmq::Context::new(),
             socket: Option::None,
+            state: ApiMessagerState::Stopped,
+            awaiting_reply: false,
+            poll_timeout,
+            robot_id,
+            protocol_version: API_LEGACY_PROTOCOL_VERSION,
+            encoding: ApiEncoding::Json,
+            reply_encoding: ApiEncoding::Json,
         }
     }
 
+    /// Returns the current state of the messager.
+    pub fn state(&self) -> ApiMessagerState {
+        self.state
+    }
+
     /// Starts the messager, initializing the socket, returning an error if an
     /// error ocurred. An error implies that the ApiMessager is unusable.
     pub fn start(&mut self) -> Result<(), ApiError> {
         match self.context.socket(zmq::REP) {
             Ok(sock) => {
-                if let Err(zmq_err) = sock.bind(self.comm_file) {
+                // Do not hold on to unsent replies when the socket is
+                // rebuilt, the peer is likely gone anyway.
+                if let Err(zmq_err) = sock.set_linger(0) {
+                    return Err(ApiError::ZMQError(zmq_err));
+                };
+                if let Err(zmq_err) = sock.bind(&self.comm_file) {
                     return Err(ApiError::ZMQError(zmq_err));
                 };
 
                 self.socket = Some(sock);
+                self.state = ApiMessagerState::Ready;
+                self.awaiting_reply = false;
                 Ok(())
             }
             Err(err) => Err(ApiError::ZMQError(err)),
         }
     }
 
-    /// Immediately stops the messager, closing any open sockets.
+    /// Returns the protocol version negotiated with the API.
+    pub fn protocol_version(&self) -> u16 {
+        self.protocol_version
+    }
+
+    /// Returns the encoding negotiated with the API.
+    pub fn encoding(&self) -> ApiEncoding {
+        self.encoding
+    }
+
+    /// Immediately stops the messager, closing any open sockets. The next API
+    /// has to handshake again.
     pub fn stop(&mut self) {
-        match &mut self.socket {
-            None => {}
-            Some(_sock) => {
-                self.socket = Option::None;
-            }
-        }
+        self.socket = Option::None;
+        self.state = ApiMessagerState::Stopped;
+        self.awaiting_reply = false;
+        self.protocol_version = API_LEGACY_PROTOCOL_VERSION;
+        self.encoding = ApiEncoding::Json;
     }
 
-    /// A function that must be hooked into a looped task, reading a message,
-    /// parsing it and emitting the requested instruction into the appropriate
-    /// channel.
+    /// Marks the socket as unusable so that it is rebuilt on the next tick.
+    fn fault(&mut self, err: &ApiError) {
+        log::error!(target: "system.api.messager",
+                    "Socket faulted, rebuilding on next tick: {:?}", err);
+        self.socket = Option::None;
+        self.state = ApiMessagerState::Faulted;
+        self.awaiting_reply = false;
+    }
+
+    /// A function that must be hooked into a looped task. It waits up to the
+    /// poll timeout for a request and then handles every pending request
+    /// without further waiting, merging the requested instructions into a
+    /// single [ApiTickOutputMessage]. Later requests override earlier ones.
     ///
-    /// This function returns an [ApiError::SockNotReady] if the socket is not
-    /// well prepared. An [ApiError::ZMQError] is thrown for all zmq-related
-    /// errors. These errors are unrecoverable the API will not be notified.
+    /// This function returns an [ApiError::SockNotReady] if the messager was
+    /// not started. Requests that are rejected (for example
+    /// [ApiError::DecodeError]) are answered with an error status, logged
+    /// and skipped.
     ///
-    /// [ApiError::DecodeError] is raised when the bytes could not be decoded,
-    /// however, the API is notified via an [ApiStatus::InvalidEncoding].
+    /// zmq-related errors put the messager into
+    /// [ApiMessagerState::Faulted]. The instructions gathered so far are
+    /// still returned and the socket is rebuilt on the next tick.
     pub fn run_tick(
         &mut self,
         data: ApiTickInputMessage,
     ) -> Result<ApiTickOutputMessage, ApiError> {
-        match &self.socket {
-            None => Err(ApiError::SockNotReady),
-            Some(sock) => {
-                // Receive the message, raising an error if an error ocurred.
-                let message_res = sock.recv_msg(0); // TODO Should not block
-                if let Err(err) = message_res {
-                    return Err(ApiError::ZMQError(err));
+        match self.state {
+            ApiMessagerState::Stopped => return Err(ApiError::SockNotReady),
+            ApiMessagerState::Faulted => {
+                log::info!(target: "system.api.messager", "Rebuilding the socket.");
+                self.start()?;
+            }
+            ApiMessagerState::Ready => {}
+        }
+
+        let mut output = ApiTickOutputMessage::none();
+        let mut timeout = self.poll_timeout;
+        for _ in 0..MAX_REQUESTS_PER_TICK {
+            let message = match self.poll_request(timeout) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(err) => {
+                    self.fault(&err);
+                    break;
                 }
+            };
+            // Only wait for the first request, drain the rest.
+            timeout = Duration::ZERO;
 
-                let message = message_res.unwrap();
-                log::debug!(target: "system.api.messager",
-                            "Received raw message: {:?}",
-                            from_utf8(&message.to_vec()).unwrap_or("<ERROR_PARSING_UTF8>"));
-
-                // Ensure the data received is valid UTF-8.
-                if message.as_str().is_none() {
-                    let response = serde_json::to_string(&ApiResponse {
-                        status: ApiStatus::InvalidEncoding,
-                        body: serde_json::to_string(&ApiIpcErrorResponseBody {
-                            message: "The character encoding is not UTF-8. 
-                                Are you trying something funny?"
-                                .to_string(),
-                        })
-                        .unwrap(),
-                    })
-                    .unwrap();
-                    match self.send_response(response) {
-                        Ok(()) => {
-                            return Err(ApiError::DecodeError);
-                        }
-                        Err(err) => {
-                            return Err(err);
-                        }
-                    }
+            match self.handle_message(message, &data) {
+                Ok(state) => output.merge(state),
+                Err(err @ ApiError::ZMQError(_)) | Err(err @ ApiError::SockNotReady) => {
+                    self.fault(&err);
+                    break;
+                }
+                Err(err) => {
+                    log::warn!(target: "system.api.messager", "Rejected request: {:?}", err);
                 }
+            }
+
+            if self.awaiting_reply {
+                self.fault(&ApiError::SockNotReady);
+                break;
+            }
+        }
+
+        Ok(output)
+    }
 
-                // Parse the request down to a concrete request.
-                let msg_str = message.as_str().unwrap();
-
-                // Ensure the message header can be decoded correctly.
-                let req_opt: Result<ApiIpcRequest, serde_json::Error> =
-                    serde_json::from_str(msg_str);
-
-                if req_opt.is_err() {
-                    let response = serde_json::to_string(&ApiResponse {
-                        status: ApiStatus::InvalidRequestHead,
-                        body: serde_json::to_string(&ApiIpcErrorResponseBody {
-                            message: "The header of the message is invalid. 
-                                Are you trying something funny?"
-                                .to_string(),
-                        })
-                        .unwrap(),
-                    })
-                    .unwrap();
-                    match self.send_response(response) {
-                        Ok(()) => {
-                            return Err(ApiError::InvalidRequestHead);
-                        }
-                        Err(err) => {
-                            return Err(err);
-                        }
-                    }
+    /// Waits up to `timeout` for a request, returning [None] if none arrived.
+    fn poll_request(&mut self, timeout: Duration) -> Result<Option<zmq::Message>, ApiError> {
+        let sock = match &self.socket {
+            None => return Err(ApiError::SockNotReady),
+            Some(sock) => sock,
+        };
+
+        match sock.poll(zmq::POLLIN, timeout.as_millis() as i64) {
+            Ok(0) => Ok(None),
+            Ok(_) => match sock.recv_msg(zmq::DONTWAIT) {
+                Ok(message) => {
+                    self.awaiting_reply = true;
+                    Ok(Some(message))
                 }
-                let request = req_opt.unwrap();
-                self.handle_request(&request, &data)
+                Err(zmq::Error::EAGAIN) => Ok(None),
+                Err(err) => Err(ApiError::ZMQError(err)),
+            },
+            Err(zmq::Error::EINTR) => Ok(None),
+            Err(err) => Err(ApiError::ZMQError(err)),
+        }
+    }
+
+    /// Parses and handles a single received message, sending the response.
+    ///
+    /// [ApiError::DecodeError] is raised when the bytes could not be decoded,
+    /// however, the API is notified via an [ApiStatus::InvalidEncoding].
+    fn handle_message(
+        &mut self,
+        message: zmq::Message,
+        data: &ApiTickInputMessage,
+    ) -> Result<ApiTickOutputMessage, ApiError> {
+        // A request is answered in the encoding it was sent in, so that an API
+        // which lost the response to its handshake can still read the
+        // response to the handshake it repeats in JSON.
+        let encoding = ApiEncoding::detect(&message).unwrap_or(self.encoding);
+        self.reply_encoding = encoding;
+        match encoding {
+            ApiEncoding::Json => {
+                log::debug!(target: "system.api.messager", "Received raw message: {:?}",
+                            from_utf8(&message).unwrap_or("<ERROR_PARSING_UTF8>"))
+            }
+            ApiEncoding::MessagePack => {
+                log::debug!(target: "system.api.messager", "Received raw message: {:02x?}",
+                            &message[..])
             }
         }
+
+        // Ensure the message header can be decoded correctly.
+        let request = match ApiIpcRequest::decode(&message, encoding) {
+            Ok(request) => request,
+            Err(err @ ApiError::DecodeError) => {
+                return Err(self.reject(
+                    ApiStatus::InvalidEncoding,
+                    "The character encoding is not UTF-8. Are you trying something funny?"
+                        .to_string(),
+                    err,
+                ))
+            }
+            Err(err) => {
+                return Err(self.reject(
+                    ApiStatus::InvalidRequestHead,
+                    "The header of the message is invalid. Are you trying something funny?"
+                        .to_string(),
+                    err,
+                ))
+            }
+        };
+        if !protocol::accepts_request(self.encoding, encoding, request.request_type) {
+            let err = ApiError::UnexpectedEncoding { received: encoding,
+                                                     negotiated: self.encoding };
+            let message = format!("Cocos {}. Send the handshake again to switch.", err);
+            return Err(self.reject(ApiStatus::InvalidEncoding, message, err));
+        }
+        self.handle_request(&request, data)
+    }
+
+    /// Answers the last request with an error response, returning `err` or
+    /// the error that occurred sending the response.
+    fn reject(&mut self, status: ApiStatus, message: String, err: ApiError) -> ApiError {
+        match self.send_response(ApiResponse::new(status, ApiIpcErrorResponseBody { message })) {
+            Ok(()) => err,
+            Err(send_err) => send_err,
+        }
     }
 
     fn handle_led_request(
         &mut self,
         request: ApiIpcLedRequestBody,
         _input_data: &ApiTickInputMessage,
-    ) -> (ApiResponse, ApiTickOutputMessage) {
+    ) -> (ApiResponse<ApiIpcLedResponseBody>, ApiTickOutputMessage) {
         debug!(target: "system.api.request", "Received LED request {:?}", request);
         (
-            ApiResponse {
-                status: ApiStatus::Success,
-                body: serde_json::to_string(&ApiIpcLedResponseBody {}).unwrap(),
-            },
+            ApiResponse::new(ApiStatus::Success, ApiIpcLedResponseBody {}),
             ApiTickOutputMessage::led(
                 LedColor::new(
                     request.r as f32 / 255f32,
@@ -181,13 +341,10 @@ impl ApiMessager {
         &mut self,
         request: ApiIpcVelRequestBody,
         _input_data: &ApiTickInputMessage,
-    ) -> (ApiResponse, ApiTickOutputMessage) {
+    ) -> (ApiResponse<ApiIpcVelResponseBody>, ApiTickOutputMessage) {
         debug!(target: "system.api.request", "Received velocity request {:?}", request);
         (
-            ApiResponse {
-                status: ApiStatus::Success,
-                body: serde_json::to_string(&ApiIpcVelResponseBody {}).unwrap(),
-            },
+            ApiResponse::new(ApiStatus::Success, ApiIpcVelResponseBody {}),
             ApiTickOutputMessage::motor(
                 MotorPower::new(request.l as f32 / 100f32, request.r as f32 / 100f32, false)
                     .unwrap(),
@@ -199,18 +356,107 @@ impl ApiMessager {
         &mut self,
         request: ApiIpcPosRequestBody,
         input_data: &ApiTickInputMessage,
-    ) -> (ApiResponse, ApiTickOutputMessage) {
+    ) -> (ApiResponse<ApiIpcPosResponseBody>, ApiTickOutputMessage) {
         debug!(target: "system.api.request", "Received position request {:?}.", request);
         (
-            ApiResponse {
-                status: ApiStatus::Success,
-                body: serde_json::to_string(&ApiIpcPosResponseBody {
-                    x: input_data.bot_pos.x.value,
-                    y: input_data.bot_pos.y.value,
-                    theta: input_data.bot_pos.theta.value,
-                })
-                .unwrap(),
+            ApiResponse::new(ApiStatus::Success, ApiIpcPosResponseBody {
+                x: input_data.bot_pos.x.value,
+                y: input_data.bot_pos.y.value,
+                theta: input_data.bot_pos.theta.value,
+            }),
+            ApiTickOutputMessage::none(),
+        )
+    }
+
+    fn handle_msg_send_request(
+        &mut self,
+        request: ApiIpcMsgSendRequestBody,
+        _input_data: &ApiTickInputMessage,
+    ) -> (ApiResponse<ApiIpcMsgSendResponseBody>, ApiTickOutputMessage) {
+        debug!(target: "system.api.request", "Received message send request {:?}.", request);
+        (
+            ApiResponse::new(ApiStatus::Success, ApiIpcMsgSendResponseBody {}),
+            ApiTickOutputMessage::net_send(trim_message(&request.msg).as_bytes().to_vec()),
+        )
+    }
+
+    fn handle_msg_recv_request(
+        &mut self,
+        request: ApiIpcMsgRecvRequestBody,
+        input_data: &ApiTickInputMessage,
+    ) -> (ApiResponse<ApiIpcMsgRecvResponseBody>, ApiTickOutputMessage) {
+        debug!(target: "system.api.request", "Received message receive request {:?}.", request);
+        let messages = input_data
+            .net_inbox
+            .iter()
+            .map(|message| String::from_utf8_lossy(&message.data).into_owned())
+            .collect();
+        let last_id = input_data.net_inbox.last().map(|message| message.id);
+        (
+            ApiResponse::new(ApiStatus::Success, ApiIpcMsgRecvResponseBody { messages }),
+            match (request.clear, last_id) {
+                (true, Some(id)) => ApiTickOutputMessage::net_ack(id),
+                _ => ApiTickOutputMessage::none(),
             },
+        )
+    }
+
+    fn handle_log_request(
+        &mut self,
+        request: ApiIpcLogRequestBody,
+        _input_data: &ApiTickInputMessage,
+    ) -> (ApiResponse<ApiIpcLogResponseBody>, ApiTickOutputMessage) {
+        user_log::log_line(USER_LOG_TARGET, user_log::level_from_python(request.level),
+                           self.robot_id, &request.message);
+        (
+            ApiResponse::new(ApiStatus::Success, ApiIpcLogResponseBody {}),
+            ApiTickOutputMessage::none(),
+        )
+    }
+
+    fn handle_status_request(
+        &mut self,
+        request: ApiIpcStatusRequestBody,
+        input_data: &ApiTickInputMessage,
+    ) -> (ApiResponse<ApiIpcStatusResponseBody>, ApiTickOutputMessage) {
+        debug!(target: "system.api.request", "Received status request {:?}.", request);
+        (
+            ApiResponse::new(ApiStatus::Success, ApiIpcStatusResponseBody {
+                motors_locked: input_data.motors_locked,
+                watchdog_tripped: input_data.watchdog_tripped,
+            }),
+            ApiTickOutputMessage::none(),
+        )
+    }
+
+    fn handle_stats_request(
+        &mut self,
+        request: ApiIpcStatsRequestBody,
+        input_data: &ApiTickInputMessage,
+    ) -> (ApiResponse<ApiIpcStatsResponseBody>, ApiTickOutputMessage) {
+        debug!(target: "system.api.request", "Received stats request {:?}.", request);
+        let tasks = input_data
+            .task_stats
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|stats| ApiIpcTaskStatsBody {
+                name: stats.name().to_string(),
+                period_us: stats.period().as_micros() as u64,
+                runs: stats.runs(),
+                overruns: stats.overruns(),
+                exec_mean_us: stats.mean().as_micros() as u64,
+                exec_worst_us: stats.worst().as_micros() as u64,
+                jitter_mean_us: stats.mean_jitter().as_micros() as u64,
+                jitter_max_us: stats.max_jitter().as_micros() as u64,
+                histogram: stats.histogram().to_vec(),
+            })
+            .collect();
+        (
+            ApiResponse::new(ApiStatus::Success, ApiIpcStatsResponseBody {
+                histogram_bounds_us: EXEC_TIME_BUCKETS_US.to_vec(),
+                tasks,
+            }),
             ApiTickOutputMessage::none(),
         )
     }
@@ -234,85 +480,150 @@ impl ApiMessager {
             ApiIpcRequestType::Pos => {
                 self.validate_and_handle_body(request, &mut Self::handle_pos_request, input_data)
             }
+            ApiIpcRequestType::MsgSend => {
+                self.validate_and_handle_body(request, &mut Self::handle_msg_send_request,
+                                              input_data)
+            }
+            ApiIpcRequestType::MsgRecv => {
+                self.validate_and_handle_body(request, &mut Self::handle_msg_recv_request,
+                                              input_data)
+            }
+            ApiIpcRequestType::Log => {
+                self.validate_and_handle_body(request, &mut Self::handle_log_request, input_data)
+            }
+            ApiIpcRequestType::Status => {
+                self.validate_and_handle_body(request, &mut Self::handle_status_request,
+                                              input_data)
+            }
+            ApiIpcRequestType::Stats => {
+                self.validate_and_handle_body(request, &mut Self::handle_stats_request,
+                                              input_data)
+            }
+            ApiIpcRequestType::Handshake => self.handle_handshake_request(request),
         }
     }
 
+    /// Negotiates the protocol version and the encoding with the API. A
+    /// version cocos does not speak is answered with an
+    /// [ApiStatus::IncompatibleVersion] and the current version is kept. The
+    /// response is sent in the encoding of the handshake, and the negotiated
+    /// version and encoding are only used once it was sent.
+    fn handle_handshake_request(
+        &mut self,
+        request: &ApiIpcRequest,
+    ) -> Result<ApiTickOutputMessage, ApiError> {
+        let body: ApiIpcHandshakeRequestBody = self.validate_body(request)?;
+        if !protocol::supports_version(body.version) {
+            let err = ApiError::IncompatibleVersion(body.version);
+            let message = format!(
+                "Cocos cannot talk to this API, {}. Update cocos or the API so that they match.",
+                err
+            );
+            return Err(self.reject(ApiStatus::IncompatibleVersion, message, err));
+        }
+
+        // Legacy bodies are JSON strings, which are not worth encoding in
+        // anything else.
+        let encoding = if body.version >= API_OBJECT_BODY_VERSION {
+            ApiEncoding::negotiate(&body.encodings)
+        } else {
+            ApiEncoding::Json
+        };
+        log::info!(target: "system.api.messager",
+                   "The API speaks protocol version {} in {:?}.", body.version, encoding);
+        self.send_response_as(body.version, ApiResponse::new(ApiStatus::Success, ApiIpcHandshakeResponseBody {
+            version: API_PROTOCOL_VERSION,
+            min_version: *API_SUPPORTED_VERSIONS.start(),
+            robot_id: self.robot_id,
+            request_types: ApiIpcRequestType::ALL.to_vec(),
+            limits: ApiIpcLimitsBody {
+                net_msg_len: NET_MSG_LEN,
+                log_message_max_len: API_LOG_MESSAGE_MAX_LEN,
+                max_requests_per_tick: MAX_REQUESTS_PER_TICK,
+            },
+            encoding,
+        }))?;
+        self.protocol_version = body.version;
+        self.encoding = encoding;
+        Ok(ApiTickOutputMessage::none())
+    }
+
     /// This function validates an ApiIpcRequestBody assuming that the request
     /// headers are correct. Then, it calls the appropriate handler that is
     /// injected via the generic.
-    fn validate_and_handle_body<'a, BodyType>(
+    fn validate_and_handle_body<BodyType, ResponseType>(
         &mut self,
-        request: &'a ApiIpcRequest,
-        handler: &mut dyn FnMut(
-            &mut Self,
-            BodyType,
-            &ApiTickInputMessage,
-        ) -> (ApiResponse, ApiTickOutputMessage),
+        request: &ApiIpcRequest,
+        handler: &mut BodyHandler<BodyType, ResponseType>,
         input_data: &ApiTickInputMessage,
     ) -> Result<ApiTickOutputMessage, ApiError>
     where
-        BodyType: Deserialize<'a> + ValidatesApiIpcBody,
+        BodyType: DeserializeOwned + ValidatesApiIpcBody,
+        ResponseType: Serialize,
     {
         // Validate body
-        let body: Result<BodyType, ApiError> = self.validate_body(&request.body.as_str());
+        let body: Result<BodyType, ApiError> = self.validate_body(request);
         match body {
             Ok(valid_body) => {
                 // Call the appropriate handler if the body is valid.
                 let (response, state) = handler(self, valid_body, input_data);
-                match self.send_response(serde_json::to_string(&response).unwrap()) {
+                match self.send_response(response) {
                     Err(e) => Err(e),
                     Ok(()) => Ok(state),
                 }
             }
-            Err(error) => {
-                return Err(error);
-            }
+            Err(error) => Err(error),
         }
     }
 
-    /// Sends a response to the last request.
-    fn send_response(&mut self, response: String) -> Result<(), ApiError> {
+    /// Sends a response to the last request, encoded in the protocol version
+    /// of the session and the encoding of the request.
+    fn send_response<BodyType: Serialize>(
+        &mut self,
+        response: ApiResponse<BodyType>,
+    ) -> Result<(), ApiError> {
+        self.send_response_as(self.protocol_version, response)
+    }
+
+    /// Sends a response to the last request, encoded in the protocol
+    /// `version` and the encoding of the request.
+    fn send_response_as<BodyType: Serialize>(
+        &mut self,
+        version: u16,
+        response: ApiResponse<BodyType>,
+    ) -> Result<(), ApiError> {
+        let response = response.encode(version, self.reply_encoding);
         match &self.socket {
             None => Err(ApiError::SockNotReady),
-            Some(sock) => match sock.send(response.as_str(), 1) {
-                Ok(()) => Ok(()),
+            Some(sock) => match sock.send(response.as_slice(), zmq::DONTWAIT) {
+                Ok(()) => {
+                    self.awaiting_reply = false;
+                    Ok(())
+                }
                 Err(err) => Err(ApiError::ZMQError(err)),
             },
         }
     }
 
     /// This function is responsible for validating IPCRequestBody's.
-    fn validate_body<'a, RequestBodyType>(
+    fn validate_body<RequestBodyType>(
         &mut self,
-        body_str: &'a str,
+        request: &ApiIpcRequest,
     ) -> Result<RequestBodyType, ApiError>
     where
-        RequestBodyType: Deserialize<'a> + ValidatesApiIpcBody,
+        RequestBodyType: DeserializeOwned + ValidatesApiIpcBody,
     {
         // Parse the body, failing on error.
-        let body_opt: Result<RequestBodyType, serde_json::Error> = serde_json::from_str(body_str);
-
-        match body_opt {
+        match request.decode_body::<RequestBodyType>(self.protocol_version) {
             Ok(valid_body) => {
                 // If the body is successfully parsed, we must valiate its arguments.
                 if !valid_body.validate() {
-                    let response = serde_json::to_string(&ApiResponse {
-                        status: ApiStatus::InvalidRequestArgs,
-                        body: serde_json::to_string(&ApiIpcErrorResponseBody {
-                            message: "The body params are invalid. Are you trying something funny?"
-                                .to_string(),
-                        })
-                        .unwrap(),
-                    })
-                    .unwrap();
-                    match self.send_response(response) {
-                        Ok(()) => {
-                            return Err(ApiError::InvalidRequestBody);
-                        }
-                        Err(err) => {
-                            return Err(err);
-                        }
-                    }
+                    return Err(self.reject(
+                        ApiStatus::InvalidRequestArgs,
+                        "The body params are invalid. Are you trying something funny?"
+                            .to_string(),
+                        ApiError::InvalidRequestBody,
+                    ));
                 }
 
                 // If all is well, let us emit the body.
@@ -320,18 +631,11 @@ impl ApiMessager {
             }
             Err(error) => {
                 // If we did not successfully parse the body, let us notify the API.
-                let response = serde_json::to_string(&ApiResponse {
-                    status: ApiStatus::InvalidRequestBody,
-                    body: serde_json::to_string(&ApiIpcErrorResponseBody {
-                        message: "The body is invalid. Are you trying something funny?".to_string(),
-                    })
-                    .unwrap(),
-                })
-                .unwrap();
-                match self.send_response(response) {
-                    Ok(()) => Err(ApiError::InvalidRequestBody),
-                    Err(err) => Err(err),
-                }
+                Err(self.reject(
+                    ApiStatus::InvalidRequestBody,
+                    format!("The body is invalid, {}.", error),
+                    ApiError::InvalidRequestBody,
+                ))
             }
         }
     }
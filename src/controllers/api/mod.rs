@@ -2,33 +2,98 @@ pub mod errors;
 pub mod ipc_requests;
 pub mod ipc_responses;
 pub mod messager;
+pub mod protocol;
+pub mod supervisor;
+pub mod user_log;
 
 use errors::ApiError;
-use messager::ApiMessager;
-use std::io::Write;
+use log::Level;
+use messager::{ApiMessager, ApiMessagerState};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::{self, Write}};
 use std::{
-    borrow::Borrow,
     sync::{Arc, Mutex},
     time::Duration,
 };
-use subprocess::{Popen, PopenConfig, Redirection};
+use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
+use supervisor::ApiExitPolicy;
 
 use crate::models::api::{ApiTickInputMessage, ApiTickOutputMessage};
 
+/// The ZMQ endpoint the API child connects to unless configured otherwise.
+pub const DEFAULT_API_ENDPOINT: &str = "ipc:///tmp/cocos-api";
+
+/// How long a single API tick waits for the first request to arrive.
+const API_POLL_TIMEOUT: Duration = Duration::from_millis(5);
+
+/// How long the API process is given to exit after SIGTERM before it is
+/// killed.
+const API_TERMINATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Describes how the API child process is launched. The child is started as
+/// `<interpreter> -m <package> <endpoint> <robot_id>` and receives the user
+/// script on stdin.
+pub struct ApiDescriptor {
+    /// The interpreter executable, looked up in `PATH` if not absolute.
+    pub interpreter: String,
+    /// The python package implementing the API.
+    pub package: String,
+    /// The ZMQ endpoint cocos binds and the API connects to, either
+    /// `ipc://<path>` or `tcp://<host>:<port>`.
+    pub endpoint: String,
+    /// What to do once the user script exits or crashes.
+    pub on_exit: ApiExitPolicy,
+}
+
+impl ApiDescriptor {
+    /// Describes the legacy Python 2 API shipped as `cocos_py2`.
+    pub fn python2() -> Self {
+        Self {
+            interpreter: String::from("python2"),
+            package: String::from("cocos_py2"),
+            endpoint: String::from(DEFAULT_API_ENDPOINT),
+            on_exit: ApiExitPolicy::default(),
+        }
+    }
+
+    /// Describes the Python 3 API shipped as `cocos_py3`.
+    pub fn python3() -> Self {
+        Self {
+            interpreter: String::from("python3"),
+            package: String::from("cocos_py3"),
+            endpoint: String::from(DEFAULT_API_ENDPOINT),
+            on_exit: ApiExitPolicy::default(),
+        }
+    }
+}
+
 /// Exposes the API controller that controlls spawning and messaging the API
 /// child process.
 pub struct ApiController {
     running_process: Option<Arc<Mutex<Popen>>>,
     api_messager: ApiMessager,
+    api_descriptor: ApiDescriptor,
+    robot_id: u32,
     script: Vec<u8>,
 }
 
 impl ApiController {
     /// Spawns a new API Controller.
-    pub fn new(comm_uri: &'static str) -> ApiController {
+    ///
+    /// Arguments:
+    /// * `api_descriptor` - Selects the interpreter and API package to run,
+    ///   and the endpoint to communicate through.
+    /// * `robot_id` - The id of the robot. It is passed to the API and tags
+    ///   everything the user script says.
+    pub fn new(api_descriptor: ApiDescriptor, robot_id: u32) -> ApiController {
         ApiController {
             running_process: Option::None,
-            api_messager: ApiMessager::new(comm_uri),
+            api_messager: ApiMessager::new(api_descriptor.endpoint.clone(), API_POLL_TIMEOUT,
+                                           robot_id),
+            api_descriptor,
+            robot_id,
             script: vec![],
         }
     }
@@ -38,7 +103,7 @@ impl ApiController {
     /// This function can only be called when the script is paused and returns
     /// ApiError::General otherwise.
     pub fn set_script(&mut self, string: Vec<u8>) -> Result<(), ApiError> {
-        if !self.running_process.is_none() {
+        if self.running_process.is_some() {
             return Err(ApiError::General);
         }
         self.script = string;
@@ -46,24 +111,39 @@ impl ApiController {
     }
 
     /// Runs a tick of the controller. Must be called in a looped task.
+    ///
+    /// A tick waits at most [API_POLL_TIMEOUT] for a request, so it returns
+    /// regularly even if the user script is idle.
     pub fn run_tick(
         &mut self,
         data: ApiTickInputMessage,
     ) -> Result<ApiTickOutputMessage, ApiError> {
-        // TODO: Dangerous unwrap
         self.api_messager.run_tick(data)
     }
 
+    /// Returns the state of the underlying messager.
+    pub fn messager_state(&self) -> ApiMessagerState {
+        self.api_messager.state()
+    }
+
     /// Restarts the API process. Can be called to initally start the process.
     pub fn restart_api(&mut self) -> Result<(), ApiError> {
-        let kill_err = self.kill();
-        if kill_err.is_err() {
-            return Err(kill_err.unwrap_err());
-        }
+        self.kill()?;
+        let robot_id = self.robot_id.to_string();
         let api_proc = Popen::create(
-            &["python2", "-m", "cocos_py2", self.api_messager.comm_file],
+            &[
+                self.api_descriptor.interpreter.as_str(),
+                // Unbuffered, so that output is forwarded as it is printed.
+                "-u",
+                "-m",
+                self.api_descriptor.package.as_str(),
+                self.api_messager.comm_file.as_str(),
+                robot_id.as_str(),
+            ],
             PopenConfig {
                 stdin: Redirection::Pipe,
+                stdout: Redirection::Pipe,
+                stderr: Redirection::Pipe,
                 detached: true,
                 ..Default::default()
             },
@@ -81,6 +161,14 @@ impl ApiController {
             Some(proc_rc) => {
                 let proc_arc = proc_rc.clone();
                 let mut proc = proc_arc.lock().unwrap();
+                if let Some(stdout) = proc.stdout.take() {
+                    user_log::forward_stream(stdout, user_log::USER_STDOUT_TARGET,
+                                             Level::Info, self.robot_id);
+                }
+                if let Some(stderr) = proc.stderr.take() {
+                    user_log::forward_stream(stderr, user_log::USER_STDERR_TARGET,
+                                             Level::Warn, self.robot_id);
+                }
                 if let Some(mut stdin) = proc.stdin.take() {
                     if stdin.write_all(&self.script).is_err() {
                         return Err(ApiError::IO);
@@ -95,35 +183,73 @@ impl ApiController {
         }
     }
 
+    /// Returns whether the API process was started and has not been reaped
+    /// yet.
+    pub fn is_running(&self) -> bool {
+        self.running_process.is_some()
+    }
+
+    /// Checks whether the API process exited without blocking. If it did, the
+    /// messager is stopped and the exit status is returned. The exit status is
+    /// only returned once per process.
+    pub fn poll_exit(&mut self) -> Option<ExitStatus> {
+        let status = self.running_process.as_ref()?.lock().unwrap().poll()?;
+        self.running_process = None;
+        self.api_messager.stop();
+        Some(status)
+    }
+
+    /// Kills the child process and removes the IPC socket file. Returns the
+    /// exit status of the process, or None if no process was running.
+    pub fn shutdown(&mut self) -> Result<Option<ExitStatus>, ApiError> {
+        let status = self.kill()?;
+
+        if let Some(path) = self.api_messager.comm_file.strip_prefix("ipc://") {
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(_) => return Err(ApiError::IO),
+            }
+        }
+
+        Ok(status)
+    }
+
     /// Kills the child process and cleans up resources.
-    pub fn kill(&mut self) -> Result<(), ApiError> {
+    ///
+    /// The process is first asked to terminate and killed if it is still
+    /// alive after [API_TERMINATE_TIMEOUT]. Returns the exit status of the
+    /// process, or None if no process was running.
+    pub fn kill(&mut self) -> Result<Option<ExitStatus>, ApiError> {
         self.api_messager.stop();
 
         // Kill the process of whose Popen we hold. Note that a malicious actor
         // could hook into SIGTERM and prevent us from shutting down, so we
         // must, after a while, run kill if the process is not done.
-        match &self.running_process {
-            Some(proc) => {
-                let mut borrowed = proc.lock().unwrap();
-                if borrowed.terminate().is_err() {
-                    return Err(ApiError::ProcessError);
-                }
+        let proc = match self.running_process.take() {
+            Some(proc) => proc,
+            None => return Ok(None),
+        };
+        let mut proc = proc.lock().unwrap();
+
+        if let Some(status) = proc.poll() {
+            return Ok(Some(status));
+        }
+        if proc.terminate().is_err() {
+            return Err(ApiError::ProcessError);
+        }
 
-                // Kill if the process is alive after some time.
+        match proc.wait_timeout(API_TERMINATE_TIMEOUT) {
+            Ok(Some(status)) => Ok(Some(status)),
+            Ok(None) => {
                 // Hook into this you dirty python snake!
-                // TODO: Magic number
-                // TODO: Dangerous unwrap
-                match borrowed.wait_timeout(Duration::from_secs(2)).unwrap() {
-                    Some(_) => {
-                        return Err(ApiError::ProcessError);
-                    }
-                    None => {
-                        borrowed.kill(); // TODO: Handle error
-                    }
+                log::warn!(target: "system.api", "API process ignored SIGTERM, killing it.");
+                if proc.kill().is_err() {
+                    return Err(ApiError::ProcessError);
                 }
-                Ok(())
+                proc.wait().map(Some).map_err(|_| ApiError::ProcessError)
             }
-            None => Ok(()),
+            Err(_) => Err(ApiError::ProcessError),
         }
     }
 }
@@ -0,0 +1,88 @@
+/// This module defines the versions of the IPC protocol spoken with the API.
+///
+/// An API that never sends a [ApiIpcRequestType::Handshake] is assumed to
+/// speak [API_LEGACY_PROTOCOL_VERSION], in which request and response bodies
+/// are JSON documents encoded into a string. From version 2 on, bodies may be
+/// plain JSON objects.
+///
+/// The handshake is always encoded in JSON. In it, the API may offer other
+/// [ApiEncoding]s, and every later message is encoded in the one picked by
+/// cocos. A JSON handshake is accepted at any time, so that an API which lost
+/// the response to its handshake can repeat it, see [accepts_request].
+///
+/// [ApiIpcRequestType::Handshake]: super::ipc_requests::ApiIpcRequestType::Handshake
+use std::ops::RangeInclusive;
+
+use serde::Serialize;
+
+use super::ipc_requests::ApiIpcRequestType;
+
+/// The protocol version spoken by APIs that do not handshake.
+pub const API_LEGACY_PROTOCOL_VERSION: u16 = 1;
+
+/// The newest protocol version cocos speaks.
+pub const API_PROTOCOL_VERSION: u16 = 2;
+
+/// The first protocol version in which bodies may be plain JSON objects.
+pub const API_OBJECT_BODY_VERSION: u16 = 2;
+
+/// The protocol versions cocos accepts in a handshake.
+pub const API_SUPPORTED_VERSIONS: RangeInclusive<u16> =
+    API_LEGACY_PROTOCOL_VERSION..=API_PROTOCOL_VERSION;
+
+/// Returns whether cocos can talk to an API speaking `version`.
+pub fn supports_version(version: u16) -> bool {
+    API_SUPPORTED_VERSIONS.contains(&version)
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+/// Represents an encoding of the messages exchanged with the API. The messages
+/// hold the same fields in every encoding.
+pub enum ApiEncoding {
+    /// UTF-8 encoded JSON.
+    #[serde(rename = "json")]
+    Json,
+    /// MessagePack, with structures encoded as maps.
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl ApiEncoding {
+    /// Returns the encoding called `name` in the handshake.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(ApiEncoding::Json),
+            "msgpack" => Some(ApiEncoding::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Recognizes the encoding of a request by its first byte, which opens a
+    /// JSON object (`{`) or a MessagePack map (a fixmap, map 16 or map 32).
+    /// Leading whitespace is skipped.
+    pub fn detect(message: &[u8]) -> Option<Self> {
+        match message.iter().find(|byte| !byte.is_ascii_whitespace())? {
+            b'{' => Some(ApiEncoding::Json),
+            0x80..=0x8f | 0xde | 0xdf => Some(ApiEncoding::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Picks the first of the encodings `offered` by the API that cocos
+    /// speaks, falling back to JSON.
+    pub fn negotiate(offered: &[String]) -> Self {
+        offered
+            .iter()
+            .find_map(|name| ApiEncoding::from_name(name))
+            .unwrap_or(ApiEncoding::Json)
+    }
+}
+
+/// Returns whether a request of `request_type` sent in `received` is handled
+/// once `negotiated` was picked in the handshake. Only the handshake may be
+/// sent in another encoding, since the API keeps speaking JSON until it
+/// received the response to its handshake.
+pub fn accepts_request(negotiated: ApiEncoding, received: ApiEncoding,
+                       request_type: ApiIpcRequestType) -> bool {
+    received == negotiated || request_type == ApiIpcRequestType::Handshake
+}
@@ -0,0 +1,86 @@
+/// This module decides what happens once the API child process exits.
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use subprocess::ExitStatus;
+
+/// The largest power of two the restart backoff is multiplied with.
+const MAX_BACKOFF_SHIFT: u32 = 6;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+/// Represents the action taken once the user script exits or crashes. The
+/// motors are halted regardless of the policy.
+pub enum ApiExitPolicy {
+    /// Keep the motors halted and stay idle until cocos is restarted.
+    #[default]
+    Idle,
+    /// Restart the user script up to `max_restarts` times. The delay before
+    /// each restart starts at `backoff` and doubles with every attempt. Once
+    /// the restarts are exhausted, cocos stays idle.
+    Restart {
+        max_restarts: u32,
+        #[serde(rename = "backoff_ms", with = "crate::config::serde_millis")]
+        backoff: Duration,
+    },
+    /// Exit cocos, forwarding the exit code of the user script.
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Represents what the master controller must do after the user script
+/// exited.
+pub enum ApiExitAction {
+    /// Stay idle with the motors halted.
+    Idle,
+    /// Restart the user script after the given delay.
+    Restart(Duration),
+    /// Exit cocos with the given exit code.
+    Exit(i32),
+}
+
+/// Applies an [ApiExitPolicy], keeping track of how often the user script
+/// was restarted.
+pub struct ApiSupervisor {
+    policy: ApiExitPolicy,
+    restarts: u32,
+}
+
+impl ApiSupervisor {
+    pub fn new(policy: ApiExitPolicy) -> Self {
+        Self { policy, restarts: 0 }
+    }
+
+    /// Returns the number of times the user script was restarted.
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Records that the user script exited with `status` and returns the
+    /// action to take.
+    pub fn on_exit(&mut self, status: ExitStatus) -> ApiExitAction {
+        match self.policy {
+            ApiExitPolicy::Idle => ApiExitAction::Idle,
+            ApiExitPolicy::Exit => ApiExitAction::Exit(exit_code(status)),
+            ApiExitPolicy::Restart { max_restarts, backoff } => {
+                if self.restarts >= max_restarts {
+                    return ApiExitAction::Idle;
+                }
+                let delay = backoff * (1u32 << self.restarts.min(MAX_BACKOFF_SHIFT));
+                self.restarts += 1;
+                ApiExitAction::Restart(delay)
+            }
+        }
+    }
+}
+
+/// Maps the exit status of the user script to a process exit code, following
+/// the shell convention of `128 + signal` for killed processes.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    match status {
+        ExitStatus::Exited(code) => code as i32,
+        ExitStatus::Signaled(signal) => 128 + signal as i32,
+        ExitStatus::Other(code) => code,
+        ExitStatus::Undetermined => 1,
+    }
+}
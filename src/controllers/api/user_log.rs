@@ -0,0 +1,60 @@
+/// This module forwards everything the user script says to the `log` facade
+/// under the `user.*` targets.
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    thread::{self, JoinHandle},
+};
+
+use log::Level;
+
+/// The target of records emitted through the IPC `Log` request.
+pub const USER_LOG_TARGET: &str = "user.log";
+/// The target of lines written by the user script to stdout.
+pub const USER_STDOUT_TARGET: &str = "user.stdout";
+/// The target of lines written by the user script to stderr.
+pub const USER_STDERR_TARGET: &str = "user.stderr";
+
+/// Maps a python `logging` level to the closest [Level].
+pub fn level_from_python(level: u8) -> Level {
+    match level {
+        40.. => Level::Error,
+        30..=39 => Level::Warn,
+        20..=29 => Level::Info,
+        10..=19 => Level::Debug,
+        _ => Level::Trace,
+    }
+}
+
+/// Emits a single line said by the user script of robot `robot_id`.
+pub fn log_line(target: &'static str, level: Level, robot_id: u32, line: &str) {
+    log::log!(target: target, level, "[robot {}] {}", robot_id, line);
+}
+
+/// Spawns a thread which emits every line read from `stream` until EOF, which
+/// happens once the user script exits.
+pub fn forward_stream(
+    stream: File,
+    target: &'static str,
+    level: Level,
+    robot_id: u32,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line);
+                    log_line(target, level, robot_id, text.trim_end_matches(&['\r', '\n'][..]));
+                }
+                Err(err) => {
+                    log::warn!(target: "system.api", "Could not read {}: {}", target, err);
+                    break;
+                }
+            }
+        }
+    })
+}
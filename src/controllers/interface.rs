@@ -19,7 +19,7 @@ pub trait HandlesPyApi {
     /// # Arguments:
     ///
     /// * `rel_speed` - The relative speed of the two motors. Values must be
-    ///                 between -1 and 1.
+    ///   between -1 and 1.
     fn on_set_rel_speed(&mut self, rel_speed: MotorPower);
 
     /// Called by the python API to set the current LED color.
@@ -1,53 +1,108 @@
 use log;
 use std::{
-    sync::{Arc, Mutex, RwLock},
-    thread::{self, JoinHandle, sleep},
-    time::{Duration, SystemTime},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::{
     config::AppConfig,
     drivers::{led_driver::LedDriver, nucifera_driver::NuciferaDriver},
-    io::interface::{gpio::DrivesGpio, pwm::DrivesPwm, uart::DrivesUart},
+    io::interface::{
+        gpio::DrivesGpio,
+        net::{BroadcastsData, ListensForData},
+        pwm::DrivesPwm,
+        uart::DrivesUart,
+    },
     models::{
         api::ApiTickInputMessage, led_color::LedColor, motor_power::MotorPower, position::Position,
-    }, controllers::api,
+    },
 };
 
-use super::{api::ApiController, motor::MotorController};
+use super::{
+    api::{
+        errors::ApiError,
+        supervisor::{ApiExitAction, ApiSupervisor},
+        ApiController,
+    },
+    motor::{MotorController, MotorControllerError},
+    network::NetworkController,
+    scheduler::{RealTimeClock, Scheduler},
+    shutdown::{signal_exit_code, ShutdownHandle},
+    tasks::{LedTask, LoggingTask, MotionTask, NetworkTask, PositioningTask},
+    watchdog::{CommandWatchdog, WatchdogEvent},
+};
 
-fn spawn_task<F, T>(f: F, period: Duration, name: &'static str) -> JoinHandle<T>
-where
-    F: Fn() -> T,
-    F: Send + 'static,
-    T: Send + 'static,
-{
-    thread::spawn(move || loop {
-        let start_time = SystemTime::now();
-        f();
-        let stop_time = SystemTime::now();
-        let delta = stop_time.duration_since(start_time).unwrap();
-        if delta > period {
-            log::warn!("{name} could not be completed in time.");
+/// The exit code returned when the hardware or the API could not be
+/// initialized.
+pub const INIT_FAILURE_EXIT_CODE: i32 = 1;
+
+#[derive(Debug)]
+/// Represents the errors that prevent the [MasterController] from starting.
+pub enum MasterError {
+    /// Thrown when the motors could not be brought into a safe state.
+    Motor(MotorControllerError),
+    /// Thrown when the user script could not be launched.
+    Api(ApiError),
+}
+
+impl fmt::Display for MasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MasterError::Motor(err) => write!(f, "could not block the motors: {}", err),
+            MasterError::Api(err) => write!(f, "could not start the API: {}", err),
         }
-        thread::sleep(period.saturating_sub(delta));
-    })
+    }
+}
+
+impl std::error::Error for MasterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MasterError::Motor(err) => Some(err),
+            MasterError::Api(err) => Some(err),
+        }
+    }
+}
+
+/// How often idle loops check whether a shutdown was requested.
+const SHUTDOWN_POLL_PERIOD: Duration = Duration::from_millis(10);
+
+/// Sleeps for `duration`, waking up early if `shutdown` is set. Returns
+/// whether a shutdown was requested.
+fn sleep_unless_shutdown(duration: Duration, shutdown: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    while !shutdown.load(Ordering::Relaxed) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return false;
+        }
+        thread::sleep(remaining.min(SHUTDOWN_POLL_PERIOD));
+    }
+    true
 }
 
 pub struct MasterController<
     GpioDriver: DrivesGpio + Send + 'static,
     PwmDriver: DrivesPwm + Send + 'static,
     UartDriver: DrivesUart + Send + 'static,
+    NetDriver: BroadcastsData + ListensForData + Send + 'static,
 > {
     gpio_driver: Arc<Mutex<GpioDriver>>,
     pwm_driver: Arc<Mutex<PwmDriver>>,
     uart_driver: Arc<Mutex<UartDriver>>,
 
     led_driver: LedDriver,
-    nucifera_driver: NuciferaDriver,
+    nucifera_driver: Arc<Mutex<NuciferaDriver>>,
 
     motor_controller: MotorController,
+    command_watchdog: Arc<Mutex<CommandWatchdog>>,
     api_controller: ApiController,
+    api_supervisor: ApiSupervisor,
+    network_controller: Arc<Mutex<NetworkController<NetDriver>>>,
 
     // TODO: Does not need to be an ARC
     current_pos: Arc<RwLock<Position>>,
@@ -55,19 +110,24 @@ pub struct MasterController<
     current_mot_pow: Arc<RwLock<MotorPower>>,
     // TODO: Does not need to be an ARC
     current_led_color: Arc<RwLock<LedColor>>,
+
+    /// Requests all tasks to stop.
+    shutdown: ShutdownHandle,
 }
 
 impl<
         GpioDriver: DrivesGpio + Send + 'static,
         PwmDriver: DrivesPwm + Send + 'static,
         UartDriver: DrivesUart + Send + 'static,
-    > MasterController<GpioDriver, PwmDriver, UartDriver>
+        NetDriver: BroadcastsData + ListensForData + Send + 'static,
+    > MasterController<GpioDriver, PwmDriver, UartDriver, NetDriver>
 {
     pub fn new(
         app_cfg: &AppConfig,
         gpio_driver: GpioDriver,
         pwm_driver: PwmDriver,
         uart_driver: UartDriver,
+        net_driver: NetDriver,
     ) -> Self {
         Self {
             gpio_driver: Arc::new(Mutex::new(gpio_driver)),
@@ -75,138 +135,266 @@ impl<
             uart_driver: Arc::new(Mutex::new(uart_driver)),
             led_driver: LedDriver::new(app_cfg.led),
 
-            api_controller: ApiController::new("ipc:///tmp/cocos-api"),
+            api_controller: ApiController::new(app_cfg.api.clone(), app_cfg.robot_id),
+            api_supervisor: ApiSupervisor::new(app_cfg.api.on_exit),
+            network_controller: Arc::new(Mutex::new(NetworkController::new(net_driver))),
 
-            nucifera_driver: NuciferaDriver::new(app_cfg.nucifera),
+            nucifera_driver: Arc::new(Mutex::new(NuciferaDriver::new(app_cfg.nucifera))),
             motor_controller: MotorController::new(app_cfg.mot_left, app_cfg.mot_right),
+            command_watchdog: Arc::new(Mutex::new(CommandWatchdog::new(app_cfg.watchdog))),
 
             current_pos: Arc::new(RwLock::new(Position::zero())),
             current_mot_pow: Arc::new(RwLock::new(MotorPower::zero())),
             current_led_color: Arc::new(RwLock::new(LedColor::off())),
+
+            shutdown: ShutdownHandle::new(),
         }
     }
 
-    fn init(&mut self) {
+    /// Returns the handle which shuts cocos down gracefully. This is meant to
+    /// be used from a signal handler.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Returns the flag which is set once cocos shuts down.
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(self.shutdown.flag())
+    }
+
+    fn init(&mut self) -> Result<(), MasterError> {
         let gpio_driver_rc = self.gpio_driver.clone();
         let mut gpio_driver = gpio_driver_rc.lock().unwrap();
 
-        self.motor_controller
-            .block(&mut *gpio_driver)
-            .expect("Could not block the motor controller on start.");
-
-        self.api_controller.restart_api().expect("Could not spawn the child process.");
+        self.motor_controller.block(&mut *gpio_driver).map_err(MasterError::Motor)?;
+        self.api_controller.restart_api().map_err(MasterError::Api)?;
 
         log::debug!(target: "system.master", "Successfully initialized");
+        Ok(())
     }
 
-    fn spawn_tasks(&mut self) {
-        // Channel definitions.
+    /// Spawns all tasks and supervises the user script. Returns the exit code
+    /// of cocos once the [ApiExitPolicy](super::api::supervisor::ApiExitPolicy)
+    /// requests cocos to exit, or `128 + signal` once a signal shut it down.
+    fn spawn_tasks(&mut self) -> i32 {
+        let shutdown_handle = &self.shutdown;
+        let shutdown = shutdown_handle.flag();
 
-        // Logging task.
-        let current_pos = Arc::clone(&self.current_pos);
-        let current_mot_pow = Arc::clone(&self.current_mot_pow);
-        let current_led_color = Arc::clone(&self.current_led_color);
-        let logging_task = spawn_task(
-            move || {
-                // TODO: Potentially dangerous unwrap
-                let pos = current_pos.read().unwrap().clone();
-                let mot_pow = current_mot_pow.read().unwrap().clone();
-                let led_color = current_led_color.read().unwrap().clone();
-                log::info!(target: "system.master.position", "Current: {}", pos);
-                log::info!(target: "system.master.motor_power", "Current: {}", mot_pow);
-                log::info!(target: "system.master.led_color", "Current: {}", led_color)
-            },
-            Duration::from_millis(100),
-            "Logging Task",
-        );
-
-        // Positioning Task
-        let pos_uart_driver = Arc::clone(&self.uart_driver);
-        let nucifera_driver = self.nucifera_driver; // TODO: Should be ref
-        let current_pos = Arc::clone(&self.current_pos);
-        let positioning_task = spawn_task(
-            move || {
-                let uart_driver = pos_uart_driver.lock().unwrap();
-                let new_pos = nucifera_driver.read_current_position(&uart_driver);
-                // TODO: Potentially dangerous unwrap
-                *current_pos.write().unwrap() = new_pos;
-            },
-            Duration::from_millis(1),
-            "Position Input Task",
-        );
-
-        // LED Task
-        let current_led_color = Arc::clone(&self.current_led_color);
-        let pwm_driver = Arc::clone(&self.pwm_driver);
-        let led_controller = self.led_driver; // TODO: Should be ref
-        let led_task = spawn_task(move || {
-            let mut pwm = pwm_driver.lock().unwrap();
-            let led = current_led_color.read().unwrap();
-            led_controller.set_color(*led, &mut *pwm);
-        }, Duration::from_millis(10), "LED Task",);
+        // The periodic tasks all run on one thread, driven by one clock.
+        let mut scheduler = Scheduler::new(RealTimeClock::new());
+        scheduler.add_tick1("Position Input Task", PositioningTask {
+            uart_driver: Arc::clone(&self.uart_driver),
+            nucifera_driver: Arc::clone(&self.nucifera_driver),
+            current_pos: Arc::clone(&self.current_pos),
+            pos_stale: false,
+        });
+        scheduler.add_tick10("Motion Output Task", MotionTask {
+            gpio_driver: Arc::clone(&self.gpio_driver),
+            pwm_driver: Arc::clone(&self.pwm_driver),
+            motor_controller: self.motor_controller,
+            command_watchdog: Arc::clone(&self.command_watchdog),
+            current_mot_pow: Arc::clone(&self.current_mot_pow),
+            failing: false,
+        });
+        scheduler.add_tick10("LED Task", LedTask {
+            pwm_driver: Arc::clone(&self.pwm_driver),
+            led_driver: self.led_driver,
+            current_led_color: Arc::clone(&self.current_led_color),
+            failing: false,
+        });
+        scheduler.add_tick10("Network Task", NetworkTask {
+            network_controller: Arc::clone(&self.network_controller),
+        });
+        scheduler.add_tick100("Logging Task", LoggingTask {
+            current_pos: Arc::clone(&self.current_pos),
+            current_mot_pow: Arc::clone(&self.current_mot_pow),
+            current_led_color: Arc::clone(&self.current_led_color),
+            task_stats: scheduler.stats(),
+            ticks: 0,
+        });
+        let task_stats = scheduler.stats();
 
         // API Task
         let current_pos = Arc::clone(&self.current_pos);
         let current_mot_pow = Arc::clone(&self.current_mot_pow);
         let current_led_color = Arc::clone(&self.current_led_color);
+        let network_controller = Arc::clone(&self.network_controller);
+        let api_watchdog = Arc::clone(&self.command_watchdog);
         let api_controller = &mut self.api_controller;
+        let api_supervisor = &mut self.api_supervisor;
+
         thread::scope(|s| {
-            s.spawn(move || {
-                loop {
-                    let pos = current_pos.read().unwrap().clone();
-                    let tick_data = ApiTickInputMessage { bot_pos: pos };
+            let api_task = s.spawn(move || {
+                let mut last_state = api_controller.messager_state();
+                while !shutdown.load(Ordering::Relaxed) {
+                    if let Some(status) = api_controller.poll_exit() {
+                        // Never leave the last motor command latched once
+                        // nobody is in control anymore.
+                        *current_mot_pow.write().unwrap() = MotorPower::locked();
+                        log::warn!(target: "system.master.api",
+                                   "User script exited with {:?}, motors halted.", status);
+                        // The user script likely received the same signal.
+                        if shutdown.load(Ordering::Relaxed) {
+                            break;
+                        }
+
+                        match api_supervisor.on_exit(status) {
+                            ApiExitAction::Idle => {
+                                log::info!(target: "system.master.api", "Staying idle.");
+                            }
+                            ApiExitAction::Restart(delay) => {
+                                log::info!(target: "system.master.api",
+                                           "Restarting user script in {:?} (restart {}).",
+                                           delay, api_supervisor.restarts());
+                                if sleep_unless_shutdown(delay, shutdown) {
+                                    break;
+                                }
+                                if let Err(err) = api_controller.restart_api() {
+                                    log::error!(target: "system.master.api",
+                                                "Could not restart user script: {}", err);
+                                }
+                            }
+                            ApiExitAction::Exit(code) => {
+                                log::info!(target: "system.master.api",
+                                           "Exiting with code {}.", code);
+                                return code;
+                            }
+                        }
+                    }
+
+                    if !api_controller.is_running() {
+                        sleep_unless_shutdown(Duration::from_millis(100), shutdown);
+                        continue;
+                    }
+
+                    let pos = *current_pos.read().unwrap();
+                    let net_inbox = network_controller.lock().unwrap().inbox();
+                    let watchdog_tripped = api_watchdog.lock().unwrap().is_tripped();
+                    let motors_locked = watchdog_tripped
+                        || current_mot_pow.read().unwrap().is_locked();
+                    let tick_data = ApiTickInputMessage {
+                        bot_pos: pos,
+                        net_inbox,
+                        motors_locked,
+                        watchdog_tripped,
+                        task_stats: Arc::clone(&task_stats),
+                    };
                     match api_controller.run_tick(tick_data) {
                         Ok(api_data) => {
-                            log::debug!("{:?}", api_data);
+                            log::trace!("{:?}", api_data);
                             if let Some(mot_pow) = api_data.request_motor_power {
                                 *current_mot_pow.write().unwrap() = mot_pow;
+                                let event = api_watchdog.lock().unwrap().feed(Instant::now());
+                                if event == Some(WatchdogEvent::Released) {
+                                    log::info!(target: "system.master.watchdog",
+                                               "Velocity commands resumed, motors released.");
+                                }
                             }
                             if let Some(led_color) = api_data.request_led_color {
                                 *current_led_color.write().unwrap() = led_color;
                             }
+                            for message in api_data.request_net_send {
+                                let mut network = network_controller.lock().unwrap();
+                                if let Err(err) = network.send(&message) {
+                                    log::warn!(target: "system.master.network",
+                                               "Could not send: {}", err);
+                                }
+                            }
+                            if let Some(id) = api_data.request_net_ack {
+                                network_controller.lock().unwrap().acknowledge(id);
+                            }
                         }
                         Err(err) => {
-                            log::error!(target: "system.master.api", "Received error: {:?}", err);
+                            log::error!(target: "system.master.api", "Received error: {}", err);
+                            // Avoid spinning if the messager is not usable.
+                            thread::sleep(Duration::from_millis(5));
                         }
                     }
 
-                    thread::sleep(Duration::from_millis(5));
+                    let state = api_controller.messager_state();
+                    if state != last_state {
+                        log::info!(target: "system.master.api",
+                                   "Messager state changed: {:?} -> {:?}", last_state, state);
+                        last_state = state;
+                    }
                 }
+                shutdown_handle.signal().map_or(0, signal_exit_code)
             });
-        });
 
-        // Driving Task
-        let motor_controller = self.motor_controller; // TODO: Should be ref
-        let current_mot_pow = Arc::clone(&self.current_mot_pow);
-        let gpio_driver = Arc::clone(&self.gpio_driver);
-        let pwm_driver = Arc::clone(&self.pwm_driver);
-        spawn_task(
-            move || {
-                let mut gpio = gpio_driver.lock().unwrap();
-                let mut pwm = pwm_driver.lock().unwrap();
-                let mot_pow = current_mot_pow.read().unwrap();
-                motor_controller.set_vel(*mot_pow, &mut *gpio, &mut *pwm);
-            },
-            Duration::from_millis(10),
-            "Motion Output Task",
-        );
-
-        log::debug!(target: "system.master", "Spawned tasks");
-
-        // Idle task.
-        loop {
-            thread::sleep(Duration::from_millis(100));
+            let scheduler_task = s.spawn(move || scheduler.run_until(shutdown));
+
+            log::debug!(target: "system.master", "Spawned tasks");
+
+            // Idle task. The API task finishes either on shutdown or once the
+            // exit policy requests cocos to exit.
+            while !api_task.is_finished() {
+                thread::sleep(Duration::from_millis(100));
+            }
+            let exit_code = api_task.join().unwrap_or(1);
+
+            shutdown.store(true, Ordering::Relaxed);
+            if scheduler_task.join().is_err() {
+                log::error!(target: "system.master", "A task panicked.");
+            }
+            log::debug!(target: "system.master", "Stopped tasks");
+            exit_code
+        })
+    }
+
+    /// Brings the hardware into a safe state and stops the user script. Must
+    /// only be called once all tasks are stopped. Also sets the
+    /// [shutdown flag](Self::shutdown_flag), which stops whatever the caller
+    /// runs alongside cocos.
+    fn shutdown(&mut self) {
+        self.shutdown.flag().store(true, Ordering::Relaxed);
+        {
+            let mut gpio = self.gpio_driver.lock().unwrap();
+            let mut pwm = self.pwm_driver.lock().unwrap();
+            if let Err(err) = self.motor_controller.halt(&mut *gpio, &mut *pwm) {
+                log::error!(target: "system.master", "Could not halt the motors: {}", err);
+            }
+            if let Err(err) = self.led_driver.set_color(LedColor::off(), &mut *pwm) {
+                log::error!(target: "system.master", "Could not turn the LED off: {}", err);
+            }
+        }
+
+        match self.api_controller.shutdown() {
+            Ok(Some(status)) => {
+                log::info!(target: "system.master.api", "User script stopped with {:?}.", status);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                log::error!(target: "system.master.api", "Could not stop the API: {}", err);
+            }
         }
+
+        log::info!(target: "system.master", "Shut down.");
     }
 
-    pub fn run(&mut self) {
-        self.init();
-        self.spawn_tasks();
+    /// Runs cocos until the exit policy of the user script requests cocos to
+    /// exit or a shutdown is requested through the
+    /// [shutdown handle](Self::shutdown_handle). The motors are
+    /// halted and the user script is stopped before returning the exit code.
+    pub fn run(&mut self) -> i32 {
+        if let Err(err) = self.init() {
+            log::error!(target: "system.master", "Could not initialize: {}", err);
+            self.shutdown();
+            return INIT_FAILURE_EXIT_CODE;
+        }
+        let exit_code = self.spawn_tasks();
+        self.shutdown();
+        exit_code
     }
 
-    pub fn run_with_script(&mut self, script: String) {
-        self.api_controller.kill();
-        self.api_controller.set_script(script.as_bytes().to_vec());
-        self.run();
+    pub fn run_with_script(&mut self, script: String) -> i32 {
+        if let Err(err) = self.api_controller.kill() {
+            log::error!(target: "system.master.api", "Could not stop the API: {}", err);
+        }
+        if let Err(err) = self.api_controller.set_script(script.as_bytes().to_vec()) {
+            log::error!(target: "system.master.api", "Could not set the user script: {}", err);
+            self.shutdown.flag().store(true, Ordering::Relaxed);
+            return INIT_FAILURE_EXIT_CODE;
+        }
+        self.run()
     }
 }
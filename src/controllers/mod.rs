@@ -1,4 +1,10 @@
-mod api;
-mod interface;
+pub mod api;
+pub mod interface;
 pub mod master;
-mod motor;
+pub mod motor;
+pub mod network;
+pub mod scheduler;
+pub mod shutdown;
+mod tasks;
+pub mod task_stats;
+pub mod watchdog;
@@ -1,15 +1,58 @@
+use std::fmt;
+
 use crate::{
-    drivers::motor_driver::{MotorDescriptor, MotorDirection, MotorDriver},
+    drivers::motor_driver::{MotorDescriptor, MotorDriver, MotorError},
     io::interface::{gpio::DrivesGpio, pwm::DrivesPwm},
-    models::motor_power::{MotorPower, MotorPowerQuadrant},
+    models::motor_power::MotorPower,
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Identifies one of the two wheels.
+pub enum MotorSide {
+    Left,
+    Right,
+}
+
+impl fmt::Display for MotorSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MotorSide::Left => "left",
+            MotorSide::Right => "right",
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum MotorControllerError {
-    IOError,
+    /// Thrown when the motor on `side` could not be driven.
+    IO { side: MotorSide, cause: MotorError },
+}
+
+impl fmt::Display for MotorControllerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MotorControllerError::IO { side, cause } => write!(f, "{} motor: {}", side, cause),
+        }
+    }
+}
+
+impl std::error::Error for MotorControllerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MotorControllerError::IO { cause, .. } => Some(cause),
+        }
+    }
+}
+
+/// Tags an error of the motor on `side`.
+fn on(side: MotorSide) -> impl FnOnce(MotorError) -> MotorControllerError {
+    move |cause| MotorControllerError::IO { side, cause }
 }
 
 #[derive(Clone, Copy)]
+/// Controls the two wheels of the coachbot. Each wheel is driven
+/// independently, its direction being derived from the sign of the requested
+/// power and the polarity in its [MotorDescriptor].
 pub struct MotorController {
     left_motor_driver: MotorDriver,
     right_motor_driver: MotorDriver,
@@ -24,35 +67,35 @@ impl MotorController {
     }
 
     pub fn block(&self, gpio_driver: &mut impl DrivesGpio) -> Result<(), MotorControllerError> {
-        let l = self.left_motor_driver.block(gpio_driver);
-        if l.is_err() {
-            return Err(MotorControllerError::IOError);
-        }
+        self.left_motor_driver.block(gpio_driver).map_err(on(MotorSide::Left))?;
+        self.right_motor_driver.block(gpio_driver).map_err(on(MotorSide::Right))?;
+        Ok(())
+    }
 
-        let r = self.right_motor_driver.block(gpio_driver);
-        if r.is_err() {
-            return Err(MotorControllerError::IOError);
-        }
+    /// Blocks both motors and zeroes their PWM output, leaving nothing
+    /// running on the motor pins.
+    pub fn halt(
+        &self,
+        gpio_driver: &mut impl DrivesGpio,
+        pwm_driver: &mut impl DrivesPwm
+    ) -> Result<(), MotorControllerError> {
+        self.block(gpio_driver)?;
 
+        self.left_motor_driver.set_speed(0.0, pwm_driver).map_err(on(MotorSide::Left))?;
+        self.right_motor_driver.set_speed(0.0, pwm_driver).map_err(on(MotorSide::Right))?;
         Ok(())
     }
 
     pub fn unblock(&self,
                    gpio_driver: &mut impl DrivesGpio,
-                   pwm_dirver: &mut impl DrivesPwm) -> Result<(), MotorControllerError> {
-        let l = self.left_motor_driver.unblock(gpio_driver);
-        if l.is_err() {
-            return Err(MotorControllerError::IOError);
-        }
-
-        let r = self.right_motor_driver.unblock(gpio_driver);
-        if r.is_err() {
-            return Err(MotorControllerError::IOError);
-        }
-
+                   _pwm_driver: &mut impl DrivesPwm) -> Result<(), MotorControllerError> {
+        self.left_motor_driver.unblock(gpio_driver).map_err(on(MotorSide::Left))?;
+        self.right_motor_driver.unblock(gpio_driver).map_err(on(MotorSide::Right))?;
         Ok(())
     }
 
+    /// Sets the velocity of both wheels. A locked [MotorPower] blocks both
+    /// motors.
     pub fn set_vel(
         &self,
         vel: MotorPower,
@@ -63,76 +106,12 @@ impl MotorController {
             return self.block(gpio_driver);
         }
 
-        if let Err(err) = self.unblock(gpio_driver, pwm_driver) {
-            return Err(err);
-        }
-
-        match vel.as_quadrant() {
-            MotorPowerQuadrant::PLeftPRight => {
-                if let Err(err) = self
-                    .left_motor_driver
-                    .set_direction(MotorDirection::CounterClockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-                if let Err(err) = self
-                    .right_motor_driver
-                    .set_direction(MotorDirection::Clockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-            }
-            MotorPowerQuadrant::PLeftNRight => {
-                if let Err(err) = self
-                    .left_motor_driver
-                    .set_direction(MotorDirection::CounterClockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-                if let Err(err) = self
-                    .right_motor_driver
-                    .set_direction(MotorDirection::CounterClockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-            }
-            MotorPowerQuadrant::NLeftPRight => {
-                if let Err(err) = self
-                    .left_motor_driver
-                    .set_direction(MotorDirection::Clockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-                if let Err(err) = self
-                    .right_motor_driver
-                    .set_direction(MotorDirection::Clockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-            }
-            MotorPowerQuadrant::NLeftNRight => {
-                if let Err(err) = self
-                    .left_motor_driver
-                    .set_direction(MotorDirection::Clockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-                if let Err(err) = self
-                    .right_motor_driver
-                    .set_direction(MotorDirection::CounterClockwise, gpio_driver)
-                {
-                    return Err(MotorControllerError::IOError);
-                }
-            }
-        }
-
-        if let Err(err) = self.left_motor_driver.set_speed(vel.pow_left(), pwm_driver) {
-            return Err(MotorControllerError::IOError);
-        };
-        if let Err(err) = self.left_motor_driver.set_speed(vel.pow_right(), pwm_driver) {
-            return Err(MotorControllerError::IOError);
-        };
-
+        self.left_motor_driver
+            .drive(vel.left(), gpio_driver, pwm_driver)
+            .map_err(on(MotorSide::Left))?;
+        self.right_motor_driver
+            .drive(vel.right(), gpio_driver, pwm_driver)
+            .map_err(on(MotorSide::Right))?;
         Ok(())
     }
 }
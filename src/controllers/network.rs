@@ -0,0 +1,141 @@
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::io::interface::net::{BroadcastsData, ListensForData, NetError};
+
+/// The two bytes that open every coachbot network packet.
+const NET_PACKET_MAGIC: [u8; 2] = *b"CB";
+
+/// The length of the packet header in bytes.
+const NET_HEADER_LEN: usize = 8;
+
+/// The total, fixed length of a packet sent over the wire.
+///
+/// A packet is laid out as follows (all multi-byte values are little endian):
+///
+/// | Offset | Size          | Field                                  |
+/// |--------|---------------|----------------------------------------|
+/// | 0      | 2             | Magic, always `CB`                     |
+/// | 2      | 2             | Length of the message (`u16`)          |
+/// | 4      | 4             | Sender id (`u32`)                      |
+/// | 8      | [NET_MSG_LEN] | Message, padded with zeros             |
+pub const NET_PACKET_LEN: usize = 64;
+
+/// The maximum length of a single message. Longer messages are trimmed.
+pub const NET_MSG_LEN: usize = NET_PACKET_LEN - NET_HEADER_LEN;
+
+/// The maximum number of messages held in the inbox. When the inbox is full,
+/// the oldest message is dropped.
+pub const NET_INBOX_CAPACITY: usize = 64;
+
+/// Trims `message` to at most [NET_MSG_LEN] bytes without splitting a
+/// character.
+pub fn trim_message(message: &str) -> &str {
+    let mut len = message.len().min(NET_MSG_LEN);
+    while !message.is_char_boundary(len) {
+        len -= 1;
+    }
+    &message[..len]
+}
+
+#[derive(Clone, Debug)]
+/// Represents a message received from another coachbot.
+pub struct NetMessage {
+    /// A locally unique, monotonically increasing message id.
+    pub id: u64,
+    /// The id of the sender.
+    pub sender: u32,
+    /// The message contents.
+    pub data: Vec<u8>,
+}
+
+/// Exposes the network controller which broadcasts fixed-size messages to
+/// other coachbots and keeps a bounded inbox of received messages.
+pub struct NetworkController<NetDriver: BroadcastsData + ListensForData> {
+    net_driver: NetDriver,
+    /// Identifies packets sent by this controller so they can be filtered out
+    /// when they are received back.
+    sender_id: u32,
+    inbox: VecDeque<NetMessage>,
+    next_message_id: u64,
+}
+
+impl<NetDriver: BroadcastsData + ListensForData> NetworkController<NetDriver> {
+    pub fn new(net_driver: NetDriver) -> Self {
+        // The sender id only needs to be unique among the coachbots
+        // listening on the same network.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+
+        Self {
+            net_driver,
+            sender_id: std::process::id().rotate_left(16) ^ nanos,
+            inbox: VecDeque::with_capacity(NET_INBOX_CAPACITY),
+            next_message_id: 0,
+        }
+    }
+
+    /// Broadcasts a message to all other coachbots. Messages longer than
+    /// [NET_MSG_LEN] are trimmed, text should be trimmed with [trim_message]
+    /// beforehand.
+    pub fn send(&mut self, message: &[u8]) -> Result<(), NetError> {
+        let message = &message[..message.len().min(NET_MSG_LEN)];
+
+        let mut packet = [0u8; NET_PACKET_LEN];
+        packet[0..2].copy_from_slice(&NET_PACKET_MAGIC);
+        packet[2..4].copy_from_slice(&(message.len() as u16).to_le_bytes());
+        packet[4..8].copy_from_slice(&self.sender_id.to_le_bytes());
+        packet[NET_HEADER_LEN..NET_HEADER_LEN + message.len()].copy_from_slice(message);
+
+        self.net_driver.send_bytes(&packet)
+    }
+
+    /// Moves all pending packets into the inbox. Must be called in a looped
+    /// task.
+    pub fn poll(&mut self) -> Result<(), NetError> {
+        let mut packet = [0u8; NET_PACKET_LEN];
+        while let Some(count) = self.net_driver.recv_bytes(&mut packet)? {
+            if count != NET_PACKET_LEN || packet[0..2] != NET_PACKET_MAGIC {
+                log::debug!(target: "system.network", "Dropping malformed packet.");
+                continue;
+            }
+
+            let len = u16::from_le_bytes([packet[2], packet[3]]) as usize;
+            let sender = u32::from_le_bytes([packet[4], packet[5], packet[6], packet[7]]);
+            if len > NET_MSG_LEN {
+                log::debug!(target: "system.network", "Dropping packet with invalid length.");
+                continue;
+            }
+            if sender == self.sender_id {
+                continue;
+            }
+
+            if self.inbox.len() == NET_INBOX_CAPACITY {
+                self.inbox.pop_front();
+            }
+            self.inbox.push_back(NetMessage {
+                id: self.next_message_id,
+                sender,
+                data: packet[NET_HEADER_LEN..NET_HEADER_LEN + len].to_vec(),
+            });
+            self.next_message_id += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of all messages currently held in the inbox, oldest
+    /// first.
+    pub fn inbox(&self) -> Vec<NetMessage> {
+        self.inbox.iter().cloned().collect()
+    }
+
+    /// Removes all messages with an id smaller than or equal to `id` from the
+    /// inbox.
+    pub fn acknowledge(&mut self, id: u64) {
+        self.inbox.retain(|message| message.id > id);
+    }
+}
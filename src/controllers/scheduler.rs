@@ -0,0 +1,315 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    interface::{HandlesTick1000Ms, HandlesTick100Ms, HandlesTick10Ms, HandlesTick1Ms},
+    task_stats::{SharedTaskStats, TaskStats},
+};
+
+/// The period of the fastest tick. Every other tick period is a multiple of
+/// it.
+pub const TICK_PERIOD: Duration = Duration::from_millis(1);
+
+/// The maximum number of ticks a real-time scheduler runs back to back to
+/// catch up after falling behind. Older ticks are skipped.
+const MAX_CATCH_UP_TICKS: u64 = 100;
+
+/// A source of time for the [Scheduler].
+pub trait Clock {
+    /// Returns the time elapsed since the clock was started.
+    fn now(&self) -> Duration;
+
+    /// Blocks until [Clock::now] reaches `deadline`.
+    fn wait_until(&mut self, deadline: Duration);
+}
+
+/// A clock following the wall clock, used on the robot.
+pub struct RealTimeClock {
+    start: Instant,
+}
+
+impl RealTimeClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for RealTimeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealTimeClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn wait_until(&mut self, deadline: Duration) {
+        thread::sleep(deadline.saturating_sub(self.now()));
+    }
+}
+
+/// A clock that only moves when told to. Waiting on it jumps straight to the
+/// deadline, so schedules run as fast as possible and always identically.
+///
+/// It is meant for tests driving components through a [Scheduler]. The
+/// binaries, including the simulated ones, follow the [RealTimeClock]
+/// because the user script runs as a separate process in real time.
+#[derive(Default)]
+pub struct VirtualClock {
+    now: Duration,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self { now: Duration::ZERO }
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&mut self, by: Duration) {
+        self.now += by;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn wait_until(&mut self, deadline: Duration) {
+        self.now = self.now.max(deadline);
+    }
+}
+
+/// Runs components implementing the `HandlesTick*` traits from a single
+/// clock.
+///
+/// Time is divided into ticks of [TICK_PERIOD]. On every tick the 1ms
+/// handlers run, on every tenth tick the 10ms handlers run after them, and so
+/// on. Handlers of the same rate run in the order they were added. Tick `n`
+/// is due once the clock reaches `n` milliseconds, so tick 0 runs right away.
+///
+/// Every handler is timed, see [Scheduler::stats].
+pub struct Scheduler<C: Clock> {
+    clock: C,
+    /// The index of the next tick to run.
+    next_tick: u64,
+    tick1: Vec<Task<dyn HandlesTick1Ms + Send>>,
+    tick10: Vec<Task<dyn HandlesTick10Ms + Send>>,
+    tick100: Vec<Task<dyn HandlesTick100Ms + Send>>,
+    tick1000: Vec<Task<dyn HandlesTick1000Ms + Send>>,
+    stats: SharedTaskStats,
+}
+
+/// A handler together with the index of its [TaskStats].
+struct Task<H: ?Sized> {
+    stats_index: usize,
+    handler: Box<H>,
+}
+
+impl<C: Clock> Scheduler<C> {
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            next_tick: 0,
+            tick1: Vec::new(),
+            tick10: Vec::new(),
+            tick100: Vec::new(),
+            tick1000: Vec::new(),
+            stats: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn add_tick1(&mut self, name: &'static str,
+                     handler: impl HandlesTick1Ms + Send + 'static) {
+        let stats_index = self.add_stats(name, 1);
+        self.tick1.push(Task { stats_index, handler: Box::new(handler) });
+    }
+
+    pub fn add_tick10(&mut self, name: &'static str,
+                      handler: impl HandlesTick10Ms + Send + 'static) {
+        let stats_index = self.add_stats(name, 10);
+        self.tick10.push(Task { stats_index, handler: Box::new(handler) });
+    }
+
+    pub fn add_tick100(&mut self, name: &'static str,
+                       handler: impl HandlesTick100Ms + Send + 'static) {
+        let stats_index = self.add_stats(name, 100);
+        self.tick100.push(Task { stats_index, handler: Box::new(handler) });
+    }
+
+    pub fn add_tick1000(&mut self, name: &'static str,
+                        handler: impl HandlesTick1000Ms + Send + 'static) {
+        let stats_index = self.add_stats(name, 1000);
+        self.tick1000.push(Task { stats_index, handler: Box::new(handler) });
+    }
+
+    fn add_stats(&mut self, name: &'static str, period_ticks: u32) -> usize {
+        let mut stats = self.stats.lock().unwrap();
+        stats.push(TaskStats::new(name, TICK_PERIOD * period_ticks));
+        stats.len() - 1
+    }
+
+    /// Returns the timing statistics of every added handler, which are
+    /// updated as ticks run.
+    pub fn stats(&self) -> SharedTaskStats {
+        Arc::clone(&self.stats)
+    }
+
+    /// Returns the clock driving this scheduler.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns the clock driving this scheduler.
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    /// Returns the number of ticks run so far.
+    pub fn ticks(&self) -> u64 {
+        self.next_tick
+    }
+
+    /// Runs every tick that is due at the current time of the clock without
+    /// waiting. Returns the number of ticks run.
+    pub fn run_pending(&mut self) -> u64 {
+        let due = self.clock.now().as_millis() as u64 + 1;
+        let mut ran = 0;
+        while self.next_tick < due {
+            self.run_tick();
+            ran += 1;
+        }
+        ran
+    }
+
+    /// Waits for and runs every tick due within `duration` from now.
+    pub fn run_for(&mut self, duration: Duration) {
+        let end = self.clock.now() + duration;
+        while tick_time(self.next_tick) < end {
+            self.clock.wait_until(tick_time(self.next_tick));
+            self.run_tick();
+        }
+    }
+
+    /// Runs ticks as they become due until `shutdown` is set.
+    ///
+    /// If the scheduler falls behind by more than [MAX_CATCH_UP_TICKS], the
+    /// missed ticks are skipped rather than run back to back.
+    pub fn run_until(&mut self, shutdown: &AtomicBool) {
+        while !shutdown.load(Ordering::Relaxed) {
+            self.clock.wait_until(tick_time(self.next_tick));
+
+            let now = self.clock.now().as_millis() as u64;
+            if now > self.next_tick + MAX_CATCH_UP_TICKS {
+                log::warn!(target: "system.scheduler",
+                           "Fell behind, skipping {} ticks.", now - self.next_tick);
+                self.next_tick = now;
+            }
+            self.run_tick();
+        }
+    }
+
+    /// Runs the next tick, regardless of the clock.
+    fn run_tick(&mut self) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+
+        let due = tick_time(tick);
+        let clock = &self.clock;
+        let stats = &self.stats;
+        run_tasks(&mut self.tick1, |h| h.on_tick1(), clock, due, stats);
+        if tick.is_multiple_of(10) {
+            run_tasks(&mut self.tick10, |h| h.on_tick10(), clock, due, stats);
+        }
+        if tick.is_multiple_of(100) {
+            run_tasks(&mut self.tick100, |h| h.on_tick100(), clock, due, stats);
+        }
+        if tick.is_multiple_of(1000) {
+            run_tasks(&mut self.tick1000, |h| h.on_tick1000(), clock, due, stats);
+        }
+    }
+}
+
+/// Runs every task in `tasks`, recording how late each one started relative
+/// to `due` and how long it took. The statistics are not locked while a
+/// handler runs, so handlers may read them.
+fn run_tasks<H: ?Sized>(
+    tasks: &mut [Task<H>],
+    run: impl Fn(&mut H),
+    clock: &impl Clock,
+    due: Duration,
+    stats: &Mutex<Vec<TaskStats>>,
+) {
+    for task in tasks {
+        let jitter = clock.now().saturating_sub(due);
+        let start = Instant::now();
+        run(&mut task.handler);
+        let exec_time = start.elapsed();
+
+        let mut stats = stats.lock().unwrap();
+        let task_stats = &mut stats[task.stats_index];
+        let previous_worst = task_stats.worst();
+        if task_stats.record(exec_time, jitter) && exec_time > previous_worst {
+            log::warn!(target: "system.scheduler",
+                       "{} could not be completed in time ({:?}).",
+                       task_stats.name(), exec_time);
+        }
+    }
+}
+
+impl Scheduler<VirtualClock> {
+    /// Moves the virtual clock forward by `by`, running every tick that
+    /// becomes due at the time it is due. Returns the number of ticks run.
+    pub fn advance(&mut self, by: Duration) -> u64 {
+        let end = self.clock.now() + by;
+        let mut ran = 0;
+        while tick_time(self.next_tick) <= end {
+            self.clock.wait_until(tick_time(self.next_tick));
+            self.run_tick();
+            ran += 1;
+        }
+        self.clock.wait_until(end);
+        ran
+    }
+}
+
+/// Returns the time at which tick `tick` is due, given that [TICK_PERIOD] is
+/// a millisecond.
+fn tick_time(tick: u64) -> Duration {
+    Duration::from_millis(tick)
+}
+
+// Shared components can be added to a scheduler while still being accessible
+// from elsewhere.
+
+impl<T: HandlesTick1Ms> HandlesTick1Ms for Arc<Mutex<T>> {
+    fn on_tick1(&mut self) {
+        self.lock().unwrap().on_tick1();
+    }
+}
+
+impl<T: HandlesTick10Ms> HandlesTick10Ms for Arc<Mutex<T>> {
+    fn on_tick10(&mut self) {
+        self.lock().unwrap().on_tick10();
+    }
+}
+
+impl<T: HandlesTick100Ms> HandlesTick100Ms for Arc<Mutex<T>> {
+    fn on_tick100(&mut self) {
+        self.lock().unwrap().on_tick100();
+    }
+}
+
+impl<T: HandlesTick1000Ms> HandlesTick1000Ms for Arc<Mutex<T>> {
+    fn on_tick1000(&mut self) {
+        self.lock().unwrap().on_tick1000();
+    }
+}
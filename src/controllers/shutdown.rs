@@ -0,0 +1,74 @@
+/// This module lets signals shut cocos down gracefully.
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+
+/// The signals that shut cocos down.
+pub const SHUTDOWN_SIGNALS: [i32; 3] = [SIGINT, SIGTERM, SIGHUP];
+
+/// Returns the exit code of cocos once it shut down because of `signal`,
+/// following the shell convention of `128 + signal`.
+pub fn signal_exit_code(signal: i32) -> i32 {
+    128 + signal
+}
+
+#[derive(Clone, Default)]
+/// Requests a [MasterController](super::master::MasterController) to shut
+/// down gracefully and remembers the signal that caused it.
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+    /// The first signal a shutdown was requested with, `0` if none.
+    signal: Arc<AtomicI32>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the flag which is set once a shutdown is requested.
+    pub fn flag(&self) -> &Arc<AtomicBool> {
+        &self.flag
+    }
+
+    /// Requests a shutdown because of `signal`. Returns whether a shutdown was
+    /// requested before.
+    pub fn request(&self, signal: i32) -> bool {
+        let _ = self.signal.compare_exchange(0, signal, Ordering::Relaxed, Ordering::Relaxed);
+        self.flag.swap(true, Ordering::AcqRel)
+    }
+
+    /// Returns the signal the shutdown was requested with, if any.
+    pub fn signal(&self) -> Option<i32> {
+        // Synchronizes with request(), which records the signal first.
+        if !self.flag.load(Ordering::Acquire) {
+            return None;
+        }
+        match self.signal.load(Ordering::Relaxed) {
+            0 => None,
+            signal => Some(signal),
+        }
+    }
+}
+
+/// Calls `handler` with every one of the [SHUTDOWN_SIGNALS] received, on a
+/// thread of its own.
+pub fn on_shutdown_signal<F: FnMut(i32) + Send + 'static>(mut handler: F) -> io::Result<()> {
+    let mut signals = Signals::new(SHUTDOWN_SIGNALS)?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            handler(signal);
+        }
+    });
+    Ok(())
+}
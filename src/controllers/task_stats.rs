@@ -0,0 +1,125 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The upper bounds of the execution time histogram buckets in microseconds.
+/// Execution times above the last bound fall into an extra overflow bucket.
+pub const EXEC_TIME_BUCKETS_US: [u64; 8] = [50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// The timing statistics of all tasks run by a
+/// [Scheduler](super::scheduler::Scheduler), in registration order.
+pub type SharedTaskStats = Arc<Mutex<Vec<TaskStats>>>;
+
+#[derive(Clone, Debug)]
+/// Holds the timing statistics of a single periodic task since boot.
+pub struct TaskStats {
+    name: &'static str,
+    period: Duration,
+    runs: u64,
+    overruns: u64,
+    worst: Duration,
+    total: Duration,
+    max_jitter: Duration,
+    total_jitter: Duration,
+    histogram: [u64; EXEC_TIME_BUCKETS_US.len() + 1],
+}
+
+impl TaskStats {
+    pub fn new(name: &'static str, period: Duration) -> Self {
+        Self {
+            name,
+            period,
+            runs: 0,
+            overruns: 0,
+            worst: Duration::ZERO,
+            total: Duration::ZERO,
+            max_jitter: Duration::ZERO,
+            total_jitter: Duration::ZERO,
+            histogram: [0; EXEC_TIME_BUCKETS_US.len() + 1],
+        }
+    }
+
+    /// Records a single run of the task which took `exec_time` and started
+    /// `jitter` after it was due. Returns whether the run overran the period.
+    pub fn record(&mut self, exec_time: Duration, jitter: Duration) -> bool {
+        self.runs += 1;
+        self.total += exec_time;
+        self.worst = self.worst.max(exec_time);
+        self.total_jitter += jitter;
+        self.max_jitter = self.max_jitter.max(jitter);
+
+        let exec_us = exec_time.as_micros() as u64;
+        let bucket = EXEC_TIME_BUCKETS_US
+            .iter()
+            .position(|&bound| exec_us <= bound)
+            .unwrap_or(EXEC_TIME_BUCKETS_US.len());
+        self.histogram[bucket] += 1;
+
+        let overrun = exec_time > self.period;
+        if overrun {
+            self.overruns += 1;
+        }
+        overrun
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn runs(&self) -> u64 {
+        self.runs
+    }
+
+    /// Returns how many runs took longer than the period.
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+
+    /// Returns the longest execution time.
+    pub fn worst(&self) -> Duration {
+        self.worst
+    }
+
+    /// Returns the mean execution time.
+    pub fn mean(&self) -> Duration {
+        mean(self.total, self.runs)
+    }
+
+    /// Returns the longest delay between a run being due and it starting.
+    pub fn max_jitter(&self) -> Duration {
+        self.max_jitter
+    }
+
+    /// Returns the mean delay between a run being due and it starting.
+    pub fn mean_jitter(&self) -> Duration {
+        mean(self.total_jitter, self.runs)
+    }
+
+    /// Returns the number of runs per execution time bucket. See
+    /// [EXEC_TIME_BUCKETS_US].
+    pub fn histogram(&self) -> &[u64] {
+        &self.histogram
+    }
+}
+
+impl Display for TaskStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?}): {} runs, {} overruns, exec mean {:?} worst {:?}, \
+                   jitter mean {:?} max {:?}",
+               self.name, self.period, self.runs, self.overruns, self.mean(), self.worst,
+               self.mean_jitter(), self.max_jitter)
+    }
+}
+
+fn mean(total: Duration, count: u64) -> Duration {
+    if count == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_nanos((total.as_nanos() / count as u128) as u64)
+}
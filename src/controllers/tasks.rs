@@ -0,0 +1,180 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Instant,
+};
+
+use crate::{
+    drivers::{led_driver::LedDriver, nucifera_driver::{NuciferaDriver, NuciferaError}},
+    io::interface::{
+        gpio::DrivesGpio,
+        net::{BroadcastsData, ListensForData},
+        pwm::DrivesPwm,
+        uart::DrivesUart,
+    },
+    models::{led_color::LedColor, motor_power::MotorPower, position::Position},
+};
+
+use super::{
+    interface::{HandlesTick100Ms, HandlesTick10Ms, HandlesTick1Ms},
+    motor::MotorController,
+    network::NetworkController,
+    task_stats::SharedTaskStats,
+    watchdog::{CommandWatchdog, WatchdogEvent},
+};
+
+/// How many runs of the [LoggingTask] pass between two dumps of the task
+/// timing statistics.
+const STATS_DUMP_EVERY: u32 = 100;
+
+/// Periodically logs the state of the robot and, less often, the timing
+/// statistics of all tasks.
+pub(crate) struct LoggingTask {
+    pub current_pos: Arc<RwLock<Position>>,
+    pub current_mot_pow: Arc<RwLock<MotorPower>>,
+    pub current_led_color: Arc<RwLock<LedColor>>,
+    pub task_stats: SharedTaskStats,
+    pub ticks: u32,
+}
+
+impl HandlesTick100Ms for LoggingTask {
+    fn on_tick100(&mut self) {
+        // TODO: Potentially dangerous unwrap
+        let pos = *self.current_pos.read().unwrap();
+        let mot_pow = *self.current_mot_pow.read().unwrap();
+        let led_color = *self.current_led_color.read().unwrap();
+        log::info!(target: "system.master.position", "Current: {}", pos);
+        log::info!(target: "system.master.motor_power", "Current: {}", mot_pow);
+        log::info!(target: "system.master.led_color", "Current: {}", led_color);
+
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(STATS_DUMP_EVERY) {
+            for stats in self.task_stats.lock().unwrap().iter() {
+                log::info!(target: "system.master.stats", "{}", stats);
+            }
+        }
+    }
+}
+
+/// Reads the position of the robot from the Nucifera feed.
+pub(crate) struct PositioningTask<UartDriver: DrivesUart> {
+    pub uart_driver: Arc<Mutex<UartDriver>>,
+    pub nucifera_driver: Arc<Mutex<NuciferaDriver>>,
+    pub current_pos: Arc<RwLock<Position>>,
+    pub pos_stale: bool,
+}
+
+impl<UartDriver: DrivesUart> HandlesTick1Ms for PositioningTask<UartDriver> {
+    fn on_tick1(&mut self) {
+        let mut uart_driver = self.uart_driver.lock().unwrap();
+        let mut nucifera = self.nucifera_driver.lock().unwrap();
+        match nucifera.read_current_position(&mut *uart_driver) {
+            Ok(new_pos) => {
+                if self.pos_stale {
+                    log::info!(target: "system.master.position", "Position feed recovered.");
+                    self.pos_stale = false;
+                }
+                // TODO: Potentially dangerous unwrap
+                *self.current_pos.write().unwrap() = new_pos;
+            }
+            Err(NuciferaError::Partial) => {}
+            Err(NuciferaError::Stale { since }) => {
+                if !self.pos_stale {
+                    log::warn!(target: "system.master.position",
+                               "No fresh position received in {:?}.", since);
+                    self.pos_stale = true;
+                }
+            }
+            Err(err) => {
+                log::warn!(target: "system.master.position",
+                           "Could not read position: {}", err);
+            }
+        }
+    }
+}
+
+/// Logs the first of a series of failures and the recovery from it, so
+/// that a persistent failure does not flood the log. Returns the new failing
+/// state.
+fn log_transition<E: std::fmt::Display>(result: Result<(), E>, failing: bool,
+                                        target: &str, what: &str) -> bool {
+    match result {
+        Err(err) => {
+            if !failing {
+                log::error!(target: target, "Could not {}: {}", what, err);
+            }
+            true
+        }
+        Ok(()) => {
+            if failing {
+                log::info!(target: target, "Could {} again.", what);
+            }
+            false
+        }
+    }
+}
+
+/// Outputs the current LED color.
+pub(crate) struct LedTask<PwmDriver: DrivesPwm> {
+    pub pwm_driver: Arc<Mutex<PwmDriver>>,
+    pub led_driver: LedDriver,
+    pub current_led_color: Arc<RwLock<LedColor>>,
+    pub failing: bool,
+}
+
+impl<PwmDriver: DrivesPwm> HandlesTick10Ms for LedTask<PwmDriver> {
+    fn on_tick10(&mut self) {
+        let mut pwm = self.pwm_driver.lock().unwrap();
+        let led = self.current_led_color.read().unwrap();
+        let result = self.led_driver.set_color(*led, &mut *pwm);
+        self.failing = log_transition(result, self.failing, "system.master.led_color",
+                                      "set the LED color");
+    }
+}
+
+/// Receives messages from other robots.
+pub(crate) struct NetworkTask<NetDriver: BroadcastsData + ListensForData> {
+    pub network_controller: Arc<Mutex<NetworkController<NetDriver>>>,
+}
+
+impl<NetDriver: BroadcastsData + ListensForData> HandlesTick10Ms for NetworkTask<NetDriver> {
+    fn on_tick10(&mut self) {
+        if let Err(err) = self.network_controller.lock().unwrap().poll() {
+            log::warn!(target: "system.master.network", "Could not receive: {}", err);
+        }
+    }
+}
+
+/// Outputs the current motor power, braking the motors while the command
+/// watchdog is tripped.
+pub(crate) struct MotionTask<GpioDriver: DrivesGpio, PwmDriver: DrivesPwm> {
+    pub gpio_driver: Arc<Mutex<GpioDriver>>,
+    pub pwm_driver: Arc<Mutex<PwmDriver>>,
+    pub motor_controller: MotorController,
+    pub command_watchdog: Arc<Mutex<CommandWatchdog>>,
+    pub current_mot_pow: Arc<RwLock<MotorPower>>,
+    pub failing: bool,
+}
+
+impl<GpioDriver: DrivesGpio, PwmDriver: DrivesPwm> HandlesTick10Ms
+    for MotionTask<GpioDriver, PwmDriver>
+{
+    fn on_tick10(&mut self) {
+        let mut gpio = self.gpio_driver.lock().unwrap();
+        let mut pwm = self.pwm_driver.lock().unwrap();
+        let mut watchdog = self.command_watchdog.lock().unwrap();
+        if watchdog.poll(Instant::now()) == Some(WatchdogEvent::Tripped) {
+            log::warn!(target: "system.master.watchdog",
+                       "No velocity command in {:?}, motors braked.",
+                       watchdog.timeout());
+        }
+        let mot_pow = if watchdog.is_tripped() {
+            MotorPower::locked()
+        } else {
+            *self.current_mot_pow.read().unwrap()
+        };
+        drop(watchdog);
+        let result = self.motor_controller.set_vel(mot_pow, &mut *gpio, &mut *pwm);
+        self.failing = log_transition(result, self.failing, "system.master.motor_power",
+                                      "drive the motors");
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Describes the command watchdog of the motion output task.
+pub struct WatchdogDescriptor {
+    /// Whether the watchdog is active at all.
+    pub enabled: bool,
+    /// How long the last velocity command is honored without a fresh one.
+    #[serde(rename = "timeout_ms", with = "crate::config::serde_millis")]
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Represents a change of the watchdog state.
+pub enum WatchdogEvent {
+    /// No velocity command arrived within the timeout.
+    Tripped,
+    /// A velocity command arrived after the watchdog tripped.
+    Released,
+}
+
+/// Brakes the motors when the API stops sending velocity commands.
+///
+/// The watchdog is armed by the first command. From then on, it trips once
+/// no command was fed within [WatchdogDescriptor::timeout] and releases as
+/// soon as commands resume.
+pub struct CommandWatchdog {
+    descriptor: WatchdogDescriptor,
+    last_command: Option<Instant>,
+    tripped: bool,
+}
+
+impl CommandWatchdog {
+    pub fn new(descriptor: WatchdogDescriptor) -> Self {
+        Self { descriptor, last_command: None, tripped: false }
+    }
+
+    /// Records that a fresh velocity command arrived at `now`.
+    pub fn feed(&mut self, now: Instant) -> Option<WatchdogEvent> {
+        self.last_command = Some(now);
+        if self.tripped {
+            self.tripped = false;
+            return Some(WatchdogEvent::Released);
+        }
+        None
+    }
+
+    /// Checks the deadline at `now`, returning an event if the watchdog just
+    /// tripped. Must be called in a looped task.
+    pub fn poll(&mut self, now: Instant) -> Option<WatchdogEvent> {
+        if !self.descriptor.enabled || self.tripped {
+            return None;
+        }
+
+        match self.last_command {
+            Some(last) if now.saturating_duration_since(last) > self.descriptor.timeout => {
+                self.tripped = true;
+                Some(WatchdogEvent::Tripped)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether the motors must currently be braked.
+    pub fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+
+    /// Returns the configured timeout.
+    pub fn timeout(&self) -> Duration {
+        self.descriptor.timeout
+    }
+}
@@ -1,8 +1,15 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 use uom::si::f32::Frequency;
 
-use crate::{io::interface::pwm::DrivesPwm, models::led_color::LedColor};
+use crate::{
+    io::interface::pwm::{DrivesPwm, PwmError},
+    models::led_color::LedColor,
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 /// Defines and describes a single LED.
 pub struct LedDescriptor {
     /// The red Rpi BCM Pin
@@ -14,13 +21,31 @@ pub struct LedDescriptor {
     /// The target frequency to operate at.
     ///
     /// Anything above 120Hz should be fine.
+    #[serde(rename = "frequency_hz", with = "crate::config::serde_hertz")]
     pub frequency: Frequency
 }
 
+#[derive(Debug)]
 /// Thrown upon an LED control error.
 pub enum LedError {
     /// Thrown when the IO layer cannot modify the state of a pin.
-    IO,
+    IO(PwmError),
+}
+
+impl fmt::Display for LedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedError::IO(err) => write!(f, "could not set the LED color: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LedError::IO(err) => Some(err),
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -39,17 +64,11 @@ impl LedDriver {
     /// * `pwm_driver` - A PWM driver to use.
     pub fn set_color(&self, color: LedColor,
                      pwm_driver: &mut impl DrivesPwm) -> Result<(), LedError> {
-        if let Err(err) = pwm_driver.set_freq_dc(self.descriptor.frequency, color.r,
-                                                 self.descriptor.pin_r_bcm) {
-            return Err(LedError::IO);
-        }
-        if let Err(err) = pwm_driver.set_freq_dc(self.descriptor.frequency, color.g,
-                                                 self.descriptor.pin_g_bcm) {
-            return Err(LedError::IO);
-        }
-        if let Err(err) = pwm_driver.set_freq_dc(self.descriptor.frequency, color.b,
-                                                 self.descriptor.pin_b_bcm) {
-            return Err(LedError::IO);
+        for (duty_cycle, pin_bcm) in [(color.r, self.descriptor.pin_r_bcm),
+                                      (color.g, self.descriptor.pin_g_bcm),
+                                      (color.b, self.descriptor.pin_b_bcm)] {
+            pwm_driver.set_freq_dc(self.descriptor.frequency, duty_cycle, pin_bcm)
+                .map_err(LedError::IO)?;
         }
 
         Ok(())
@@ -1,25 +1,72 @@
-use uom::si::{f32::Frequency, frequency::hertz};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use uom::si::f32::Frequency;
 
 use crate::io::interface::{
-    gpio::{DrivesGpio, PullMode},
-    pwm::DrivesPwm,
+    gpio::{DrivesGpio, GpioError, PullMode},
+    pwm::{DrivesPwm, PwmError},
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MotorDirection {
     Clockwise,
     CounterClockwise,
 }
 
+#[derive(Debug)]
+/// Represents the errors of a single motor, wrapping the failed IO.
 pub enum MotorError {
-    IOError,
+    /// Thrown when a direction or standby pin could not be driven.
+    Gpio(GpioError),
+    /// Thrown when the speed could not be set.
+    Pwm(PwmError),
 }
 
-#[derive(Clone, Copy)]
+impl From<GpioError> for MotorError {
+    fn from(err: GpioError) -> Self {
+        MotorError::Gpio(err)
+    }
+}
+
+impl From<PwmError> for MotorError {
+    fn from(err: PwmError) -> Self {
+        MotorError::Pwm(err)
+    }
+}
+
+impl fmt::Display for MotorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MotorError::Gpio(err) => err.fmt(f),
+            MotorError::Pwm(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MotorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MotorError::Gpio(err) => Some(err),
+            MotorError::Pwm(err) => Some(err),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MotorDescriptor {
     pub pin_in1: u8,
     pub pin_in2: u8,
     pub pin_pwm: u8,
     pub pin_stdby: u8,
+    /// The polarity of the motor. A non-inverted motor spins clockwise on
+    /// positive power, an inverted one counter-clockwise. Motors mounted
+    /// mirrored on opposite sides of the chassis have opposite polarities.
+    pub inverted: bool,
+    /// The frequency of the PWM driving the motor speed.
+    #[serde(rename = "pwm_frequency_hz", with = "crate::config::serde_hertz")]
+    pub pwm_frequency: Frequency,
 }
 
 #[derive(Clone, Copy)]
@@ -39,17 +86,9 @@ impl MotorDriver {
     /// It is left to the implementation to decide whether the motor will be
     /// halted upon this function call.
     pub fn unblock(&self, gpio_driver: &mut impl DrivesGpio) -> Result<(), MotorError> {
-        if gpio_driver.clear(self.descriptor.pin_in1).is_err() {
-            return Err(MotorError::IOError);
-        }
-
-        if gpio_driver.clear(self.descriptor.pin_in2).is_err() {
-            return Err(MotorError::IOError);
-        }
-
-        if gpio_driver.set(self.descriptor.pin_stdby).is_err() {
-            return Err(MotorError::IOError);
-        }
+        gpio_driver.clear(self.descriptor.pin_in1)?;
+        gpio_driver.clear(self.descriptor.pin_in2)?;
+        gpio_driver.set(self.descriptor.pin_stdby)?;
 
         Ok(())
     }
@@ -59,17 +98,9 @@ impl MotorDriver {
     /// This function must immediately halt the motor and block it from further
     /// operation.
     pub fn block(&self, gpio_driver: &mut impl DrivesGpio) -> Result<(), MotorError> {
-        if gpio_driver.set(self.descriptor.pin_in1).is_err() {
-            return Err(MotorError::IOError);
-        }
-
-        if gpio_driver.set(self.descriptor.pin_in2).is_err() {
-            return Err(MotorError::IOError);
-        }
-
-        if gpio_driver.clear(self.descriptor.pin_stdby).is_err() {
-            return Err(MotorError::IOError);
-        }
+        gpio_driver.set(self.descriptor.pin_in1)?;
+        gpio_driver.set(self.descriptor.pin_in2)?;
+        gpio_driver.clear(self.descriptor.pin_stdby)?;
 
         Ok(())
     }
@@ -79,16 +110,44 @@ impl MotorDriver {
     /// # Arguments
     ///
     /// * `percent` - The percentage speed of the motor. Value must be between
-    ///               0 and 1.
+    ///   0 and 1.
     pub fn set_speed(&self, percent: f32,
                      pwm_driver: &mut impl DrivesPwm) -> Result<(), MotorError> {
-        match pwm_driver.set_freq_dc(Frequency::new::<hertz>(600f32),
-                                     percent, self.descriptor.pin_pwm) {
-            Err(_) => Err(MotorError::IOError),
-            Ok(()) => Ok(())
+        pwm_driver.set_freq_dc(self.descriptor.pwm_frequency, percent,
+                               self.descriptor.pin_pwm)?;
+        Ok(())
+    }
+
+    /// Returns the direction the motor must spin in to deliver the given
+    /// signed power, according to its polarity.
+    pub fn direction_for(&self, power: f32) -> MotorDirection {
+        match (power >= 0.0, self.descriptor.inverted) {
+            (true, false) | (false, true) => MotorDirection::Clockwise,
+            (true, true) | (false, false) => MotorDirection::CounterClockwise,
         }
     }
 
+    /// Drives the motor at the given signed power, setting its direction and
+    /// speed and taking it out of standby.
+    ///
+    /// # Arguments
+    ///
+    /// * `power` - The signed power of the motor. Value must be between -1
+    ///   and 1.
+    pub fn drive(
+        &self,
+        power: f32,
+        gpio_driver: &mut impl DrivesGpio,
+        pwm_driver: &mut impl DrivesPwm,
+    ) -> Result<(), MotorError> {
+        self.set_direction(self.direction_for(power), gpio_driver)?;
+        self.set_speed(power.abs(), pwm_driver)?;
+
+        gpio_driver.set(self.descriptor.pin_stdby)?;
+
+        Ok(())
+    }
+
     /// Sets the direction of the motor.
     ///
     /// # Arguments
@@ -101,38 +160,26 @@ impl MotorDriver {
     ) -> Result<(), MotorError> {
         match direction {
             MotorDirection::CounterClockwise => {
-                if gpio_driver.clear(self.descriptor.pin_in1).is_err() {
-                    return Err(MotorError::IOError);
-                }
-
-                if gpio_driver.set(self.descriptor.pin_in2).is_err() {
-                    return Err(MotorError::IOError);
-                }
+                gpio_driver.clear(self.descriptor.pin_in1)?;
+                gpio_driver.set(self.descriptor.pin_in2)?;
             }
             MotorDirection::Clockwise => {
-                if gpio_driver.set(self.descriptor.pin_in1).is_err() {
-                    return Err(MotorError::IOError);
-                }
-
-                if gpio_driver.clear(self.descriptor.pin_in2).is_err() {
-                    return Err(MotorError::IOError);
-                }
+                gpio_driver.set(self.descriptor.pin_in1)?;
+                gpio_driver.clear(self.descriptor.pin_in2)?;
             }
         }
         Ok(())
     }
 
     pub fn init(&self, gpio_driver: &mut impl DrivesGpio) -> Result<(), MotorError> {
-        if gpio_driver.set_out(self.descriptor.pin_in1, PullMode::Down).is_err()
-            || gpio_driver.set_out(self.descriptor.pin_in2, PullMode::Down).is_err()
-            || gpio_driver.set_out(self.descriptor.pin_pwm, PullMode::Down).is_err()
-            || gpio_driver.set_out(self.descriptor.pin_stdby, PullMode::Down).is_err()
-            || gpio_driver.clear(self.descriptor.pin_in1).is_err()
-            || gpio_driver.clear(self.descriptor.pin_in2).is_err()
-            || gpio_driver.clear(self.descriptor.pin_pwm).is_err()
-            || gpio_driver.clear(self.descriptor.pin_stdby).is_err()
-        {
-            return Err(MotorError::IOError);
+        // The PWM pin belongs to the PWM driver and is left alone here.
+        let pins = [self.descriptor.pin_in1, self.descriptor.pin_in2,
+                    self.descriptor.pin_stdby];
+        for pin in pins {
+            gpio_driver.set_out(pin, PullMode::Down)?;
+        }
+        for pin in pins {
+            gpio_driver.clear(pin)?;
         }
 
         Ok(())
@@ -1,15 +1,45 @@
 use crate::{
-    io::interface::{uart::{DrivesUart, UartParity, UartDescriptor}, IODriver, IOError},
+    io::interface::{
+        uart::{DrivesUart, UartDescriptor, UartError, UartParity},
+        IODriver, IOError,
+    },
     models::position::Position,
 };
-use std::sync::MutexGuard;
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::{Duration, Instant}};
+use uom::si::{angle::radian, f32::{Angle, Length}, length::meter};
 
-#[derive(Clone, Copy)]
+/// The two bytes that open every Nucifera frame.
+pub const NUCIFERA_FRAME_HEADER: [u8; 2] = [0xAA, 0x55];
+
+/// The total length of a Nucifera frame in bytes.
+///
+/// A frame is laid out as follows (all multi-byte values are little endian):
+///
+/// | Offset | Size | Field                                     |
+/// |--------|------|-------------------------------------------|
+/// | 0      | 2    | Header, always [NUCIFERA_FRAME_HEADER]    |
+/// | 2      | 1    | Sequence number, wraps around at 255      |
+/// | 3      | 4    | X position in meters (`f32`)              |
+/// | 7      | 4    | Y position in meters (`f32`)              |
+/// | 11     | 4    | Theta in radians, CCW from X (`f32`)      |
+/// | 15     | 1    | Checksum, XOR of bytes 2 through 14       |
+pub const NUCIFERA_FRAME_LEN: usize = 16;
+
+/// The maximum number of bytes read from the UART in a single poll.
+const READ_CHUNK_LEN: usize = 4 * NUCIFERA_FRAME_LEN;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct NuciferaDescriptor {
     pub baud_rate: u32,
     pub parity: UartParity,
     pub data_bits: u8,
-    pub stop_bits: u8
+    pub stop_bits: u8,
+    /// The time after which the last received position is considered stale
+    /// if no newer frame has arrived.
+    #[serde(rename = "stale_timeout_ms", with = "crate::config::serde_millis")]
+    pub stale_timeout: Duration,
 }
 
 impl NuciferaDescriptor {
@@ -23,33 +53,223 @@ impl NuciferaDescriptor {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug)]
+/// Represents the errors that can occur while reading Nucifera frames.
+pub enum NuciferaError {
+    /// Raised when the UART layer could not be read.
+    IO(UartError),
+    /// Raised when the bytes received so far do not form a complete frame.
+    /// This is the regular state between two frames and the caller should
+    /// simply retry later.
+    Partial,
+    /// Raised when a complete frame was received, but its checksum does not
+    /// match. The frame is discarded.
+    Corrupt { expected: u8, received: u8 },
+    /// Raised when no fresh frame was received within the configured
+    /// [NuciferaDescriptor::stale_timeout]. Frames repeating the sequence
+    /// number of the previous frame are not considered fresh.
+    Stale { since: Duration },
+}
+
+impl fmt::Display for NuciferaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NuciferaError::IO(err) => write!(f, "could not read the Nucifera feed: {}", err),
+            NuciferaError::Partial => write!(f, "no complete frame received yet"),
+            NuciferaError::Corrupt { expected, received } => {
+                write!(f, "corrupt frame, expected checksum {:#04x} but received {:#04x}",
+                       expected, received)
+            }
+            NuciferaError::Stale { since } => {
+                write!(f, "no fresh position received in {:?}", since)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NuciferaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NuciferaError::IO(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Represents a single decoded Nucifera frame.
+pub struct NuciferaMessage {
+    /// The sequence number of the frame.
+    pub sequence: u8,
+    /// The x position in meters.
+    pub x: f32,
+    /// The y position in meters.
+    pub y: f32,
+    /// The angle in radians.
+    pub theta: f32,
+}
+
+impl NuciferaMessage {
+    /// Computes the checksum over the frame body, ie. everything between the
+    /// header and the checksum byte.
+    fn checksum(body: &[u8]) -> u8 {
+        body.iter().fold(0u8, |acc, byte| acc ^ byte)
+    }
+
+    /// Decodes a single frame. The given slice must be exactly
+    /// [NUCIFERA_FRAME_LEN] bytes long and start with [NUCIFERA_FRAME_HEADER].
+    pub fn decode(frame: &[u8]) -> Result<Self, NuciferaError> {
+        if frame.len() < NUCIFERA_FRAME_LEN || frame[0..2] != NUCIFERA_FRAME_HEADER {
+            return Err(NuciferaError::Partial);
+        }
+
+        let expected = Self::checksum(&frame[2..NUCIFERA_FRAME_LEN - 1]);
+        let received = frame[NUCIFERA_FRAME_LEN - 1];
+        if expected != received {
+            return Err(NuciferaError::Corrupt { expected, received });
+        }
+
+        let read_f32 = |offset: usize| {
+            f32::from_le_bytes([frame[offset], frame[offset + 1], frame[offset + 2],
+                                frame[offset + 3]])
+        };
+
+        Ok(Self {
+            sequence: frame[2],
+            x: read_f32(3),
+            y: read_f32(7),
+            theta: read_f32(11),
+        })
+    }
+
+    /// Encodes this message into a full frame, including header and checksum.
+    pub fn encode(&self) -> [u8; NUCIFERA_FRAME_LEN] {
+        let mut frame = [0u8; NUCIFERA_FRAME_LEN];
+        frame[0..2].copy_from_slice(&NUCIFERA_FRAME_HEADER);
+        frame[2] = self.sequence;
+        frame[3..7].copy_from_slice(&self.x.to_le_bytes());
+        frame[7..11].copy_from_slice(&self.y.to_le_bytes());
+        frame[11..15].copy_from_slice(&self.theta.to_le_bytes());
+        frame[NUCIFERA_FRAME_LEN - 1] = Self::checksum(&frame[2..NUCIFERA_FRAME_LEN - 1]);
+        frame
+    }
+
+    /// Converts this message into a [Position].
+    pub fn to_position(&self) -> Position {
+        Position {
+            x: Length::new::<meter>(self.x),
+            y: Length::new::<meter>(self.y),
+            theta: Angle::new::<radian>(self.theta),
+        }
+    }
+}
+
 pub struct NuciferaDriver {
     descriptor: NuciferaDescriptor,
-}
 
-pub struct NuciferaMessage {}
+    /// Bytes received from the UART that have not yet been consumed.
+    buffer: Vec<u8>,
+    /// The sequence number of the last accepted frame.
+    last_sequence: Option<u8>,
+    /// The time at which the last frame was accepted.
+    last_update: Instant,
+}
 
 impl IODriver for NuciferaDriver {
     fn init(&mut self) -> Result<(), IOError> {
-        // TODO: Call initialization steps.
+        self.buffer.clear();
+        self.last_sequence = None;
+        self.last_update = Instant::now();
         Ok(())
     }
 }
 
 impl NuciferaDriver {
     pub fn new(descriptor: NuciferaDescriptor) -> Self {
-        Self { descriptor }
+        Self {
+            descriptor,
+            buffer: Vec::with_capacity(2 * READ_CHUNK_LEN),
+            last_sequence: None,
+            last_update: Instant::now(),
+        }
     }
 
     /// Reads the current position of the coachbot.
     ///
-    /// In case Nucifera provides more than 1 position sample since the last
-    /// invokation of this function, it is left to the implementation to decide
-    /// on the behavior.
-    pub fn read_current_position<UartDriver: DrivesUart>(&self, uart_driver:
-                                                         &MutexGuard<UartDriver>) -> Position {
-        // TODO: Implement this
-        Position::zero()
+    /// All bytes available on the UART are consumed. In case Nucifera
+    /// provides more than one frame since the last invokation of this
+    /// function, the most recent valid frame wins. Corrupt frames are dropped
+    /// and the decoder resynchronizes on the next frame header.
+    ///
+    /// A [NuciferaError::Partial] is returned if no complete frame has been
+    /// received yet, which is not a failure.
+    pub fn read_current_position(
+        &mut self,
+        uart_driver: &mut impl DrivesUart,
+    ) -> Result<Position, NuciferaError> {
+        let mut chunk = [0u8; READ_CHUNK_LEN];
+        match uart_driver.read_bytes(&mut chunk) {
+            Ok(count) => self.buffer.extend_from_slice(&chunk[..count]),
+            Err(err) => return Err(NuciferaError::IO(err)),
+        }
+
+        let mut latest: Option<NuciferaMessage> = None;
+        let mut corrupt: Option<NuciferaError> = None;
+
+        while let Some(start) = self.find_header() {
+            // Drop any garbage preceding the header.
+            self.buffer.drain(..start);
+            if self.buffer.len() < NUCIFERA_FRAME_LEN {
+                break;
+            }
+
+            match NuciferaMessage::decode(&self.buffer[..NUCIFERA_FRAME_LEN]) {
+                Ok(message) => {
+                    self.buffer.drain(..NUCIFERA_FRAME_LEN);
+                    if self.last_sequence == Some(message.sequence) {
+                        log::trace!(target: "system.drivers.nucifera",
+                                    "Dropping repeated frame {}", message.sequence);
+                        continue;
+                    }
+                    self.last_sequence = Some(message.sequence);
+                    latest = Some(message);
+                }
+                Err(err) => {
+                    // Skip the header so that we can resync on the next one.
+                    self.buffer.drain(..NUCIFERA_FRAME_HEADER.len());
+                    corrupt = Some(err);
+                }
+            }
+        }
+
+        // If no header is found, keep at most the last byte which may be the
+        // first half of a header.
+        if self.find_header().is_none() && self.buffer.len() > 1 {
+            let keep_from = self.buffer.len() - 1;
+            self.buffer.drain(..keep_from);
+        }
+
+        if let Some(message) = latest {
+            self.last_update = Instant::now();
+            return Ok(message.to_position());
+        }
+
+        if let Some(err) = corrupt {
+            return Err(err);
+        }
+
+        let since = self.last_update.elapsed();
+        if since > self.descriptor.stale_timeout {
+            return Err(NuciferaError::Stale { since });
+        }
+
+        Err(NuciferaError::Partial)
+    }
+
+    /// Returns the index of the first frame header in the buffer, if any.
+    fn find_header(&self) -> Option<usize> {
+        self.buffer
+            .windows(NUCIFERA_FRAME_HEADER.len())
+            .position(|window| window == NUCIFERA_FRAME_HEADER)
     }
 }
@@ -1,5 +1,8 @@
 /// This interface exposes the GPIO IO layer.
 /// This layer is responsible for setting GPIO pins high or low.
+use std::{fmt, sync::mpsc::Receiver, time::Instant};
+
+use super::IOCause;
 
 #[derive(Debug)]
 /// Enumeration exposing internal pull values.
@@ -8,10 +11,91 @@ pub enum PullMode {
     Down,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Enumerates the operations of [DrivesGpio] that can fail.
+pub enum GpioOperation {
+    Set,
+    Clear,
+    SetOut,
+    SetInp,
+    Read,
+    Subscribe,
+    Unsubscribe,
+}
+
+impl fmt::Display for GpioOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GpioOperation::Set => "set",
+            GpioOperation::Clear => "clear",
+            GpioOperation::SetOut => "configure as output",
+            GpioOperation::SetInp => "configure as input",
+            GpioOperation::Read => "read",
+            GpioOperation::Subscribe => "watch",
+            GpioOperation::Unsubscribe => "stop watching",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The level changes of an input pin that raise a [GpioEvent].
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl Edge {
+    /// Returns whether a change to `level` is an edge of this kind.
+    pub fn matches(&self, level: bool) -> bool {
+        match self {
+            Edge::Rising => level,
+            Edge::Falling => !level,
+            Edge::Both => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Represents an edge detected on an input pin.
+pub struct GpioEvent {
+    pub pin_bcm: u8,
+    /// The level of the pin after the edge, `true` being high.
+    pub level: bool,
+    /// The time at which the edge was detected.
+    pub at: Instant,
+}
+
+#[derive(Debug)]
 /// Errors this layer can possibly throw.
 pub enum GpioError {
+    /// Thrown when the GPIO peripheral could not be opened.
+    Init(IOCause),
     /// Thrown upon a GPIO IO error case.
-    IO,
+    IO {
+        pin_bcm: u8,
+        operation: GpioOperation,
+        cause: IOCause,
+    },
+}
+
+impl fmt::Display for GpioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpioError::Init(cause) => write!(f, "could not open the GPIO peripheral: {}", cause),
+            GpioError::IO { pin_bcm, operation, cause } => {
+                write!(f, "could not {} GPIO pin {}: {}", operation, pin_bcm, cause)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GpioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GpioError::Init(cause) | GpioError::IO { cause, .. } => Some(cause.as_ref()),
+        }
+    }
 }
 
 pub trait DrivesGpio {
@@ -34,10 +118,36 @@ pub trait DrivesGpio {
     /// * `pull_mode` - whether the pin should be pulled up or down.
     fn set_out(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError>;
 
-    /// Sets a pin to be an input pin.
+    /// Sets a pin to be an input pin. A watched pin stays watched, see
+    /// [DrivesGpio::subscribe].
     ///
     /// Arguments:
     /// * `pin_bcm` - The BCM pin number on the Raspberry Pi
     /// * `pull_mode` - whether the pin should be pulled up or down.
     fn set_inp(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError>;
+
+    /// Reads the current level of a pin, `true` being high.
+    ///
+    /// Arguments:
+    /// * `pin_bcm` - The BCM pin number on the Raspberry Pi
+    fn read(&mut self, pin_bcm: u8) -> Result<bool, GpioError>;
+
+    /// Watches an input pin for edges. Every matching edge is sent as a
+    /// [GpioEvent] through the returned channel as soon as it is detected. A
+    /// pin can only be watched once, subscribing again replaces the previous
+    /// channel.
+    ///
+    /// Pins that were not configured via [DrivesGpio::set_inp] are
+    /// configured as floating inputs.
+    ///
+    /// Arguments:
+    /// * `pin_bcm` - The BCM pin number on the Raspberry Pi
+    /// * `edge` - The edges to report.
+    fn subscribe(&mut self, pin_bcm: u8, edge: Edge) -> Result<Receiver<GpioEvent>, GpioError>;
+
+    /// Stops watching a pin, closing its channel.
+    ///
+    /// Arguments:
+    /// * `pin_bcm` - The BCM pin number on the Raspberry Pi
+    fn unsubscribe(&mut self, pin_bcm: u8) -> Result<(), GpioError>;
 }
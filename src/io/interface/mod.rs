@@ -2,6 +2,9 @@ use self::gpio::DrivesGpio;
 use self::pwm::DrivesPwm;
 use self::uart::DrivesUart;
 
+/// The underlying cause of an IO error, as reported by the HAL or the OS.
+pub type IOCause = Box<dyn std::error::Error + Send + Sync>;
+
 pub enum IOError {
     Unknown,
     Reinitialization,
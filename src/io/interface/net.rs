@@ -1,9 +1,63 @@
+use std::{fmt, io, net::Ipv4Addr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+/// Represents possible networking errors.
+pub enum NetError {
+    /// Thrown when the underlying transport could not send.
+    Send(io::Error),
+    /// Thrown when the underlying transport could not receive.
+    Receive(io::Error),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Send(cause) => write!(f, "could not send datagram: {}", cause),
+            NetError::Receive(cause) => write!(f, "could not receive datagram: {}", cause),
+        }
+    }
+}
+
+impl std::error::Error for NetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NetError::Send(cause) | NetError::Receive(cause) => Some(cause),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Represents the information required to construct a networking driver.
+pub struct NetDescriptor {
+    /// The port all coachbots send to and listen on.
+    pub port: u16,
+    /// The address datagrams are broadcast to.
+    pub broadcast_addr: Ipv4Addr,
+}
+
 /// Implementing this enables data to be broadcast over the wire between
 /// coachbots.
-trait BroadcastsData {
+pub trait BroadcastsData {
     /// Attempts to send bytes over the wire to all other devices via a
     /// broadcast transport (likely UDP to a broadcast address).
-    fn send_bytes(data: Vec<u8>);
+    ///
+    /// Arguments:
+    /// * `data` - The datagram to send.
+    fn send_bytes(&mut self, data: &[u8]) -> Result<(), NetError>;
 }
 
-trait ListensForData {}
+/// Implementing this enables data broadcast by other coachbots to be
+/// received.
+pub trait ListensForData {
+    /// Receives a single pending datagram without blocking.
+    ///
+    /// Returns the number of bytes written into `into`, or [None] if no
+    /// datagram is pending. Datagrams longer than `into` are truncated.
+    ///
+    /// Arguments:
+    /// * `into` - The buffer to receive into.
+    fn recv_bytes(&mut self, into: &mut [u8]) -> Result<Option<usize>, NetError>;
+}
@@ -1,9 +1,35 @@
+use std::fmt;
+
 use uom::si::f32::Frequency;
 
+use super::IOCause;
+
+#[derive(Debug)]
 /// An enumeration of possible errors to be thrown.
 pub enum PwmError {
-    /// Represents a HAL layer error.
-    IO,
+    /// Thrown when the PWM peripheral could not be opened.
+    Init(IOCause),
+    /// Represents a HAL layer error while driving `pin_bcm`.
+    IO { pin_bcm: u8, cause: IOCause },
+}
+
+impl fmt::Display for PwmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PwmError::Init(cause) => write!(f, "could not open the PWM peripheral: {}", cause),
+            PwmError::IO { pin_bcm, cause } => {
+                write!(f, "could not set the PWM of pin {}: {}", pin_bcm, cause)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PwmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PwmError::Init(cause) | PwmError::IO { cause, .. } => Some(cause.as_ref()),
+        }
+    }
 }
 
 pub trait DrivesPwm {
@@ -1,9 +1,64 @@
+use std::{fmt, thread, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+
+use super::IOCause;
+
+/// The default capacity of a [UartRingBuffer] in bytes.
+pub const UART_RING_BUFFER_CAPACITY: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Enumerates the operations of [DrivesUart] that can fail.
+pub enum UartOperation {
+    Read,
+    Write,
+    Flush,
+}
+
+impl fmt::Display for UartOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UartOperation::Read => "read",
+            UartOperation::Write => "write",
+            UartOperation::Flush => "flush",
+        })
+    }
+}
+
+#[derive(Debug)]
 /// Represents possible UART errors.
 pub enum UartError {
-    IO
+    /// Thrown when the device could not be opened or configured.
+    Init(IOCause),
+    /// Thrown when the underlying device could not be read or written.
+    IO { operation: UartOperation, cause: IOCause },
+    /// Thrown when no data arrived within the requested timeout.
+    Timeout,
 }
 
-#[derive(Clone, Copy)]
+impl fmt::Display for UartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UartError::Init(cause) => write!(f, "could not open the UART: {}", cause),
+            UartError::IO { operation, cause } => {
+                write!(f, "could not {} the UART: {}", operation, cause)
+            }
+            UartError::Timeout => write!(f, "timed out waiting for UART data"),
+        }
+    }
+}
+
+impl std::error::Error for UartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UartError::Init(cause) | UartError::IO { cause, .. } => Some(cause.as_ref()),
+            UartError::Timeout => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 /// Represents the possible parity modes for the IO UART layer.
 pub enum UartParity { None, Even, Odd, Mark, Space }
 
@@ -16,6 +71,152 @@ pub struct UartDescriptor {
     pub stop_bits: u8
 }
 
+impl UartDescriptor {
+    /// Returns the number of bits on the wire required to transmit a single
+    /// byte, including the start, parity and stop bits.
+    pub fn bits_per_byte(&self) -> u32 {
+        let parity_bits = match self.parity {
+            UartParity::None => 0,
+            _ => 1,
+        };
+        1 + self.data_bits as u32 + parity_bits + self.stop_bits as u32
+    }
+}
+
+/// A fixed-capacity FIFO holding bytes that were received from the device
+/// but not yet consumed by a reader.
+///
+/// When the buffer is full, the oldest bytes are overwritten. The number of
+/// overwritten bytes is tracked and can be retrieved via
+/// [UartRingBuffer::take_dropped].
+pub struct UartRingBuffer {
+    data: Box<[u8]>,
+    /// The index of the oldest byte.
+    head: usize,
+    /// The number of bytes currently stored.
+    len: usize,
+    /// The number of bytes overwritten since the last call to take_dropped.
+    dropped: usize,
+}
+
+impl UartRingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: vec![0u8; capacity].into_boxed_slice(),
+            head: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Returns the total number of bytes this buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the number of bytes currently stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes that can be pushed without overwriting.
+    pub fn free(&self) -> usize {
+        self.capacity() - self.len
+    }
+
+    /// Appends bytes to the buffer, overwriting the oldest bytes if the
+    /// buffer runs out of space.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            self.dropped += bytes.len();
+            return;
+        }
+
+        for &byte in bytes {
+            let tail = (self.head + self.len) % capacity;
+            self.data[tail] = byte;
+            if self.len == capacity {
+                self.head = (self.head + 1) % capacity;
+                self.dropped += 1;
+            } else {
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Moves up to `into.len()` of the oldest bytes into `into`, returning
+    /// the number of bytes moved.
+    pub fn pop_into(&mut self, into: &mut [u8]) -> usize {
+        let count = into.len().min(self.len);
+        for slot in into.iter_mut().take(count) {
+            *slot = self.data[self.head];
+            self.head = (self.head + 1) % self.capacity();
+        }
+        self.len -= count;
+        count
+    }
+
+    /// Discards all stored bytes.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Returns and resets the number of bytes overwritten due to overflow.
+    pub fn take_dropped(&mut self) -> usize {
+        std::mem::take(&mut self.dropped)
+    }
+}
+
 pub trait DrivesUart {
-    fn read_bytes(&mut self, into: &mut [u8]) -> Result<Box<[u8]>, UartError>;
+    /// Reads the bytes that are currently available without blocking.
+    ///
+    /// Returns the number of bytes written into `into`, which is `0` if no
+    /// data is available.
+    ///
+    /// Arguments:
+    /// * `into` - The buffer to read into.
+    fn read_bytes(&mut self, into: &mut [u8]) -> Result<usize, UartError>;
+
+    /// Reads the bytes that are currently available, waiting for at most
+    /// `timeout` for at least one byte to arrive.
+    ///
+    /// Returns [UartError::Timeout] if no data arrived in time.
+    ///
+    /// Arguments:
+    /// * `into` - The buffer to read into.
+    /// * `timeout` - The maximum time to wait for.
+    fn read_bytes_timeout(&mut self, into: &mut [u8],
+                          timeout: Duration) -> Result<usize, UartError> {
+        let begin = Instant::now();
+        loop {
+            let count = self.read_bytes(into)?;
+            if count > 0 || into.is_empty() {
+                return Ok(count);
+            }
+
+            let elapsed = begin.elapsed();
+            if elapsed >= timeout {
+                return Err(UartError::Timeout);
+            }
+            thread::sleep((timeout - elapsed).min(Duration::from_millis(1)));
+        }
+    }
+
+    /// Queues bytes for transmission without blocking.
+    ///
+    /// Returns the number of bytes that were accepted, which may be less than
+    /// `data.len()` if the output queue is full.
+    ///
+    /// Arguments:
+    /// * `data` - The bytes to send.
+    fn write_bytes(&mut self, data: &[u8]) -> Result<usize, UartError>;
+
+    /// Blocks until all queued bytes have been transmitted.
+    fn flush(&mut self) -> Result<(), UartError>;
 }
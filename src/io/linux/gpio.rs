@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    io,
+    os::unix::io::AsRawFd,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use gpio_cdev::{Chip, EventRequestFlags, EventType, LineEventHandle, LineHandle, LineRequestFlags};
+
+use super::super::interface::gpio::{
+    DrivesGpio, Edge, GpioError, GpioEvent, GpioOperation, PullMode,
+};
+
+/// The consumer label the requested lines are tagged with.
+const CONSUMER: &str = "cocos";
+
+/// How long a watcher thread waits for an edge before checking whether it
+/// should stop, in milliseconds.
+const WATCH_POLL_MS: i32 = 100;
+
+/// Watches a line for edges on a separate thread.
+struct Watcher {
+    stop: Arc<AtomicBool>,
+    /// The level after the last edge, `true` being high.
+    level: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    fn spawn(pin_bcm: u8, mut handle: LineEventHandle, level: bool,
+             sender: Sender<GpioEvent>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let level = Arc::new(AtomicBool::new(level));
+        let thread = {
+            let stop = stop.clone();
+            let level = level.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let mut poll_fd = libc::pollfd {
+                        fd: handle.as_raw_fd(),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    };
+                    // SAFETY: `poll_fd` outlives the call and refers to the
+                    // open event handle.
+                    let ready = unsafe { libc::poll(&mut poll_fd, 1, WATCH_POLL_MS) };
+                    if ready < 0 {
+                        let err = io::Error::last_os_error();
+                        if err.kind() == io::ErrorKind::Interrupted {
+                            continue;
+                        }
+                        log::error!(target: "system.io.gpio",
+                                    "Stopped watching pin {}. {}", pin_bcm, err);
+                        return;
+                    }
+                    if ready == 0 {
+                        continue;
+                    }
+
+                    match handle.get_event() {
+                        Ok(event) => {
+                            let high = event.event_type() == EventType::RisingEdge;
+                            level.store(high, Ordering::Relaxed);
+                            // The receiver may be gone, in which case nobody
+                            // is interested anymore.
+                            let _ = sender.send(GpioEvent { pin_bcm, level: high,
+                                                            at: Instant::now() });
+                        }
+                        Err(err) => {
+                            log::error!(target: "system.io.gpio",
+                                        "Stopped watching pin {}. {}", pin_bcm, err);
+                            return;
+                        }
+                    }
+                }
+            })
+        };
+        Self { stop, level, thread: Some(thread) }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A line requested by the [CdevGpioDriver].
+enum RequestedLine {
+    Output(LineHandle),
+    Input(LineHandle),
+    Watched(Watcher),
+}
+
+/// Defines a driver that interfaces with the GPIO lines of a Linux GPIO
+/// character device, such as `/dev/gpiochip0`. Pins are the line offsets on
+/// that chip.
+///
+/// Lines are requested on their first use and held on to until they change
+/// direction. The character device ABI used cannot set the bias of a line, so
+/// inputs keep whatever pull the device tree configures.
+///
+/// The driver works with the `gpio-mockup` and `gpio-sim` kernel modules,
+/// which makes it testable without any hardware.
+pub struct CdevGpioDriver {
+    chip: Chip,
+    lines: HashMap<u8, RequestedLine>,
+}
+
+impl CdevGpioDriver {
+    pub fn new(path: &str) -> Result<Self, GpioError> {
+        match Chip::new(path) {
+            Ok(chip) => Ok(Self { chip, lines: HashMap::new() }),
+            Err(err) => Err(GpioError::Init(err.into())),
+        }
+    }
+
+    /// Releases `pin_bcm` and requests it again with `flags`, holding on to
+    /// it as `direction`.
+    fn request(&mut self, pin_bcm: u8, operation: GpioOperation, flags: LineRequestFlags,
+               default: u8,
+               direction: fn(LineHandle) -> RequestedLine) -> Result<&LineHandle, GpioError> {
+        self.lines.remove(&pin_bcm);
+        let handle = self.chip
+            .get_line(pin_bcm as u32)
+            .and_then(|line| line.request(flags, default, CONSUMER))
+            .map_err(|err| GpioError::IO { pin_bcm, operation, cause: err.into() })?;
+        match self.lines.entry(pin_bcm).or_insert(direction(handle)) {
+            RequestedLine::Output(handle) | RequestedLine::Input(handle) => Ok(handle),
+            RequestedLine::Watched(_) => unreachable!("the line was just requested"),
+        }
+    }
+
+    /// Writes `value` to `pin_bcm`, requesting it as an output if it is not
+    /// one yet.
+    fn write(&mut self, pin_bcm: u8, operation: GpioOperation,
+             value: u8) -> Result<(), GpioError> {
+        if let Some(RequestedLine::Output(handle)) = self.lines.get(&pin_bcm) {
+            return handle
+                .set_value(value)
+                .map_err(|err| GpioError::IO { pin_bcm, operation, cause: err.into() });
+        }
+        self.request(pin_bcm, operation, LineRequestFlags::OUTPUT, value,
+                     RequestedLine::Output)?;
+        Ok(())
+    }
+}
+
+impl DrivesGpio for CdevGpioDriver {
+    fn set_inp(&mut self, pin_bcm: u8, _pull_mode: PullMode) -> Result<(), GpioError> {
+        // A watched line already is an input, and requesting it again would
+        // stop its watcher.
+        if let Some(RequestedLine::Watched(_)) = self.lines.get(&pin_bcm) {
+            return Ok(());
+        }
+        self.request(pin_bcm, GpioOperation::SetInp, LineRequestFlags::INPUT, 0,
+                     RequestedLine::Input)?;
+        Ok(())
+    }
+
+    fn set_out(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError> {
+        let value = match pull_mode {
+            PullMode::Up => 0,
+            PullMode::Down => 1,
+        };
+        self.request(pin_bcm, GpioOperation::SetOut, LineRequestFlags::OUTPUT, value,
+                     RequestedLine::Output)?;
+        Ok(())
+    }
+
+    fn set(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        self.write(pin_bcm, GpioOperation::Set, 1)
+    }
+
+    fn clear(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        self.write(pin_bcm, GpioOperation::Clear, 0)
+    }
+
+    fn read(&mut self, pin_bcm: u8) -> Result<bool, GpioError> {
+        let io_error = |err: gpio_cdev::Error| GpioError::IO {
+            pin_bcm,
+            operation: GpioOperation::Read,
+            cause: err.into(),
+        };
+        match self.lines.get(&pin_bcm) {
+            Some(RequestedLine::Output(handle)) | Some(RequestedLine::Input(handle)) => {
+                handle.get_value().map(|value| value != 0).map_err(io_error)
+            }
+            Some(RequestedLine::Watched(watcher)) => Ok(watcher.level.load(Ordering::Relaxed)),
+            None => {
+                // Requesting a line without a direction leaves it as is.
+                let handle = self.chip
+                    .get_line(pin_bcm as u32)
+                    .and_then(|line| line.request(LineRequestFlags::empty(), 0, CONSUMER))
+                    .map_err(io_error)?;
+                handle.get_value().map(|value| value != 0).map_err(io_error)
+            }
+        }
+    }
+
+    fn subscribe(&mut self, pin_bcm: u8, edge: Edge) -> Result<Receiver<GpioEvent>, GpioError> {
+        let io_error = |err: gpio_cdev::Error| GpioError::IO {
+            pin_bcm,
+            operation: GpioOperation::Subscribe,
+            cause: err.into(),
+        };
+        let edges = match edge {
+            Edge::Rising => EventRequestFlags::RISING_EDGE,
+            Edge::Falling => EventRequestFlags::FALLING_EDGE,
+            Edge::Both => EventRequestFlags::BOTH_EDGES,
+        };
+        self.lines.remove(&pin_bcm);
+        let handle = self.chip
+            .get_line(pin_bcm as u32)
+            .and_then(|line| line.events(LineRequestFlags::INPUT, edges, CONSUMER))
+            .map_err(io_error)?;
+        let level = handle.get_value().map_err(io_error)? != 0;
+
+        let (sender, receiver) = mpsc::channel();
+        self.lines.insert(pin_bcm, RequestedLine::Watched(Watcher::spawn(pin_bcm, handle, level,
+                                                                         sender)));
+        Ok(receiver)
+    }
+
+    fn unsubscribe(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        if let Some(RequestedLine::Watched(_)) = self.lines.get(&pin_bcm) {
+            // Dropping the watcher stops its thread and releases the line.
+            self.lines.remove(&pin_bcm);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+/// This module exposes drivers for the generic Linux GPIO character device
+/// and sysfs PWM interfaces, so that cocos can run on boards other than the
+/// Raspberry Pi.
+pub mod gpio;
+pub mod pwm;
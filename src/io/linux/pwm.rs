@@ -0,0 +1,139 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use uom::si::{f32::Frequency, frequency::hertz};
+
+use crate::io::interface::pwm::{DrivesPwm, PwmError};
+
+/// How often and how long to wait for an exported channel to become
+/// writable. udev fixes up the permissions of a new channel asynchronously.
+const EXPORT_RETRIES: u32 = 20;
+const EXPORT_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// A channel exported by the [SysfsPwmDriver].
+struct ExportedChannel {
+    chip: PathBuf,
+    channel: u32,
+    period_ns: u64,
+    duty_ns: u64,
+}
+
+impl ExportedChannel {
+    fn export(chip: &Path, channel: u32) -> io::Result<Self> {
+        let exported = Self { chip: chip.to_path_buf(), channel, period_ns: 0, duty_ns: 0 };
+        if !exported.path().exists() {
+            fs::write(chip.join("export"), channel.to_string())?;
+        }
+
+        let mut retries = 0;
+        loop {
+            match exported.write("enable", 0) {
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied
+                    || err.kind() == io::ErrorKind::NotFound => {
+                    if retries == EXPORT_RETRIES {
+                        return Err(err);
+                    }
+                    retries += 1;
+                    thread::sleep(EXPORT_RETRY_DELAY);
+                }
+                result => return result.map(|()| exported),
+            }
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.chip.join(format!("pwm{}", self.channel))
+    }
+
+    fn write(&self, attribute: &str, value: u64) -> io::Result<()> {
+        fs::write(self.path().join(attribute), value.to_string())
+    }
+
+    /// Sets the period and the duty cycle, only writing what changed.
+    fn set(&mut self, period_ns: u64, duty_ns: u64) -> io::Result<()> {
+        let enable = self.period_ns == 0;
+        if period_ns != self.period_ns {
+            // The duty cycle must never exceed the period.
+            if self.duty_ns > period_ns {
+                self.write("duty_cycle", 0)?;
+                self.duty_ns = 0;
+            }
+            self.write("period", period_ns)?;
+            self.period_ns = period_ns;
+        }
+        if duty_ns != self.duty_ns {
+            self.write("duty_cycle", duty_ns)?;
+            self.duty_ns = duty_ns;
+        }
+        if enable {
+            self.write("enable", 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ExportedChannel {
+    fn drop(&mut self) {
+        let _ = self.write("enable", 0);
+        let _ = fs::write(self.chip.join("unexport"), self.channel.to_string());
+    }
+}
+
+/// Defines a driver that drives the channels of a Linux sysfs PWM chip, such
+/// as `/sys/class/pwm/pwmchip0`. Every pin driven with PWM must be mapped to
+/// one of the channels of the chip.
+pub struct SysfsPwmDriver {
+    chip: PathBuf,
+    channels: BTreeMap<u8, u32>,
+    exported: HashMap<u8, ExportedChannel>,
+}
+
+impl SysfsPwmDriver {
+    /// Creates a driver for the PWM chip at `chip`, driving each pin of
+    /// `channels` through its channel.
+    pub fn new(chip: &str, channels: BTreeMap<u8, u32>) -> Result<Self, PwmError> {
+        let chip = PathBuf::from(chip);
+        if let Err(err) = fs::metadata(chip.join("export")) {
+            return Err(PwmError::Init(err.into()));
+        }
+        Ok(Self { chip, channels, exported: HashMap::new() })
+    }
+}
+
+impl DrivesPwm for SysfsPwmDriver {
+    fn set_freq_dc(
+        &mut self,
+        frequency: Frequency,
+        duty_cycle: f32,
+        pin_bcm: u8,
+    ) -> Result<(), PwmError> {
+        let period_ns = (1e9 / frequency.get::<hertz>() as f64) as u64;
+        let duty_ns = (period_ns as f64 * duty_cycle.clamp(0.0, 1.0) as f64) as u64;
+
+        if !self.exported.contains_key(&pin_bcm) {
+            let channel = match self.channels.get(&pin_bcm) {
+                Some(&channel) => channel,
+                None => {
+                    return Err(PwmError::IO {
+                        pin_bcm,
+                        cause: "the pin is not mapped to a PWM channel".into(),
+                    });
+                }
+            };
+            let exported = ExportedChannel::export(&self.chip, channel)
+                .map_err(|err| PwmError::IO { pin_bcm, cause: err.into() })?;
+            self.exported.insert(pin_bcm, exported);
+        }
+
+        self.exported
+            .get_mut(&pin_bcm)
+            .unwrap()
+            .set(period_ns, duty_ns)
+            .map_err(|err| PwmError::IO { pin_bcm, cause: err.into() })
+    }
+}
@@ -1,3 +1,45 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
 pub mod interface;
+pub mod linux;
 pub mod rpi;
 pub mod sim_print;
+pub mod udp;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Enumerates the hardware backends `cocos_rpi` can drive GPIO and PWM with.
+pub enum IoBackend {
+    /// The Raspberry Pi peripherals, via rppal.
+    Rpi,
+    /// The Linux GPIO character device and sysfs PWM. The UART is still the
+    /// one of the Raspberry Pi.
+    Linux,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Describes the hardware backend driving GPIO and PWM. All keys but
+/// `backend` only apply to [IoBackend::Linux].
+pub struct IoDescriptor {
+    pub backend: IoBackend,
+    /// The GPIO character device. Pins are the line offsets on this chip.
+    pub gpio_chip: String,
+    /// The sysfs directory of the PWM chip.
+    pub pwm_chip: String,
+    /// Maps every pin driven with PWM to its channel on `pwm_chip`.
+    pub pwm_channels: BTreeMap<u8, u32>,
+}
+
+impl Default for IoDescriptor {
+    fn default() -> Self {
+        Self {
+            backend: IoBackend::Rpi,
+            gpio_chip: String::from("/dev/gpiochip0"),
+            pwm_chip: String::from("/sys/class/pwm/pwmchip0"),
+            pwm_channels: BTreeMap::new(),
+        }
+    }
+}
@@ -1,49 +1,145 @@
-use super::super::interface::gpio::{DrivesGpio, GpioError, PullMode};
-use rppal::gpio::Gpio;
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, Sender},
+    time::Instant,
+};
+
+use super::super::interface::gpio::{
+    DrivesGpio, Edge, GpioError, GpioEvent, GpioOperation, PullMode,
+};
+use super::pins::{PinOwner, PinRegistryError, RpiPinRegistry};
+use rppal::gpio::{InputPin, Level, OutputPin, Pin, Trigger};
 
 pub struct RpiGpioDriver {
-    rpi_driver: Gpio,
+    /// The pins used by this driver. Outputs and inputs are held on to, so
+    /// that they are configured once and interrupts stay registered.
+    pins: RpiPinRegistry,
+    /// The edges watched per pin, so that an interrupt can be registered
+    /// again once its pin is reconfigured.
+    subscriptions: HashMap<u8, (Trigger, Sender<GpioEvent>)>,
 }
 
 /// Defines a driver that interfaces with the Raspberry Pi GPIO Pins on Linux
 impl RpiGpioDriver {
-    pub fn new() -> Self { Self { rpi_driver: Gpio::new().unwrap(), } }
+    pub fn new(pins: RpiPinRegistry) -> Self {
+        Self { pins, subscriptions: HashMap::new() }
+    }
+
+    /// Writes `level` to `pin_bcm`, configuring it as an output via
+    /// `configure` if it is not one yet. An output is not watched anymore.
+    fn write(&mut self, pin_bcm: u8, operation: GpioOperation, level: Level,
+             configure: impl FnOnce(Pin) -> OutputPin) -> Result<(), GpioError> {
+        self.subscriptions.remove(&pin_bcm);
+        self.pins
+            .with_output(pin_bcm, PinOwner::Gpio, |p| keep_output(configure(p)),
+                         |output_pin| output_pin.write(level))
+            .map_err(|err| io_error(pin_bcm, operation, err))
+    }
+}
+
+fn keep_output(mut output_pin: OutputPin) -> OutputPin {
+    output_pin.set_reset_on_drop(false);
+    output_pin
+}
+
+fn keep_input(mut input_pin: InputPin) -> InputPin {
+    input_pin.set_reset_on_drop(false);
+    input_pin
+}
+
+/// Sends every `trigger` edge of `input_pin` through `sender`.
+fn watch(input_pin: &mut InputPin, pin_bcm: u8, trigger: Trigger,
+         sender: Sender<GpioEvent>) -> rppal::gpio::Result<()> {
+    input_pin.set_async_interrupt(trigger, move |level| {
+        // The receiver may be gone, in which case nobody is interested
+        // anymore.
+        let _ = sender.send(GpioEvent { pin_bcm, level: level == Level::High,
+                                        at: Instant::now() });
+    })
+}
+
+fn io_error(pin_bcm: u8, operation: GpioOperation, err: PinRegistryError) -> GpioError {
+    GpioError::IO { pin_bcm, operation, cause: err.into() }
 }
 
 impl DrivesGpio for RpiGpioDriver {
     fn set_inp(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError> {
-        let p = self.rpi_driver.get(pin_bcm).expect("Could not retrieve pin");
-        match pull_mode {
-            PullMode::Up => { p.into_input_pullup().set_reset_on_drop(false); }
-            PullMode::Down => { p.into_input_pulldown().set_reset_on_drop(false); }
-        }
-        Ok(())
+        // Reconfiguring the pin releases its interrupt, which is registered
+        // again to keep the pin watched.
+        let subscription = self.subscriptions.get(&pin_bcm).cloned();
+        self.pins
+            .with_input(pin_bcm, PinOwner::Gpio, true,
+                        |p| keep_input(match pull_mode {
+                            PullMode::Up => p.into_input_pullup(),
+                            PullMode::Down => p.into_input_pulldown(),
+                        }),
+                        |input_pin| match subscription {
+                            Some((trigger, sender)) => watch(input_pin, pin_bcm, trigger, sender),
+                            None => Ok(()),
+                        })
+            .map_err(|err| io_error(pin_bcm, GpioOperation::SetInp, err))?
+            .map_err(|err| GpioError::IO {
+                pin_bcm,
+                operation: GpioOperation::SetInp,
+                cause: err.into(),
+            })
     }
 
     fn set_out(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError> {
-        let p = self.rpi_driver.get(pin_bcm).expect("Could not retrieve pin");
         match pull_mode {
-            PullMode::Up => { p.into_output_low().set_reset_on_drop(false); }
-            PullMode::Down => { p.into_output_high().set_reset_on_drop(false); }
+            PullMode::Up => self.write(pin_bcm, GpioOperation::SetOut, Level::Low,
+                                       Pin::into_output_low),
+            PullMode::Down => self.write(pin_bcm, GpioOperation::SetOut, Level::High,
+                                         Pin::into_output_high),
         }
-        Ok(())
     }
 
     fn set(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
-        let p = self.rpi_driver.get(pin_bcm).expect("Could not retrieve pin");
-        let mut output_pin = p.into_output();
-        output_pin.set_reset_on_drop(false); // TODO: Do we really need to set this flag all the
-                                             // time?
-        output_pin.set_high();
-        Ok(())
+        self.write(pin_bcm, GpioOperation::Set, Level::High, Pin::into_output)
     }
 
     fn clear(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
-        let p = self.rpi_driver.get(pin_bcm).expect("Could not retrieve pin");
-        let mut output_pin = p.into_output();
-        output_pin.set_reset_on_drop(false); // TODO: Do we really need to set this flag all the
-                                             // time?
-        output_pin.set_low();
-        Ok(())
+        self.write(pin_bcm, GpioOperation::Clear, Level::Low, Pin::into_output)
+    }
+
+    fn read(&mut self, pin_bcm: u8) -> Result<bool, GpioError> {
+        self.pins
+            .read(pin_bcm)
+            .map_err(|err| io_error(pin_bcm, GpioOperation::Read, err))
+    }
+
+    fn subscribe(&mut self, pin_bcm: u8, edge: Edge) -> Result<Receiver<GpioEvent>, GpioError> {
+        let trigger = match edge {
+            Edge::Rising => Trigger::RisingEdge,
+            Edge::Falling => Trigger::FallingEdge,
+            Edge::Both => Trigger::Both,
+        };
+        let (sender, receiver) = mpsc::channel();
+        let watched = sender.clone();
+        self.pins
+            .with_input(pin_bcm, PinOwner::Gpio, false, |p| keep_input(p.into_input()),
+                        |input_pin| watch(input_pin, pin_bcm, trigger, watched))
+            .map_err(|err| io_error(pin_bcm, GpioOperation::Subscribe, err))?
+            .map_err(|err| GpioError::IO {
+                pin_bcm,
+                operation: GpioOperation::Subscribe,
+                cause: err.into(),
+            })?;
+        self.subscriptions.insert(pin_bcm, (trigger, sender));
+        Ok(receiver)
+    }
+
+    fn unsubscribe(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        self.subscriptions.remove(&pin_bcm);
+        self.pins
+            .with_claimed_input(pin_bcm, PinOwner::Gpio, |input_pin| {
+                input_pin.clear_async_interrupt()
+            })
+            .unwrap_or(Ok(()))
+            .map_err(|err| GpioError::IO {
+                pin_bcm,
+                operation: GpioOperation::Unsubscribe,
+                cause: err.into(),
+            })
     }
 }
@@ -1,3 +1,4 @@
 pub mod gpio;
+pub mod pins;
 pub mod pwm;
 pub mod uart;
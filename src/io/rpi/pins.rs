@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use rppal::gpio::{self, Gpio, InputPin, Level, Mode, OutputPin};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Represents the driver a pin is claimed by.
+pub enum PinOwner {
+    Gpio,
+    Pwm,
+}
+
+impl fmt::Display for PinOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PinOwner::Gpio => "GPIO",
+            PinOwner::Pwm => "PWM",
+        })
+    }
+}
+
+#[derive(Debug)]
+/// Represents the errors of claiming a pin from the [RpiPinRegistry].
+pub enum PinRegistryError {
+    /// Thrown when rppal could not hand out the pin.
+    Unavailable(gpio::Error),
+    /// Thrown when the pin is already claimed by another driver.
+    Conflict { pin_bcm: u8, owner: PinOwner, requested: PinOwner },
+    /// Thrown when the pin is already driven by a peripheral of `owner`.
+    Peripheral { pin_bcm: u8, owner: PinOwner },
+}
+
+impl fmt::Display for PinRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinRegistryError::Unavailable(err) => err.fmt(f),
+            PinRegistryError::Conflict { pin_bcm, owner, requested } => {
+                write!(f, "BCM pin {} is used for {} and cannot be used for {}",
+                       pin_bcm, owner, requested)
+            }
+            PinRegistryError::Peripheral { pin_bcm, owner } => {
+                write!(f, "BCM pin {} is driven by a {} peripheral and cannot be used again",
+                       pin_bcm, owner)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PinRegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PinRegistryError::Unavailable(err) => Some(err),
+            PinRegistryError::Conflict { .. } | PinRegistryError::Peripheral { .. } => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Claim {
+    owner: PinOwner,
+    /// Whether the pin is driven by a peripheral, such as a hardware PWM
+    /// channel, and must not be reconfigured.
+    peripheral: bool,
+}
+
+#[derive(Debug, Default)]
+/// Keeps track of which driver every pin is claimed by, without touching the
+/// hardware. A driver may use its pins as often as it likes, but a pin
+/// driven by a peripheral is claimed once only.
+pub struct PinClaims {
+    claims: HashMap<u8, Claim>,
+}
+
+impl PinClaims {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that `pin_bcm` may be used by `owner`.
+    fn check(&self, pin_bcm: u8, owner: PinOwner) -> Result<(), PinRegistryError> {
+        match self.claims.get(&pin_bcm) {
+            Some(claim) if claim.owner != owner => Err(PinRegistryError::Conflict {
+                pin_bcm,
+                owner: claim.owner,
+                requested: owner,
+            }),
+            Some(claim) if claim.peripheral => {
+                Err(PinRegistryError::Peripheral { pin_bcm, owner })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Claims `pin_bcm` for `owner`. Claiming a pin again for its owner
+    /// succeeds, unless a peripheral drives it.
+    pub fn claim(&mut self, pin_bcm: u8, owner: PinOwner) -> Result<(), PinRegistryError> {
+        self.check(pin_bcm, owner)?;
+        self.claims.insert(pin_bcm, Claim { owner, peripheral: false });
+        Ok(())
+    }
+
+    /// Claims `pin_bcm` for a peripheral of `owner`, which no one, including
+    /// `owner`, may claim the pin for again until it is released.
+    pub fn claim_peripheral(&mut self, pin_bcm: u8,
+                            owner: PinOwner) -> Result<(), PinRegistryError> {
+        self.check(pin_bcm, owner)?;
+        self.claims.insert(pin_bcm, Claim { owner, peripheral: true });
+        Ok(())
+    }
+
+    /// Frees `pin_bcm`, if it is claimed.
+    pub fn release(&mut self, pin_bcm: u8) {
+        self.claims.remove(&pin_bcm);
+    }
+
+    /// Returns the driver `pin_bcm` is claimed by, if any.
+    pub fn owner(&self, pin_bcm: u8) -> Option<PinOwner> {
+        self.claims.get(&pin_bcm).map(|claim| claim.owner)
+    }
+}
+
+/// A pin handle held by the registry.
+enum PinHandle {
+    Output(OutputPin),
+    Input(InputPin),
+}
+
+struct Registry {
+    gpio: Gpio,
+    claims: PinClaims,
+    /// The handles of the claimed pins. Pins driven by a peripheral have
+    /// none.
+    handles: HashMap<u8, PinHandle>,
+}
+
+#[derive(Clone)]
+/// Owns the rppal handles of all pins in use, shared between the
+/// [RpiGpioDriver](super::gpio::RpiGpioDriver) and the
+/// [RpiPwmDriver](super::pwm::RpiPwmDriver).
+///
+/// A pin is claimed by the first driver using it and configured only when
+/// its direction changes, so that repeated writes do not reconfigure it.
+/// Using a pin claimed by the other driver is rejected with a
+/// [PinRegistryError::Conflict], as [PinClaims] decides.
+pub struct RpiPinRegistry {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl RpiPinRegistry {
+    pub fn new() -> Result<Self, gpio::Error> {
+        Ok(Self {
+            registry: Arc::new(Mutex::new(Registry {
+                gpio: Gpio::new()?,
+                claims: PinClaims::new(),
+                handles: HashMap::new(),
+            })),
+        })
+    }
+
+    /// Runs `f` on `pin_bcm` as an output, claiming it for `owner` and
+    /// configuring it via `configure` if it is not an output yet.
+    pub(crate) fn with_output<R>(
+        &self,
+        pin_bcm: u8,
+        owner: PinOwner,
+        configure: impl FnOnce(gpio::Pin) -> OutputPin,
+        f: impl FnOnce(&mut OutputPin) -> R,
+    ) -> Result<R, PinRegistryError> {
+        let mut registry = self.registry.lock().unwrap();
+        registry.claims.claim(pin_bcm, owner)?;
+
+        if !matches!(registry.handles.get(&pin_bcm), Some(PinHandle::Output(_))) {
+            // Release any other handle before retrieving the pin again.
+            registry.handles.remove(&pin_bcm);
+            let pin = registry.gpio.get(pin_bcm).map_err(PinRegistryError::Unavailable)?;
+            registry.handles.insert(pin_bcm, PinHandle::Output(configure(pin)));
+        }
+
+        match registry.handles.get_mut(&pin_bcm) {
+            Some(PinHandle::Output(output_pin)) => Ok(f(output_pin)),
+            _ => unreachable!("the pin was just configured as an output"),
+        }
+    }
+
+    /// Runs `f` on `pin_bcm` as an input, claiming it for `owner`. The pin is
+    /// configured via `configure` if it is not an input yet or if
+    /// `reconfigure` is set.
+    pub(crate) fn with_input<R>(
+        &self,
+        pin_bcm: u8,
+        owner: PinOwner,
+        reconfigure: bool,
+        configure: impl FnOnce(gpio::Pin) -> InputPin,
+        f: impl FnOnce(&mut InputPin) -> R,
+    ) -> Result<R, PinRegistryError> {
+        let mut registry = self.registry.lock().unwrap();
+        registry.claims.claim(pin_bcm, owner)?;
+
+        if reconfigure || !matches!(registry.handles.get(&pin_bcm), Some(PinHandle::Input(_))) {
+            registry.handles.remove(&pin_bcm);
+            let pin = registry.gpio.get(pin_bcm).map_err(PinRegistryError::Unavailable)?;
+            registry.handles.insert(pin_bcm, PinHandle::Input(configure(pin)));
+        }
+
+        match registry.handles.get_mut(&pin_bcm) {
+            Some(PinHandle::Input(input_pin)) => Ok(f(input_pin)),
+            _ => unreachable!("the pin was just configured as an input"),
+        }
+    }
+
+    /// Claims `pin_bcm` for `owner` on behalf of a peripheral, leaving its
+    /// configuration untouched. See [PinClaims::claim_peripheral].
+    pub(crate) fn claim_peripheral(&self, pin_bcm: u8,
+                                   owner: PinOwner) -> Result<(), PinRegistryError> {
+        let mut registry = self.registry.lock().unwrap();
+        registry.claims.claim_peripheral(pin_bcm, owner)?;
+        registry.handles.remove(&pin_bcm);
+        Ok(())
+    }
+
+    /// Frees `pin_bcm` and drops its handle, if any.
+    pub(crate) fn release(&self, pin_bcm: u8) {
+        let mut registry = self.registry.lock().unwrap();
+        registry.claims.release(pin_bcm);
+        registry.handles.remove(&pin_bcm);
+    }
+
+    /// Reads the level of `pin_bcm`, `true` being high. Pins that are not
+    /// claimed are read without claiming them.
+    pub(crate) fn read(&self, pin_bcm: u8) -> Result<bool, PinRegistryError> {
+        let registry = self.registry.lock().unwrap();
+        match registry.handles.get(&pin_bcm) {
+            Some(PinHandle::Output(output_pin)) => Ok(output_pin.is_set_high()),
+            Some(PinHandle::Input(input_pin)) => Ok(input_pin.is_high()),
+            None => {
+                let pin = registry.gpio.get(pin_bcm).map_err(PinRegistryError::Unavailable)?;
+                Ok(pin.read() == Level::High)
+            }
+        }
+    }
+
+    /// Returns the function `pin_bcm` is currently configured for. Pins that
+    /// are not claimed are inspected without claiming them.
+    pub(crate) fn mode(&self, pin_bcm: u8) -> Result<Mode, PinRegistryError> {
+        let registry = self.registry.lock().unwrap();
+        match registry.handles.get(&pin_bcm) {
+            Some(PinHandle::Output(_)) => Ok(Mode::Output),
+            Some(PinHandle::Input(_)) => Ok(Mode::Input),
+            None => {
+                let pin = registry.gpio.get(pin_bcm).map_err(PinRegistryError::Unavailable)?;
+                Ok(pin.mode())
+            }
+        }
+    }
+
+    /// Runs `f` on the input handle of `pin_bcm` if it is claimed by `owner`
+    /// as an input.
+    pub(crate) fn with_claimed_input<R>(
+        &self,
+        pin_bcm: u8,
+        owner: PinOwner,
+        f: impl FnOnce(&mut InputPin) -> R,
+    ) -> Option<R> {
+        let mut registry = self.registry.lock().unwrap();
+        if registry.claims.owner(pin_bcm) != Some(owner) {
+            return None;
+        }
+        match registry.handles.get_mut(&pin_bcm) {
+            Some(PinHandle::Input(input_pin)) => Some(f(input_pin)),
+            _ => None,
+        }
+    }
+}
@@ -1,15 +1,137 @@
+use std::collections::HashMap;
+
 use crate::io::interface::pwm::{DrivesPwm, PwmError};
-use rppal::gpio::{self, Gpio};
-use uom::si::f32::Frequency;
+use super::pins::{PinOwner, RpiPinRegistry};
+use rppal::{gpio::{Mode, Pin}, pwm::{Channel, Polarity, Pwm}};
+use uom::si::{f32::Frequency, frequency::hertz};
+
+/// Returns the hardware PWM channel `pin_bcm` can be routed to, if any.
+///
+/// Only the pins on the 40 pin header are considered. Routing a pin to its
+/// channel is left to the `pwm-2chan` device tree overlay, see [is_routed].
+pub fn hardware_channel(pin_bcm: u8) -> Option<Channel> {
+    match pin_bcm {
+        12 | 18 => Some(Channel::Pwm0),
+        13 | 19 => Some(Channel::Pwm1),
+        _ => None,
+    }
+}
+
+/// Returns whether `pin_bcm`, configured for `mode`, is routed to its
+/// hardware PWM channel. BCM 12 and 13 carry the channels as their Alt0
+/// function, BCM 18 and 19 as their Alt5 function.
+pub fn is_routed(pin_bcm: u8, mode: Mode) -> bool {
+    let routed_mode = match pin_bcm {
+        12 | 13 => Mode::Alt0,
+        18 | 19 => Mode::Alt5,
+        _ => return false,
+    };
+    mode == routed_mode
+}
+
+#[derive(Default)]
+/// Hands out each hardware PWM channel to at most one pin. Pins which cannot
+/// get a channel are driven with software PWM.
+pub struct PwmChannelMap {
+    owners: [Option<u8>; 2],
+}
+
+impl PwmChannelMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns `pin_bcm` its hardware channel, returning `None` if the pin has
+    /// none or if the channel is already assigned to another pin.
+    pub fn assign(&mut self, pin_bcm: u8) -> Option<Channel> {
+        let channel = hardware_channel(pin_bcm)?;
+        match &mut self.owners[channel as usize] {
+            Some(owner) if *owner != pin_bcm => None,
+            owner => {
+                *owner = Some(pin_bcm);
+                Some(channel)
+            }
+        }
+    }
+
+    /// Returns the channel assigned to `pin_bcm`, if any.
+    pub fn channel(&self, pin_bcm: u8) -> Option<Channel> {
+        hardware_channel(pin_bcm)
+            .filter(|&channel| self.owners[channel as usize] == Some(pin_bcm))
+    }
+
+    /// Frees the channel assigned to `pin_bcm`, if any.
+    pub fn release(&mut self, pin_bcm: u8) {
+        if let Some(channel) = self.channel(pin_bcm) {
+            self.owners[channel as usize] = None;
+        }
+    }
+}
+
+/// How a pin is driven by the [RpiPwmDriver].
+enum PwmOutput {
+    Hardware(Pwm),
+    Software,
+}
 
+/// Defines a driver that drives PWM on the Raspberry Pi. Pins which are
+/// routed to a hardware PWM channel are driven by it, all others (and those
+/// whose channel cannot be opened) with rppal software PWM.
 pub struct RpiPwmDriver {
-    rpi_driver: Gpio,
+    /// The pins used by this driver. Their outputs are held on to, so that
+    /// the software PWM keeps running between calls.
+    pins: RpiPinRegistry,
+    channels: PwmChannelMap,
+    outputs: HashMap<u8, PwmOutput>,
 }
 
 impl RpiPwmDriver {
-    pub fn new() -> Self {
-        Self {
-            rpi_driver: Gpio::new().unwrap(),
+    pub fn new(pins: RpiPinRegistry) -> Self {
+        Self { pins, channels: PwmChannelMap::new(), outputs: HashMap::new() }
+    }
+
+    /// Decides how to drive `pin_bcm` on its first use.
+    fn open(&mut self, pin_bcm: u8, freq_hz: f64, duty_cycle: f64) -> Result<PwmOutput, PwmError> {
+        let channel = match self.channels.assign(pin_bcm) {
+            None => return Ok(PwmOutput::Software),
+            Some(channel) => channel,
+        };
+        // Without the overlay the channel runs, but its signal never reaches
+        // the pin.
+        match self.pins.mode(pin_bcm) {
+            Ok(mode) if is_routed(pin_bcm, mode) => (),
+            Ok(mode) => {
+                log::warn!(target: "system.io.pwm",
+                           "Pin {} is configured as {} and not routed to hardware PWM channel \
+                            {}, falling back to software PWM. Load the pwm-2chan overlay to \
+                            route it.", pin_bcm, mode, channel);
+                self.channels.release(pin_bcm);
+                return Ok(PwmOutput::Software);
+            }
+            Err(err) => {
+                self.channels.release(pin_bcm);
+                return Err(PwmError::IO { pin_bcm, cause: err.into() });
+            }
+        }
+        if let Err(err) = self.pins.claim_peripheral(pin_bcm, PinOwner::Pwm) {
+            self.channels.release(pin_bcm);
+            return Err(PwmError::IO { pin_bcm, cause: err.into() });
+        }
+
+        match Pwm::with_frequency(channel, freq_hz, duty_cycle, Polarity::Normal, true) {
+            Ok(pwm) => {
+                log::info!(target: "system.io.pwm",
+                           "Driving pin {} with hardware PWM channel {}.", pin_bcm, channel);
+                Ok(PwmOutput::Hardware(pwm))
+            }
+            Err(err) => {
+                log::warn!(target: "system.io.pwm",
+                           "Could not open hardware PWM channel {} for pin {}, falling back to \
+                            software PWM. {}", channel, pin_bcm, err);
+                self.channels.release(pin_bcm);
+                self.pins.release(pin_bcm);
+                Ok(PwmOutput::Software)
+            }
         }
     }
 }
@@ -21,20 +143,22 @@ impl DrivesPwm for RpiPwmDriver {
         duty_cycle: f32,
         pin_bcm: u8,
     ) -> Result<(), PwmError> {
-        match self.rpi_driver.get(pin_bcm) {
-            Err(err) => {
-                return Err(PwmError::IO);
-            }
-            Ok(pin) => {
-                let freq_hz = frequency.value;
-                if let Err(err) = pin
-                    .into_output()
-                    .set_pwm_frequency(freq_hz as f64, duty_cycle as f64)
-                {
-                    return Err(PwmError::IO);
-                }
-            }
+        let freq_hz = frequency.get::<hertz>() as f64;
+        let duty_cycle = duty_cycle as f64;
+        if !self.outputs.contains_key(&pin_bcm) {
+            let output = self.open(pin_bcm, freq_hz, duty_cycle)?;
+            self.outputs.insert(pin_bcm, output);
+        }
+
+        match &self.outputs[&pin_bcm] {
+            PwmOutput::Hardware(pwm) => pwm
+                .set_frequency(freq_hz, duty_cycle)
+                .map_err(|err| PwmError::IO { pin_bcm, cause: err.into() }),
+            PwmOutput::Software => self.pins
+                .with_output(pin_bcm, PinOwner::Pwm, Pin::into_output,
+                             |output_pin| output_pin.set_pwm_frequency(freq_hz, duty_cycle))
+                .map_err(|err| PwmError::IO { pin_bcm, cause: err.into() })?
+                .map_err(|err| PwmError::IO { pin_bcm, cause: err.into() }),
         }
-        Ok(())
     }
 }
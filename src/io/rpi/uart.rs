@@ -1,36 +1,87 @@
+use std::time::Duration;
+
 use rppal::uart::{Uart, Parity};
-use crate::io::interface::uart::{UartDescriptor, UartParity};
+use crate::io::interface::uart::{
+    UartDescriptor, UartParity, UartRingBuffer, UART_RING_BUFFER_CAPACITY,
+};
+
+use super::super::interface::uart::{DrivesUart, UartError, UartOperation};
 
-use super::super::interface::uart::{DrivesUart, UartError};
+/// The number of bytes moved from the device into the ring buffer at once.
+const DEVICE_READ_CHUNK_LEN: usize = 256;
 
 impl UartParity {
-    fn to_rppal(&self) -> Parity {
+    fn to_rppal(self) -> Parity {
         match self {
             UartParity::None => Parity::None,
-            Even => Parity::Even,
-            Odd => Parity::Odd,
-            Mark => Parity::Mark,
-            Space => Parity::Space
+            UartParity::Even => Parity::Even,
+            UartParity::Odd => Parity::Odd,
+            UartParity::Mark => Parity::Mark,
+            UartParity::Space => Parity::Space
         }
     }
 }
 
+/// Defines a driver that interfaces with the Raspberry Pi UART. The device is
+/// operated in non-blocking mode and every read first moves all pending bytes
+/// from the device into a ring buffer.
 pub struct RpiUartDriver {
     rpi_driver: Uart,
+    ring_buffer: UartRingBuffer,
 }
 
 impl RpiUartDriver {
-    pub fn new(uart_descriptor: UartDescriptor) -> Self {
-        Self {
-            rpi_driver: Uart::new(uart_descriptor.baud_rate, uart_descriptor.parity.to_rppal(),
-                                  uart_descriptor.data_bits, uart_descriptor.stop_bits).expect(
-                                      "Could not initialize the rppal Uart driver")
+    pub fn new(uart_descriptor: UartDescriptor) -> Result<Self, UartError> {
+        let init = |err: rppal::uart::Error| UartError::Init(err.into());
+        let mut rpi_driver = Uart::new(uart_descriptor.baud_rate,
+                                       uart_descriptor.parity.to_rppal(),
+                                       uart_descriptor.data_bits,
+                                       uart_descriptor.stop_bits).map_err(init)?;
+        rpi_driver.set_read_mode(0, Duration::ZERO).map_err(init)?;
+        rpi_driver.set_write_mode(false).map_err(init)?;
+
+        Ok(Self {
+            rpi_driver,
+            ring_buffer: UartRingBuffer::new(UART_RING_BUFFER_CAPACITY),
+        })
+    }
+
+    /// Moves all bytes pending on the device into the ring buffer.
+    fn fill_ring_buffer(&mut self) -> Result<(), UartError> {
+        let mut chunk = [0u8; DEVICE_READ_CHUNK_LEN];
+        loop {
+            match self.rpi_driver.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(count) => self.ring_buffer.push(&chunk[..count]),
+                Err(err) => {
+                    return Err(UartError::IO { operation: UartOperation::Read, cause: err.into() });
+                }
+            }
         }
+
+        let dropped = self.ring_buffer.take_dropped();
+        if dropped > 0 {
+            log::warn!(target: "system.io.uart", "Ring buffer overflow, dropped {} bytes", dropped);
+        }
+        Ok(())
     }
 }
 
 impl DrivesUart for RpiUartDriver {
-    fn read_bytes(&mut self, into: &mut [u8]) -> Result<Box<[u8]>, UartError> {
-        panic!("Not implemented") // TODO
+    fn read_bytes(&mut self, into: &mut [u8]) -> Result<usize, UartError> {
+        self.fill_ring_buffer()?;
+        Ok(self.ring_buffer.pop_into(into))
+    }
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<usize, UartError> {
+        self.rpi_driver
+            .write(data)
+            .map_err(|err| UartError::IO { operation: UartOperation::Write, cause: err.into() })
+    }
+
+    fn flush(&mut self) -> Result<(), UartError> {
+        self.rpi_driver
+            .drain()
+            .map_err(|err| UartError::IO { operation: UartOperation::Flush, cause: err.into() })
     }
 }
@@ -1,50 +1,149 @@
-use std::{fs::File, io::Write, time::Instant};
+use std::{
+    collections::HashMap,
+    sync::{mpsc::{self, Receiver, Sender}, Arc, Mutex},
+    time::Instant,
+};
 
-use super::super::interface::gpio::{DrivesGpio, GpioError, PullMode};
+use super::super::interface::gpio::{
+    DrivesGpio, Edge, GpioError, GpioEvent, GpioOperation, PullMode,
+};
+use super::{sink::TraceSink, trace::{TraceDirection, TraceEvent, TracePull}};
 
-/// This GPIO IO implementation outputs its data to an injected file. The
-/// encoding is a tremendously simple CSV-style output that can be used to
-/// fully reconstruct the outputs.
+/// This GPIO IO implementation outputs its data to an injected trace sink, which
+/// can be used to fully reconstruct the outputs. Every configuration is
+/// traced as a [TraceEvent::Configure] and every output state change as a
+/// [TraceEvent::Level]. See [trace](super::trace) for the format.
 ///
-/// The message always appears in one single line and may look like this:
-/// * `S,<PIN: int>,<STATE: {0/1}>`
-/// * `C,<PIN: int>,<DIRECTION: {I/O}>,<PULL_MODE: {D/U}>`
-///
-/// A `C` packet indicates a GPIO configuration while an `S` packet represents
-/// an output state change.
+/// The levels of input pins are simulated by [SimGpioInputs], a handle to
+/// which is retrieved via [PrintGpioDriver::inputs].
 pub struct PrintGpioDriver {
-    file: File,
-    init_time: Instant
+    trace: TraceSink,
+    inputs: SimGpioInputs,
 }
 
 impl PrintGpioDriver {
-    pub fn new(file: File, init_time: Instant) -> PrintGpioDriver {
-        PrintGpioDriver { file, init_time }
+    pub fn new(trace: TraceSink) -> PrintGpioDriver {
+        PrintGpioDriver { trace, inputs: SimGpioInputs::default() }
+    }
+
+    /// Traces `event` which was caused by `operation` on `pin_bcm`.
+    fn trace(&mut self, event: TraceEvent, pin_bcm: u8,
+             operation: GpioOperation) -> Result<(), GpioError> {
+        self.trace
+            .record(event)
+            .map_err(|err| GpioError::IO { pin_bcm, operation, cause: err.into() })
+    }
+
+    /// Returns a handle through which the simulated pin levels are driven.
+    pub fn inputs(&self) -> SimGpioInputs {
+        self.inputs.clone()
+    }
+}
+
+#[derive(Default)]
+struct SimPinState {
+    levels: HashMap<u8, bool>,
+    subscribers: HashMap<u8, (Edge, Sender<GpioEvent>)>,
+}
+
+#[derive(Clone, Default)]
+/// The simulated levels of the pins of a [PrintGpioDriver], shared with
+/// whatever simulates the outside world (a test, a physics simulation, ...).
+/// Pins that were never driven read low, unless configured as pulled-up
+/// inputs.
+pub struct SimGpioInputs {
+    state: Arc<Mutex<SimPinState>>,
+}
+
+impl SimGpioInputs {
+    /// Drives `pin_bcm` to `level`, sending a [GpioEvent] to its subscriber
+    /// if this is a matching edge.
+    pub fn set_level(&self, pin_bcm: u8, level: bool) {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.levels.insert(pin_bcm, level).unwrap_or(false);
+        if previous == level {
+            return;
+        }
+
+        let closed = match state.subscribers.get(&pin_bcm) {
+            Some((edge, sender)) if edge.matches(level) => {
+                sender.send(GpioEvent { pin_bcm, level, at: Instant::now() }).is_err()
+            }
+            _ => false,
+        };
+        if closed {
+            state.subscribers.remove(&pin_bcm);
+        }
+    }
+
+    /// Returns the current level of `pin_bcm`.
+    pub fn level(&self, pin_bcm: u8) -> bool {
+        self.state.lock().unwrap().levels.get(&pin_bcm).copied().unwrap_or(false)
+    }
+
+    /// Sets the level an undriven pin floats to.
+    fn pull(&self, pin_bcm: u8, level: bool) {
+        self.state.lock().unwrap().levels.entry(pin_bcm).or_insert(level);
+    }
+
+    fn subscribe(&self, pin_bcm: u8, edge: Edge) -> Receiver<GpioEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.state.lock().unwrap().subscribers.insert(pin_bcm, (edge, sender));
+        receiver
+    }
+
+    fn unsubscribe(&self, pin_bcm: u8) {
+        self.state.lock().unwrap().subscribers.remove(&pin_bcm);
+    }
+}
+
+fn trace_pull(pull_mode: PullMode) -> TracePull {
+    match pull_mode {
+        PullMode::Up => TracePull::Up,
+        PullMode::Down => TracePull::Down,
     }
 }
 
 impl DrivesGpio for PrintGpioDriver {
     fn set(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
-        let time_since_init = Instant::now() - self.init_time;
-        writeln!(self.file, "{:},1,S,{:},1 ; PrintGpioDriver", time_since_init.as_secs_f64(), pin_bcm);
-        Result::Ok(())
+        self.inputs.set_level(pin_bcm, true);
+        self.trace(TraceEvent::Level { pin_bcm, level: true }, pin_bcm, GpioOperation::Set)
     }
 
     fn clear(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
-        let time_since_init = Instant::now() - self.init_time;
-        writeln!(self.file, "{:},1,S,{:},0 ; PrintGpioDriver", time_since_init.as_secs_f64(), pin_bcm);
-        Result::Ok(())
+        self.inputs.set_level(pin_bcm, false);
+        self.trace(TraceEvent::Level { pin_bcm, level: false }, pin_bcm, GpioOperation::Clear)
     }
 
     fn set_out(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError> {
-        let time_since_init = Instant::now() - self.init_time;
-        writeln!(self.file, "{:},1,C,{:},O,{:?} ; PrintGpioDriver", time_since_init.as_secs_f64(), pin_bcm, pull_mode);
-        Result::Ok(())
+        let event = TraceEvent::Configure {
+            pin_bcm,
+            direction: TraceDirection::Output,
+            pull: trace_pull(pull_mode),
+        };
+        self.trace(event, pin_bcm, GpioOperation::SetOut)
     }
 
     fn set_inp(&mut self, pin_bcm: u8, pull_mode: PullMode) -> Result<(), GpioError> {
-        let time_since_init = Instant::now() - self.init_time;
-        writeln!(self.file, "{:},1,C,{:},I,{:?} ; PrintGpioDriver", time_since_init.as_secs_f64(), pin_bcm, pull_mode);
-        Result::Ok(())
+        self.inputs.pull(pin_bcm, matches!(pull_mode, PullMode::Up));
+        let event = TraceEvent::Configure {
+            pin_bcm,
+            direction: TraceDirection::Input,
+            pull: trace_pull(pull_mode),
+        };
+        self.trace(event, pin_bcm, GpioOperation::SetInp)
+    }
+
+    fn read(&mut self, pin_bcm: u8) -> Result<bool, GpioError> {
+        Ok(self.inputs.level(pin_bcm))
+    }
+
+    fn subscribe(&mut self, pin_bcm: u8, edge: Edge) -> Result<Receiver<GpioEvent>, GpioError> {
+        Ok(self.inputs.subscribe(pin_bcm, edge))
+    }
+
+    fn unsubscribe(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        self.inputs.unsubscribe(pin_bcm);
+        Ok(())
     }
 }
@@ -1,3 +1,5 @@
 pub mod gpio;
 pub mod pwm;
+pub mod sink;
+pub mod trace;
 pub mod uart;
@@ -1,27 +1,54 @@
-use std::{fs::File, io::Write, time::{Instant, Duration}, thread::{self, JoinHandle}, collections::HashMap, borrow::Borrow, sync::{Arc, atomic::AtomicBool, Mutex}};
+use std::{collections::HashMap, sync::{Arc, Mutex}};
 
 use super::super::interface::pwm::{DrivesPwm, PwmError};
-use uom::si::f32::Frequency;
+use super::{sink::TraceSink, trace::TraceEvent};
+use uom::si::{f32::Frequency, frequency::hertz};
 
+/// This PWM IO implementation outputs its data to an injected trace sink. Every
+/// change of the settings of a pin is traced as a [TraceEvent::Pwm], which
+/// describes the waveform of the pin until the next change. See
+/// [trace](super::trace) for the format.
+///
+/// No waveform is generated. Whatever simulates the outside world reads the
+/// duty cycles through [SimPwmOutputs] on its own tick instead, the way the
+/// physics simulation averages the motor voltage.
 pub struct PrintPwmDriver {
-    file: Arc<Mutex<File>>,
-    begin_time: Instant,
-    pwm_pin_map: HashMap<u8, (JoinHandle<()>, Arc<Mutex<(Frequency, f32)>>)>
+    trace: TraceSink,
+    /// The frequency and duty cycle last set on every pin.
+    settings: HashMap<u8, (Frequency, f32)>,
+    outputs: SimPwmOutputs,
 }
 
 impl PrintPwmDriver {
-    pub fn new(file: File, begin_time: Instant) -> PrintPwmDriver {
-        let driver = PrintPwmDriver {
-            file: Arc::new(Mutex::new(file)),
-            begin_time,
-            pwm_pin_map: HashMap::new()
-        };
-        driver
+    pub fn new(trace: TraceSink) -> PrintPwmDriver {
+        PrintPwmDriver { trace, settings: HashMap::new(), outputs: SimPwmOutputs::default() }
+    }
+
+    /// Returns a handle through which the duty cycles of the pins are read.
+    pub fn outputs(&self) -> SimPwmOutputs {
+        self.outputs.clone()
+    }
+}
+
+#[derive(Clone, Default)]
+/// The duty cycles last set on the pins of a [PrintPwmDriver], shared with
+/// whatever simulates the outside world. Pins that were never set have a duty
+/// cycle of 0.
+pub struct SimPwmOutputs {
+    duty_cycles: Arc<Mutex<HashMap<u8, f32>>>,
+}
+
+impl SimPwmOutputs {
+    /// Returns the duty cycle of `pin_bcm`.
+    pub fn duty_cycle(&self, pin_bcm: u8) -> f32 {
+        self.duty_cycles.lock().unwrap().get(&pin_bcm).copied().unwrap_or(0.0)
+    }
+
+    fn set(&self, pin_bcm: u8, duty_cycle: f32) {
+        self.duty_cycles.lock().unwrap().insert(pin_bcm, duty_cycle);
     }
 }
 
-/// Due to the fact that the PWM used on the raspberry pi is a software PWM, this emulates that
-/// behavior via threads. Shutdown is ungraceful, but who cares?
 impl DrivesPwm for PrintPwmDriver {
     fn set_freq_dc(
         &mut self,
@@ -29,37 +56,17 @@ impl DrivesPwm for PrintPwmDriver {
         duty_cycle: f32,
         pin_bcm: u8,
     ) -> Result<(), PwmError> {
-        if !self.pwm_pin_map.contains_key(&pin_bcm) {
-            self.pwm_pin_map.insert(pin_bcm, (
-                thread::spawn(||{}),
-                Arc::new(Mutex::new((frequency, duty_cycle)))
-            ));
-
-            let file = Arc::clone(&self.file);
-            let begin_time = self.begin_time;
-            let data_mutex = Arc::clone(&self.pwm_pin_map[&pin_bcm].1);
-
-            let work_thread = thread::spawn(move || {
-                loop {
-                    let (frequency, duty_cycle) = data_mutex.lock().unwrap().clone();
-
-                    let time_since_start = Instant::now() - begin_time;
-                    let period = 1f32 / frequency.value;
-                    writeln!(file.lock().unwrap(), "{:},1,S,{:},1 ; PrintPwmDriver {:}Hz, {:}DC", time_since_start.as_secs_f64(),
-                             pin_bcm, frequency.value, duty_cycle);
-                    thread::sleep(Duration::from_secs_f32(duty_cycle * period));
-                    writeln!(file.lock().unwrap(), "{:},1,S,{:},0 ; PrintPwmDriver {:}Hz, {:}DC", time_since_start.as_secs_f64(),
-                             pin_bcm, frequency.value, duty_cycle);
-                    thread::sleep(Duration::from_secs_f32((1f32 - duty_cycle) * period));
-                }
-            });
-
-            self.pwm_pin_map.get_mut(&pin_bcm).unwrap().0 = work_thread;
-        } else {
-            let pin_data_arc = self.pwm_pin_map[&pin_bcm].1.clone();
-            let mut pin_data = pin_data_arc.lock().unwrap();
-            *pin_data = (frequency, duty_cycle);
+        if self.settings.get(&pin_bcm) != Some(&(frequency, duty_cycle)) {
+            self.trace
+                .record(TraceEvent::Pwm {
+                    pin_bcm,
+                    frequency_hz: frequency.get::<hertz>(),
+                    duty_cycle,
+                })
+                .map_err(|err| PwmError::IO { pin_bcm, cause: err.into() })?;
+            self.settings.insert(pin_bcm, (frequency, duty_cycle));
         }
+        self.outputs.set(pin_bcm, duty_cycle);
         Ok(())
     }
 }
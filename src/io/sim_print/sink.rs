@@ -0,0 +1,101 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::trace::{TraceEvent, TraceFormat, TraceRecord, TraceWriter};
+
+/// How long records may sit in the buffer before they are written out.
+const FLUSH_PERIOD: Duration = Duration::from_millis(100);
+
+struct SinkState {
+    writer: TraceWriter<Box<dyn Write + Send>>,
+    begin_time: Instant,
+    last_flush: Instant,
+}
+
+#[derive(Clone)]
+/// The single trace all `sim_print` drivers write to. Clones share the same
+/// underlying writer.
+///
+/// Records are timestamped and written while holding the lock of the sink,
+/// so they appear in the order they were recorded, are never interleaved
+/// with one another and carry monotonic timestamps. Records are buffered and
+/// written out at least every 100 ms by a background thread, which stops once
+/// the last clone is dropped, and by [TraceSink::flush], which must be called
+/// on shutdown.
+pub struct TraceSink {
+    state: Arc<Mutex<SinkState>>,
+}
+
+impl TraceSink {
+    /// Creates (or truncates) the file at `path` and writes a trace to it.
+    ///
+    /// Arguments:
+    /// * `format` - The encoding of the trace.
+    /// * `begin_time` - The time the simulation started, which the record
+    ///   timestamps are relative to.
+    pub fn create<P: AsRef<Path>>(path: P, format: TraceFormat,
+                                  begin_time: Instant) -> io::Result<TraceSink> {
+        Self::new(BufWriter::new(File::create(path)?), format, begin_time)
+    }
+
+    /// Creates a sink writing a trace to `inner`.
+    pub fn new<W: Write + Send + 'static>(inner: W, format: TraceFormat,
+                                          begin_time: Instant) -> io::Result<TraceSink> {
+        let writer = TraceWriter::new(Box::new(inner) as Box<dyn Write + Send>, format)?;
+        let state = Arc::new(Mutex::new(SinkState {
+            writer,
+            begin_time,
+            last_flush: Instant::now(),
+        }));
+        spawn_flusher(Arc::downgrade(&state));
+        Ok(TraceSink { state })
+    }
+
+    /// Records `event`, timestamped now.
+    pub fn record(&self, event: TraceEvent) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        // Timestamping under the lock keeps the timestamps in record order.
+        let time = state.begin_time.elapsed();
+        state.writer.write(&TraceRecord::new(time, event))?;
+
+        if state.last_flush.elapsed() >= FLUSH_PERIOD {
+            state.last_flush = Instant::now();
+            state.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes out all buffered records.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.last_flush = Instant::now();
+        state.writer.flush()
+    }
+}
+
+/// Writes out the records buffered in `state` every [FLUSH_PERIOD] until the
+/// sink is dropped, so that a quiet simulation does not hold back its last
+/// records.
+fn spawn_flusher(state: Weak<Mutex<SinkState>>) {
+    thread::spawn(move || loop {
+        thread::sleep(FLUSH_PERIOD);
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+        let mut state = state.lock().unwrap();
+        if state.last_flush.elapsed() < FLUSH_PERIOD {
+            continue;
+        }
+        state.last_flush = Instant::now();
+        if let Err(err) = state.writer.flush() {
+            log::error!(target: "system.io.trace", "Could not write the trace. {}", err);
+        }
+    });
+}
@@ -0,0 +1,519 @@
+/// This module defines the trace format written by the `sim_print` drivers,
+/// along with a writer and a reader for it.
+///
+/// A trace starts with a header naming the format and its version, followed
+/// by one record per event. Every record carries the time since the
+/// simulation started. There are two encodings of the same records.
+///
+/// # CSV
+///
+/// The header is the line `#cocos-trace,<VERSION>`. Each following line holds
+/// one record, `<TIME>` being seconds with nanosecond precision:
+/// * `<TIME>,S,<PIN>,<LEVEL: {0/1}>` - a pin was driven to a level.
+/// * `<TIME>,C,<PIN>,<DIRECTION: {I/O}>,<PULL_MODE: {U/D}>` - a pin was
+///   configured.
+/// * `<TIME>,P,<PIN>,<FREQUENCY_HZ>,<DUTY_CYCLE>` - the PWM of a pin changed.
+///   The pin carries this waveform until its next `P` record.
+/// * `<TIME>,T,<BYTES: hex>` - bytes were transmitted on the UART.
+///
+/// Empty lines are skipped.
+///
+/// # Binary
+///
+/// The header is the magic `CCTR` followed by the version as a little endian
+/// `u16`. Each record is the time in nanoseconds as a little endian `u64`, the
+/// kind letter of the CSV encoding as a byte and its fields:
+/// * `S`: the pin and the level as one byte each.
+/// * `C`: the pin, the direction letter and the pull mode letter as one byte
+///   each.
+/// * `P`: the pin as one byte, the frequency and duty cycle as little endian
+///   `f32`s.
+/// * `T`: the number of bytes as a little endian `u16`, then the bytes. Longer
+///   transmissions are split into several records.
+use std::{
+    fmt,
+    io::{self, BufRead, Read, Write},
+    time::Duration,
+};
+
+/// The version of the trace format written by [TraceWriter].
+pub const TRACE_VERSION: u16 = 1;
+
+/// The header prefix of a CSV trace.
+const CSV_HEADER: &str = "#cocos-trace";
+
+/// The magic a binary trace starts with.
+const BINARY_MAGIC: &[u8; 4] = b"CCTR";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Enumerates the encodings of a trace.
+pub enum TraceFormat {
+    Csv,
+    Binary,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The direction a pin was configured in.
+pub enum TraceDirection {
+    Input,
+    Output,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The pull mode a pin was configured with.
+pub enum TracePull {
+    Up,
+    Down,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Represents a single traced event.
+pub enum TraceEvent {
+    /// `pin_bcm` was driven high (`true`) or low.
+    Level { pin_bcm: u8, level: bool },
+    /// `pin_bcm` was configured.
+    Configure { pin_bcm: u8, direction: TraceDirection, pull: TracePull },
+    /// The PWM of `pin_bcm` was set.
+    Pwm { pin_bcm: u8, frequency_hz: f32, duty_cycle: f32 },
+    /// `data` was transmitted on the UART.
+    UartTx { data: Vec<u8> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Represents a [TraceEvent] along with the time since the simulation
+/// started.
+pub struct TraceRecord {
+    pub time: Duration,
+    pub event: TraceEvent,
+}
+
+#[derive(Debug)]
+/// Represents the errors of reading a trace.
+pub enum TraceError {
+    /// Thrown when the trace cannot be read.
+    IO(io::Error),
+    /// Thrown when the trace does not start with a known header.
+    Header,
+    /// Thrown when the trace was written in a version this reader does not
+    /// understand.
+    UnsupportedVersion(u16),
+    /// Thrown when record number `record` (starting at 1) is malformed.
+    Malformed { record: usize, reason: String },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::IO(err) => write!(f, "could not read the trace: {}", err),
+            TraceError::Header => write!(f, "not a cocos trace"),
+            TraceError::UnsupportedVersion(version) => {
+                write!(f, "unsupported trace version {}, expected {}", version, TRACE_VERSION)
+            }
+            TraceError::Malformed { record, reason } => {
+                write!(f, "malformed trace record {}: {}", record, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TraceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TraceError::IO(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> Self {
+        TraceError::IO(err)
+    }
+}
+
+impl TraceDirection {
+    fn letter(self) -> u8 {
+        match self {
+            TraceDirection::Input => b'I',
+            TraceDirection::Output => b'O',
+        }
+    }
+
+    fn from_letter(letter: u8) -> Option<Self> {
+        match letter {
+            b'I' => Some(TraceDirection::Input),
+            b'O' => Some(TraceDirection::Output),
+            _ => None,
+        }
+    }
+}
+
+impl TracePull {
+    fn letter(self) -> u8 {
+        match self {
+            TracePull::Up => b'U',
+            TracePull::Down => b'D',
+        }
+    }
+
+    fn from_letter(letter: u8) -> Option<Self> {
+        match letter {
+            b'U' => Some(TracePull::Up),
+            b'D' => Some(TracePull::Down),
+            _ => None,
+        }
+    }
+}
+
+impl TraceRecord {
+    pub fn new(time: Duration, event: TraceEvent) -> Self {
+        Self { time, event }
+    }
+
+    /// Encodes the record as a single CSV line, without the line break.
+    pub fn to_csv(&self) -> String {
+        let time = format!("{}.{:09}", self.time.as_secs(), self.time.subsec_nanos());
+        match &self.event {
+            TraceEvent::Level { pin_bcm, level } => {
+                format!("{},S,{},{}", time, pin_bcm, *level as u8)
+            }
+            TraceEvent::Configure { pin_bcm, direction, pull } => {
+                format!("{},C,{},{},{}", time, pin_bcm, direction.letter() as char,
+                        pull.letter() as char)
+            }
+            TraceEvent::Pwm { pin_bcm, frequency_hz, duty_cycle } => {
+                format!("{},P,{},{},{}", time, pin_bcm, frequency_hz, duty_cycle)
+            }
+            TraceEvent::UartTx { data } => {
+                let hex: String = data.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("{},T,{}", time, hex)
+            }
+        }
+    }
+
+    /// Decodes a single CSV line.
+    pub fn from_csv(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.trim_end().split(',').collect();
+        let expect_fields = |count: usize| {
+            if fields.len() == count {
+                Ok(())
+            } else {
+                Err(format!("expected {} fields, got {}", count, fields.len()))
+            }
+        };
+        let pin = |field: &str| field.parse::<u8>().map_err(|err| format!("pin {:?}: {}", field, err));
+        let letter = |field: &str| match field.as_bytes() {
+            [letter] => Ok(*letter),
+            _ => Err(format!("expected a single letter, got {:?}", field)),
+        };
+
+        let time = parse_csv_time(fields[0])?;
+        let event = match fields.get(1).copied() {
+            Some("S") => {
+                expect_fields(4)?;
+                let level = match fields[3] {
+                    "0" => false,
+                    "1" => true,
+                    other => return Err(format!("level {:?} is neither 0 nor 1", other)),
+                };
+                TraceEvent::Level { pin_bcm: pin(fields[2])?, level }
+            }
+            Some("C") => {
+                expect_fields(5)?;
+                TraceEvent::Configure {
+                    pin_bcm: pin(fields[2])?,
+                    direction: TraceDirection::from_letter(letter(fields[3])?)
+                        .ok_or_else(|| format!("unknown direction {:?}", fields[3]))?,
+                    pull: TracePull::from_letter(letter(fields[4])?)
+                        .ok_or_else(|| format!("unknown pull mode {:?}", fields[4]))?,
+                }
+            }
+            Some("P") => {
+                expect_fields(5)?;
+                TraceEvent::Pwm {
+                    pin_bcm: pin(fields[2])?,
+                    frequency_hz: fields[3].parse()
+                        .map_err(|err| format!("frequency {:?}: {}", fields[3], err))?,
+                    duty_cycle: fields[4].parse()
+                        .map_err(|err| format!("duty cycle {:?}: {}", fields[4], err))?,
+                }
+            }
+            Some("T") => {
+                expect_fields(3)?;
+                TraceEvent::UartTx { data: parse_hex(fields[2])? }
+            }
+            Some(kind) => return Err(format!("unknown record kind {:?}", kind)),
+            None => return Err(String::from("missing record kind")),
+        };
+        Ok(Self { time, event })
+    }
+
+    /// Appends the binary encoding of the record to `buf`.
+    pub fn encode_binary(&self, buf: &mut Vec<u8>) {
+        if let TraceEvent::UartTx { data } = &self.event {
+            if data.len() > u16::MAX as usize {
+                // Longer transmissions are split into several records.
+                for chunk in data.chunks(u16::MAX as usize) {
+                    TraceRecord::new(self.time, TraceEvent::UartTx { data: chunk.to_vec() })
+                        .encode_binary(buf);
+                }
+                return;
+            }
+        }
+
+        buf.extend_from_slice(&(self.time.as_nanos() as u64).to_le_bytes());
+        match &self.event {
+            TraceEvent::Level { pin_bcm, level } => {
+                buf.extend_from_slice(&[b'S', *pin_bcm, *level as u8]);
+            }
+            TraceEvent::Configure { pin_bcm, direction, pull } => {
+                buf.extend_from_slice(&[b'C', *pin_bcm, direction.letter(), pull.letter()]);
+            }
+            TraceEvent::Pwm { pin_bcm, frequency_hz, duty_cycle } => {
+                buf.extend_from_slice(&[b'P', *pin_bcm]);
+                buf.extend_from_slice(&frequency_hz.to_le_bytes());
+                buf.extend_from_slice(&duty_cycle.to_le_bytes());
+            }
+            TraceEvent::UartTx { data } => {
+                buf.push(b'T');
+                buf.extend_from_slice(&(data.len() as u16).to_le_bytes());
+                buf.extend_from_slice(data);
+            }
+        }
+    }
+}
+
+fn parse_csv_time(field: &str) -> Result<Duration, String> {
+    let (secs, nanos) = field.split_once('.').unwrap_or((field, "0"));
+    let secs: u64 = secs.parse().map_err(|err| format!("time {:?}: {}", field, err))?;
+    if nanos.is_empty() || nanos.len() > 9 || !nanos.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("time {:?} is not in seconds with up to 9 decimals", field));
+    }
+    let nanos: u32 = format!("{:0<9}", nanos).parse().unwrap();
+    Ok(Duration::new(secs, nanos))
+}
+
+fn parse_hex(field: &str) -> Result<Vec<u8>, String> {
+    if !field.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in {:?}", field));
+    }
+    (0..field.len())
+        .step_by(2)
+        .map(|i| {
+            field.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("invalid hex bytes {:?}", field))
+        })
+        .collect()
+}
+
+/// Writes [TraceRecord]s in either [TraceFormat], starting with the header.
+pub struct TraceWriter<W: Write> {
+    inner: W,
+    format: TraceFormat,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Creates a writer, writing the header to `inner` right away.
+    pub fn new(mut inner: W, format: TraceFormat) -> io::Result<Self> {
+        match format {
+            TraceFormat::Csv => writeln!(inner, "{},{}", CSV_HEADER, TRACE_VERSION)?,
+            TraceFormat::Binary => {
+                inner.write_all(BINARY_MAGIC)?;
+                inner.write_all(&TRACE_VERSION.to_le_bytes())?;
+            }
+        }
+        Ok(Self { inner, format, buf: Vec::new() })
+    }
+
+    pub fn format(&self) -> TraceFormat {
+        self.format
+    }
+
+    /// Writes `record` with a single write, so that records are never torn
+    /// apart by the underlying writer.
+    pub fn write(&mut self, record: &TraceRecord) -> io::Result<()> {
+        self.buf.clear();
+        match self.format {
+            TraceFormat::Csv => {
+                self.buf.extend_from_slice(record.to_csv().as_bytes());
+                self.buf.push(b'\n');
+            }
+            TraceFormat::Binary => record.encode_binary(&mut self.buf),
+        }
+        self.inner.write_all(&self.buf)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads the [TraceRecord]s of a trace in either [TraceFormat], detecting the
+/// format from the header.
+pub struct TraceReader<R: BufRead> {
+    inner: R,
+    format: TraceFormat,
+    records: usize,
+    done: bool,
+}
+
+impl<R: BufRead> TraceReader<R> {
+    /// Reads the header of the trace in `inner`.
+    pub fn new(mut inner: R) -> Result<Self, TraceError> {
+        let format = match inner.fill_buf()?.first() {
+            Some(b'#') => TraceFormat::Csv,
+            Some(_) => TraceFormat::Binary,
+            None => return Err(TraceError::Header),
+        };
+
+        let version = match format {
+            TraceFormat::Csv => {
+                let mut header = String::new();
+                inner.read_line(&mut header)?;
+                let version = header.trim_end()
+                    .strip_prefix(CSV_HEADER)
+                    .and_then(|rest| rest.strip_prefix(','))
+                    .ok_or(TraceError::Header)?;
+                version.parse().map_err(|_| TraceError::Header)?
+            }
+            TraceFormat::Binary => {
+                let mut header = [0u8; 6];
+                read_exact_or(&mut inner, &mut header, TraceError::Header)?;
+                if &header[..4] != BINARY_MAGIC {
+                    return Err(TraceError::Header);
+                }
+                u16::from_le_bytes([header[4], header[5]])
+            }
+        };
+        if version != TRACE_VERSION {
+            return Err(TraceError::UnsupportedVersion(version));
+        }
+
+        Ok(Self { inner, format, records: 0, done: false })
+    }
+
+    pub fn format(&self) -> TraceFormat {
+        self.format
+    }
+
+    fn malformed(&self, reason: String) -> TraceError {
+        TraceError::Malformed { record: self.records, reason }
+    }
+
+    fn read_csv(&mut self) -> Result<Option<TraceRecord>, TraceError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.inner.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        self.records += 1;
+        TraceRecord::from_csv(&line).map(Some).map_err(|reason| self.malformed(reason))
+    }
+
+    fn read_binary(&mut self) -> Result<Option<TraceRecord>, TraceError> {
+        if self.inner.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        self.records += 1;
+
+        let mut time = [0u8; 8];
+        self.read_field(&mut time)?;
+        let time = Duration::from_nanos(u64::from_le_bytes(time));
+        let mut kind = [0u8; 1];
+        self.read_field(&mut kind)?;
+
+        let event = match kind[0] {
+            b'S' => {
+                let mut fields = [0u8; 2];
+                self.read_field(&mut fields)?;
+                let level = match fields[1] {
+                    0 => false,
+                    1 => true,
+                    other => return Err(self.malformed(format!("level {} is neither 0 nor 1",
+                                                               other))),
+                };
+                TraceEvent::Level { pin_bcm: fields[0], level }
+            }
+            b'C' => {
+                let mut fields = [0u8; 3];
+                self.read_field(&mut fields)?;
+                let direction = TraceDirection::from_letter(fields[1])
+                    .ok_or_else(|| self.malformed(format!("unknown direction {}", fields[1])))?;
+                let pull = TracePull::from_letter(fields[2])
+                    .ok_or_else(|| self.malformed(format!("unknown pull mode {}", fields[2])))?;
+                TraceEvent::Configure { pin_bcm: fields[0], direction, pull }
+            }
+            b'P' => {
+                let mut fields = [0u8; 9];
+                self.read_field(&mut fields)?;
+                TraceEvent::Pwm {
+                    pin_bcm: fields[0],
+                    frequency_hz: f32::from_le_bytes(fields[1..5].try_into().unwrap()),
+                    duty_cycle: f32::from_le_bytes(fields[5..9].try_into().unwrap()),
+                }
+            }
+            b'T' => {
+                let mut len = [0u8; 2];
+                self.read_field(&mut len)?;
+                let mut data = vec![0u8; u16::from_le_bytes(len) as usize];
+                self.read_field(&mut data)?;
+                TraceEvent::UartTx { data }
+            }
+            other => return Err(self.malformed(format!("unknown record kind {}", other))),
+        };
+        Ok(Some(TraceRecord { time, event }))
+    }
+
+    /// Reads the next field of the current binary record.
+    fn read_field(&mut self, buf: &mut [u8]) -> Result<(), TraceError> {
+        let truncated = self.malformed(String::from("truncated record"));
+        read_exact_or(&mut self.inner, buf, truncated)
+    }
+}
+
+/// Fills `buf` from `inner`, returning `eof_error` if `inner` ends early.
+fn read_exact_or<R: Read>(inner: &mut R, buf: &mut [u8],
+                          eof_error: TraceError) -> Result<(), TraceError> {
+    match inner.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(eof_error),
+        Err(err) => Err(TraceError::IO(err)),
+    }
+}
+
+impl<R: BufRead> Iterator for TraceReader<R> {
+    type Item = Result<TraceRecord, TraceError>;
+
+    /// Returns the next record. Iteration ends after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = match self.format {
+            TraceFormat::Csv => self.read_csv(),
+            TraceFormat::Binary => self.read_binary(),
+        };
+        match record {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
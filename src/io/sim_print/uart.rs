@@ -1,16 +1,149 @@
-use super::super::interface::uart::{DrivesUart, UartError};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
 
-#[derive(Copy, Clone)]
-pub struct PrintUartDriver {}
+use super::super::interface::uart::{
+    DrivesUart, UartDescriptor, UartError, UartOperation, UartRingBuffer,
+    UART_RING_BUFFER_CAPACITY,
+};
+use super::{sink::TraceSink, trace::TraceEvent};
+
+/// The number of bytes the replay thread reads from its source at once.
+const REPLAY_CHUNK_LEN: usize = 64;
+
+/// This UART IO implementation simulates a UART device. Received bytes can be
+/// replayed from any byte source, most notably a file or a FIFO, while written
+/// bytes are emitted to the log and, if given, to a [TraceSink].
+///
+/// The replay happens on a separate thread which pushes bytes into a ring
+/// buffer at the rate given by the [UartDescriptor]'s baud rate, similarly to
+/// how a real device would deliver them. The replay thread never overwrites
+/// bytes that were not read yet, it waits for the reader instead.
+pub struct PrintUartDriver {
+    ring_buffer: Arc<Mutex<UartRingBuffer>>,
+    trace: Option<TraceSink>,
+}
 
 impl PrintUartDriver {
+    /// Creates a driver that never receives any bytes.
     pub fn new() -> PrintUartDriver {
-        PrintUartDriver {}
+        PrintUartDriver {
+            ring_buffer: Arc::new(Mutex::new(UartRingBuffer::new(UART_RING_BUFFER_CAPACITY))),
+            trace: None,
+        }
+    }
+
+    /// Returns a handle through which bytes are delivered to this driver, as
+    /// if they were received on the line.
+    pub fn feed(&self) -> SimUartFeed {
+        SimUartFeed { ring_buffer: Arc::clone(&self.ring_buffer) }
+    }
+
+    /// Traces the written bytes to `trace`.
+    pub fn traced(mut self, trace: TraceSink) -> PrintUartDriver {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Creates a driver that receives the bytes of the file or FIFO at
+    /// `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P,
+                                     descriptor: UartDescriptor) -> io::Result<PrintUartDriver> {
+        Ok(Self::replay(File::open(path)?, descriptor))
+    }
+
+    /// Creates a driver that receives the bytes read from `source`.
+    ///
+    /// Arguments:
+    /// * `source` - The byte source. It is read until EOF.
+    /// * `descriptor` - Describes the simulated line, used for pacing.
+    pub fn replay<R: Read + Send + 'static>(mut source: R,
+                                            descriptor: UartDescriptor) -> PrintUartDriver {
+        let driver = Self::new();
+        let ring_buffer = Arc::clone(&driver.ring_buffer);
+        let byte_time = Duration::from_secs_f64(
+            descriptor.bits_per_byte() as f64 / descriptor.baud_rate.max(1) as f64);
+
+        thread::spawn(move || {
+            let mut chunk = [0u8; REPLAY_CHUNK_LEN];
+            loop {
+                let count = match source.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(count) => count,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        log::error!(target: "system.io.uart", "Could not read replay source. {}", err);
+                        break;
+                    }
+                };
+
+                let mut pending = &chunk[..count];
+                while !pending.is_empty() {
+                    let pushed = {
+                        let mut ring = ring_buffer.lock().unwrap();
+                        let pushed = ring.free().min(pending.len());
+                        ring.push(&pending[..pushed]);
+                        pushed
+                    };
+                    pending = &pending[pushed..];
+                    thread::sleep(byte_time * pushed.max(1) as u32);
+                }
+            }
+            log::debug!(target: "system.io.uart", "Replay source exhausted.");
+        });
+
+        driver
+    }
+}
+
+impl Default for PrintUartDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+/// Delivers bytes to a [PrintUartDriver], shared with whatever simulates the
+/// device on the other end of the line. Like a real UART, the oldest unread
+/// bytes are overwritten once the receive buffer is full.
+pub struct SimUartFeed {
+    ring_buffer: Arc<Mutex<UartRingBuffer>>,
+}
+
+impl SimUartFeed {
+    /// Delivers `bytes` to the driver.
+    pub fn push(&self, bytes: &[u8]) {
+        self.ring_buffer.lock().unwrap().push(bytes);
     }
 }
 
 impl DrivesUart for PrintUartDriver {
-    fn read_bytes(&mut self, into: &mut [u8]) -> Result<Box<[u8]>, UartError> {
-        panic!("Not implemented") // TODO
+    fn read_bytes(&mut self, into: &mut [u8]) -> Result<usize, UartError> {
+        match self.ring_buffer.lock() {
+            Ok(mut ring) => Ok(ring.pop_into(into)),
+            Err(_) => Err(UartError::IO {
+                operation: UartOperation::Read,
+                cause: "the replay thread panicked".into(),
+            }),
+        }
+    }
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<usize, UartError> {
+        log::debug!(target: "system.io.uart", "PrintUartDriver TX {:02x?}", data);
+        if let Some(trace) = &self.trace {
+            trace
+                .record(TraceEvent::UartTx { data: data.to_vec() })
+                .map_err(|err| UartError::IO { operation: UartOperation::Write, cause: err.into() })?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> Result<(), UartError> {
+        Ok(())
     }
 }
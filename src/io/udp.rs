@@ -0,0 +1,53 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddrV4, UdpSocket},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::interface::net::{BroadcastsData, ListensForData, NetDescriptor, NetError};
+
+/// Defines a driver that broadcasts datagrams over UDP.
+///
+/// The socket is bound with `SO_REUSEADDR` so that multiple instances (for
+/// example several `cocos_simulated` processes broadcasting to
+/// `127.255.255.255`) can share a single port on one host. Note that every
+/// instance receives its own broadcasts as well.
+pub struct UdpBroadcastDriver {
+    socket: UdpSocket,
+    broadcast_addr: SocketAddrV4,
+}
+
+impl UdpBroadcastDriver {
+    pub fn new(descriptor: NetDescriptor) -> io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, descriptor.port).into())?;
+
+        Ok(Self {
+            socket: socket.into(),
+            broadcast_addr: SocketAddrV4::new(descriptor.broadcast_addr, descriptor.port),
+        })
+    }
+}
+
+impl BroadcastsData for UdpBroadcastDriver {
+    fn send_bytes(&mut self, data: &[u8]) -> Result<(), NetError> {
+        match self.socket.send_to(data, self.broadcast_addr) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(NetError::Send(err)),
+        }
+    }
+}
+
+impl ListensForData for UdpBroadcastDriver {
+    fn recv_bytes(&mut self, into: &mut [u8]) -> Result<Option<usize>, NetError> {
+        match self.socket.recv_from(into) {
+            Ok((count, _)) => Ok(Some(count)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(NetError::Receive(err)),
+        }
+    }
+}
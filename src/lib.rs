@@ -1,8 +1,7 @@
-#[macro_use]
-extern crate lazy_static;
-
+pub mod cli;
 pub mod config;
 pub mod controllers;
 pub mod drivers;
 pub mod io;
 pub mod models;
+pub mod sim;
@@ -1,3 +1,5 @@
+use crate::controllers::{network::NetMessage, task_stats::SharedTaskStats};
+
 use super::{led_color::LedColor, motor_power::MotorPower, position::Position};
 
 #[derive(Debug)]
@@ -5,6 +7,17 @@ use super::{led_color::LedColor, motor_power::MotorPower, position::Position};
 pub struct ApiTickInputMessage {
     /// The current bot position emitted to the API.
     pub bot_pos: Position,
+    /// The messages currently held in the network inbox, oldest first.
+    pub net_inbox: Vec<NetMessage>,
+    /// Whether the motors are currently braked, either on request or by the
+    /// command watchdog.
+    pub motors_locked: bool,
+    /// Whether the command watchdog tripped because no velocity command
+    /// arrived in time.
+    pub watchdog_tripped: bool,
+    /// The timing statistics of the periodic tasks, which are only locked to
+    /// answer a stats request.
+    pub task_stats: SharedTaskStats,
 }
 
 #[derive(Debug)]
@@ -12,20 +25,39 @@ pub struct ApiTickInputMessage {
 pub struct ApiTickOutputMessage {
     pub request_motor_power: Option<MotorPower>,
     pub request_led_color: Option<LedColor>,
+    /// The messages to broadcast to other coachbots, in order.
+    pub request_net_send: Vec<Vec<u8>>,
+    /// Requests that all inbox messages up to and including the given id are
+    /// removed.
+    pub request_net_ack: Option<u64>,
 }
 
 impl ApiTickOutputMessage {
     pub fn motor(pow: MotorPower) -> Self {
         Self {
-            request_led_color: None,
             request_motor_power: Some(pow),
+            ..Self::none()
         }
     }
 
     pub fn led(color: LedColor) -> Self {
         Self {
             request_led_color: Some(color),
-            request_motor_power: None,
+            ..Self::none()
+        }
+    }
+
+    pub fn net_send(message: Vec<u8>) -> Self {
+        Self {
+            request_net_send: vec![message],
+            ..Self::none()
+        }
+    }
+
+    pub fn net_ack(id: u64) -> Self {
+        Self {
+            request_net_ack: Some(id),
+            ..Self::none()
         }
     }
 
@@ -33,6 +65,21 @@ impl ApiTickOutputMessage {
         Self {
             request_led_color: None,
             request_motor_power: None,
+            request_net_send: vec![],
+            request_net_ack: None,
+        }
+    }
+
+    /// Merges a later output into this one. Requested values of `later`
+    /// override the ones of `self`, while messages to send are appended.
+    pub fn merge(&mut self, later: ApiTickOutputMessage) {
+        if later.request_motor_power.is_some() {
+            self.request_motor_power = later.request_motor_power;
+        }
+        if later.request_led_color.is_some() {
+            self.request_led_color = later.request_led_color;
         }
+        self.request_net_send.extend(later.request_net_send);
+        self.request_net_ack = self.request_net_ack.max(later.request_net_ack);
     }
 }
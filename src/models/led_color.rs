@@ -10,7 +10,7 @@ pub struct LedColor {
 
 impl LedColor {
     pub fn new(r: f32, g: f32, b: f32) -> Result<Self, &'static str> {
-        if !(0.0 <= r && r <= 1.0 && 0.0 <= g && g <= 1.0 && 0.0 <= b && b <= 1.0) {
+        if ![r, g, b].iter().all(|channel| (0.0..=1.0).contains(channel)) {
             return Err("Attmepting to construct LED color with invalid args.");
         }
 
@@ -33,11 +33,20 @@ impl MotorPower {
         }
     }
 
+    /// Creates a zeroed-out, locked MotorPower which halts both motors.
+    pub fn locked() -> Self {
+        Self {
+            left: 0.0,
+            right: 0.0,
+            locked: true,
+        }
+    }
+
     /// Creates a new MotorPower object.
     ///
     /// If the passed parameters are invalid, this returns a None
     pub fn new(left: f32, right: f32, locked: bool) -> Option<MotorPower> {
-        if !(-1f32 <= left && left <= 1f32 && -1f32 <= right && right <= 1f32) {
+        if !((-1f32..=1f32).contains(&left) && (-1f32..=1f32).contains(&right)) {
             return None;
         }
 
@@ -64,7 +73,17 @@ impl MotorPower {
             return MotorPowerQuadrant::NLeftPRight;
         }
 
-        return MotorPowerQuadrant::NLeftNRight;
+        MotorPowerQuadrant::NLeftNRight
+    }
+
+    /// Returns the signed motor power of the left motor.
+    pub fn left(&self) -> f32 {
+        self.left
+    }
+
+    /// Returns the signed motor power of the right motor.
+    pub fn right(&self) -> f32 {
+        self.right
     }
 
     /// Returns the absolute motor power of the left motor.
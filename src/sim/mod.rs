@@ -0,0 +1,4 @@
+pub mod network;
+pub mod physics;
+pub mod robot;
+pub mod trajectory;
@@ -0,0 +1,61 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use crate::io::interface::net::{BroadcastsData, ListensForData, NetError};
+
+/// The number of datagrams a driver holds before dropping new ones.
+const SIM_NET_QUEUE_CAPACITY: usize = 256;
+
+#[derive(Clone, Default)]
+/// A broadcast network connecting simulated coachbots within one process.
+///
+/// Every datagram sent by a [SimNetDriver] is delivered to all other drivers
+/// attached to the same network. Like a socket buffer, each driver holds a
+/// bounded queue of received datagrams and drops new ones once it is full.
+pub struct SimNetwork {
+    queues: Arc<Mutex<Vec<VecDeque<Vec<u8>>>>>,
+}
+
+impl SimNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches a new coachbot to the network, returning its driver.
+    pub fn attach(&self) -> SimNetDriver {
+        let mut queues = self.queues.lock().unwrap();
+        queues.push(VecDeque::new());
+        SimNetDriver { network: self.clone(), index: queues.len() - 1 }
+    }
+}
+
+/// The network driver of a coachbot attached to a [SimNetwork].
+pub struct SimNetDriver {
+    network: SimNetwork,
+    index: usize,
+}
+
+impl BroadcastsData for SimNetDriver {
+    fn send_bytes(&mut self, data: &[u8]) -> Result<(), NetError> {
+        let mut queues = self.network.queues.lock().unwrap();
+        for (index, queue) in queues.iter_mut().enumerate() {
+            if index != self.index && queue.len() < SIM_NET_QUEUE_CAPACITY {
+                queue.push_back(data.to_vec());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ListensForData for SimNetDriver {
+    fn recv_bytes(&mut self, into: &mut [u8]) -> Result<Option<usize>, NetError> {
+        let mut queues = self.network.queues.lock().unwrap();
+        Ok(queues[self.index].pop_front().map(|datagram| {
+            let count = datagram.len().min(into.len());
+            into[..count].copy_from_slice(&datagram[..count]);
+            count
+        }))
+    }
+}
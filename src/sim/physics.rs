@@ -0,0 +1,220 @@
+use std::{f32::consts::PI, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use uom::si::{angle::radian, f32::{Angle, Length}, length::meter};
+
+use crate::models::position::Position;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Describes the rectangle the robots are confined to, in meters.
+pub struct ArenaDescriptor {
+    pub min_x_m: f32,
+    pub max_x_m: f32,
+    pub min_y_m: f32,
+    pub max_y_m: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Describes a pose in the arena.
+pub struct PoseDescriptor {
+    pub x_m: f32,
+    pub y_m: f32,
+    /// The heading in radians, CCW from the X axis.
+    pub theta_rad: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Describes the kinematic simulation `cocos_simulated` moves the robot in.
+pub struct PhysicsDescriptor {
+    /// The distance between the two wheels.
+    pub wheel_base_m: f32,
+    /// The speed of a wheel driven at a duty cycle of 1.
+    pub max_speed_mps: f32,
+    /// The standard deviation of the wheel speeds, relative to the speed.
+    pub speed_noise: f32,
+    /// The standard deviation of the reported position.
+    pub position_noise_m: f32,
+    pub arena: ArenaDescriptor,
+    /// How often the position is reported over the Nucifera UART, at most
+    /// [MAX_FRAME_RATE_HZ](super::robot::MAX_FRAME_RATE_HZ).
+    pub frame_rate_hz: f32,
+    /// Seeds the noise, making runs reproducible. A random seed is used if
+    /// not given.
+    pub seed: Option<u64>,
+    /// The pose the robot starts at.
+    pub start: PoseDescriptor,
+}
+
+impl Default for PhysicsDescriptor {
+    fn default() -> Self {
+        Self {
+            wheel_base_m: 0.08,
+            max_speed_mps: 0.2,
+            speed_noise: 0.02,
+            position_noise_m: 0.0,
+            arena: ArenaDescriptor { min_x_m: -1.5, max_x_m: 1.5, min_y_m: -1.5, max_y_m: 1.5 },
+            frame_rate_hz: 30.0,
+            seed: None,
+            start: PoseDescriptor { x_m: 0.0, y_m: 0.0, theta_rad: 0.0 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The pose of a simulated robot. Lengths are in meters, angles in radians
+/// CCW from the X axis and normalized to (-π, π].
+pub struct Pose {
+    pub x: f32,
+    pub y: f32,
+    pub theta: f32,
+}
+
+impl Pose {
+    pub fn to_position(&self) -> Position {
+        Position {
+            x: Length::new::<meter>(self.x),
+            y: Length::new::<meter>(self.y),
+            theta: Angle::new::<radian>(self.theta),
+        }
+    }
+}
+
+impl From<PoseDescriptor> for Pose {
+    fn from(descriptor: PoseDescriptor) -> Self {
+        Self { x: descriptor.x_m, y: descriptor.y_m, theta: normalize(descriptor.theta_rad) }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// The signed ground speeds of the wheels of a robot in meters per second,
+/// positive moving it forward.
+pub struct WheelSpeeds {
+    pub left: f32,
+    pub right: f32,
+}
+
+/// Arranges `count` poses on a square grid centered at `center`, `spacing_m`
+/// apart, all facing the heading of `center`. The grid is filled row by row.
+pub fn grid_poses(center: Pose, count: usize, spacing_m: f32) -> Vec<Pose> {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = count.div_ceil(columns);
+    let offset = |index: usize, len: usize| (index as f32 - (len - 1) as f32 / 2.0) * spacing_m;
+    (0..count)
+        .map(|index| Pose {
+            x: center.x + offset(index % columns, columns),
+            y: center.y + offset(index / columns, rows),
+            theta: center.theta,
+        })
+        .collect()
+}
+
+/// Normalizes `theta` to (-π, π].
+fn normalize(theta: f32) -> f32 {
+    let theta = theta.rem_euclid(2.0 * PI);
+    if theta > PI { theta - 2.0 * PI } else { theta }
+}
+
+/// A kinematic 2D world of differential-drive robots.
+///
+/// Robots are moved by integrating their wheel speeds exactly along the arc
+/// they describe, so that the accuracy does not depend on the step size.
+/// Robots do not collide with each other, but are stopped at the walls of
+/// the arena.
+pub struct World {
+    descriptor: PhysicsDescriptor,
+    poses: Vec<Pose>,
+    rng: fastrand::Rng,
+}
+
+impl World {
+    pub fn new(descriptor: PhysicsDescriptor) -> Self {
+        let rng = match descriptor.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        };
+        Self { descriptor, poses: Vec::new(), rng }
+    }
+
+    pub fn descriptor(&self) -> &PhysicsDescriptor {
+        &self.descriptor
+    }
+
+    /// Places a new robot at `pose` and returns its index.
+    pub fn add_robot(&mut self, pose: Pose) -> usize {
+        self.poses.push(self.clamp(pose));
+        self.poses.len() - 1
+    }
+
+    /// Returns the true pose of the robot at `index`.
+    pub fn pose(&self, index: usize) -> Pose {
+        self.poses[index]
+    }
+
+    /// Returns the pose of the robot at `index` as a tracker would measure
+    /// it, ie. with position noise applied.
+    pub fn measure(&mut self, index: usize) -> Pose {
+        let pose = self.poses[index];
+        let noise = self.descriptor.position_noise_m;
+        Pose {
+            x: pose.x + noise * self.gaussian(),
+            y: pose.y + noise * self.gaussian(),
+            theta: pose.theta,
+        }
+    }
+
+    /// Advances the world by `dt`, driving each robot with the wheel speeds
+    /// of the same index. Robots without wheel speeds stand still.
+    pub fn step(&mut self, dt: Duration, speeds: &[WheelSpeeds]) {
+        let dt = dt.as_secs_f32();
+        for (index, speed) in speeds.iter().enumerate().take(self.poses.len()) {
+            let left = self.noisy(speed.left);
+            let right = self.noisy(speed.right);
+            let pose = self.poses[index];
+
+            let v = (left + right) / 2.0;
+            let omega = (right - left) / self.descriptor.wheel_base_m;
+            let theta = pose.theta + omega * dt;
+            let (dx, dy) = if omega.abs() < 1e-6 {
+                (v * dt * pose.theta.cos(), v * dt * pose.theta.sin())
+            } else {
+                let radius = v / omega;
+                (radius * (theta.sin() - pose.theta.sin()),
+                 radius * (pose.theta.cos() - theta.cos()))
+            };
+
+            self.poses[index] = self.clamp(Pose {
+                x: pose.x + dx,
+                y: pose.y + dy,
+                theta: normalize(theta),
+            });
+        }
+    }
+
+    /// Applies the speed noise to `speed`.
+    fn noisy(&mut self, speed: f32) -> f32 {
+        if speed == 0.0 {
+            // A wheel that is not driven does not creep.
+            return speed;
+        }
+        speed * (1.0 + self.descriptor.speed_noise * self.gaussian())
+    }
+
+    /// Keeps `pose` within the arena.
+    fn clamp(&self, pose: Pose) -> Pose {
+        let arena = &self.descriptor.arena;
+        Pose {
+            x: pose.x.clamp(arena.min_x_m, arena.max_x_m),
+            y: pose.y.clamp(arena.min_y_m, arena.max_y_m),
+            theta: pose.theta,
+        }
+    }
+
+    /// Draws from an approximately standard normal distribution, as the sum
+    /// of 12 uniform samples (Irwin-Hall).
+    fn gaussian(&mut self) -> f32 {
+        (0..12).map(|_| self.rng.f32()).sum::<f32>() - 6.0
+    }
+}
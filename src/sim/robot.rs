@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use crate::{
+    controllers::interface::HandlesTick10Ms,
+    drivers::{motor_driver::MotorDescriptor, nucifera_driver::NuciferaMessage},
+    io::sim_print::{gpio::SimGpioInputs, pwm::SimPwmOutputs, uart::SimUartFeed},
+};
+
+use super::{
+    physics::{Pose, WheelSpeeds, World},
+    trajectory::TrajectoryLog,
+};
+
+/// The period the [PhysicsTask] advances the world by on every tick.
+const STEP: Duration = Duration::from_millis(10);
+
+/// The highest Nucifera frame rate, at which a frame is sent on every step.
+pub const MAX_FRAME_RATE_HZ: f32 = 100.0;
+
+/// Decodes the ground speed of the wheel driven by `motor` from the levels
+/// of its pins and the duty cycle of its PWM, as the motor driver chip
+/// would. The wheel stands still while the driver is in standby or while
+/// both direction pins are at the same level, which brakes the motor.
+pub fn wheel_speed(motor: &MotorDescriptor, gpio: &SimGpioInputs, pwm: &SimPwmOutputs,
+                   max_speed_mps: f32) -> f32 {
+    let in1 = gpio.level(motor.pin_in1);
+    if !gpio.level(motor.pin_stdby) || in1 == gpio.level(motor.pin_in2) {
+        return 0.0;
+    }
+
+    // The motor spins clockwise while in1 is high, which moves a
+    // non-inverted motor forward.
+    let forward = in1 != motor.inverted;
+    let speed = pwm.duty_cycle(motor.pin_pwm).clamp(0.0, 1.0) * max_speed_mps;
+    if forward { speed } else { -speed }
+}
+
+/// The simulated outside world of a single robot: the outputs it drives its
+/// motors with and the UART it receives its position on.
+pub struct SimRobot {
+    robot_id: u32,
+    mot_left: MotorDescriptor,
+    mot_right: MotorDescriptor,
+    gpio: SimGpioInputs,
+    pwm: SimPwmOutputs,
+    uart: SimUartFeed,
+    sequence: u8,
+}
+
+impl SimRobot {
+    pub fn new(robot_id: u32, mot_left: MotorDescriptor, mot_right: MotorDescriptor,
+               gpio: SimGpioInputs, pwm: SimPwmOutputs, uart: SimUartFeed) -> Self {
+        Self { robot_id, mot_left, mot_right, gpio, pwm, uart, sequence: 0 }
+    }
+
+    pub fn robot_id(&self) -> u32 {
+        self.robot_id
+    }
+
+    /// Returns the speeds the robot currently drives its wheels at.
+    pub fn wheel_speeds(&self, max_speed_mps: f32) -> WheelSpeeds {
+        WheelSpeeds {
+            left: wheel_speed(&self.mot_left, &self.gpio, &self.pwm, max_speed_mps),
+            right: wheel_speed(&self.mot_right, &self.gpio, &self.pwm, max_speed_mps),
+        }
+    }
+
+    /// Sends `pose` to the robot as a Nucifera frame.
+    pub fn report(&mut self, pose: Pose) {
+        let message = NuciferaMessage {
+            sequence: self.sequence,
+            x: pose.x,
+            y: pose.y,
+            theta: pose.theta,
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        self.uart.push(&message.encode());
+    }
+}
+
+/// Moves the robots of a [World] by their motor outputs and reports their
+/// positions back to them at the configured frame rate. The robot at index
+/// `i` drives the body at index `i` of the world.
+pub struct PhysicsTask {
+    world: World,
+    robots: Vec<SimRobot>,
+    /// The simulated time since the task started.
+    elapsed: Duration,
+    since_frame: Duration,
+    trajectory: Option<TrajectoryLog>,
+}
+
+impl PhysicsTask {
+    pub fn new(world: World, robots: Vec<SimRobot>) -> Self {
+        Self {
+            world,
+            robots,
+            elapsed: Duration::ZERO,
+            since_frame: Duration::ZERO,
+            trajectory: None,
+        }
+    }
+
+    /// Logs the true poses of all robots to `trajectory` on every frame.
+    pub fn logged(mut self, trajectory: TrajectoryLog) -> Self {
+        self.trajectory = Some(trajectory);
+        self
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Logs the current poses of all robots, giving up on the first error.
+    fn log_trajectory(&mut self) {
+        let trajectory = match &mut self.trajectory {
+            Some(trajectory) => trajectory,
+            None => return,
+        };
+        for (index, robot) in self.robots.iter().enumerate() {
+            let pose = self.world.pose(index);
+            if let Err(err) = trajectory.write(self.elapsed, robot.robot_id(), pose) {
+                log::error!(target: "system.sim", "Stopped logging the trajectory. {}", err);
+                self.trajectory = None;
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for PhysicsTask {
+    fn drop(&mut self) {
+        if let Some(trajectory) = &mut self.trajectory {
+            if let Err(err) = trajectory.flush() {
+                log::error!(target: "system.sim", "Could not write the trajectory. {}", err);
+            }
+        }
+    }
+}
+
+impl HandlesTick10Ms for PhysicsTask {
+    fn on_tick10(&mut self) {
+        let max_speed_mps = self.world.descriptor().max_speed_mps;
+        let speeds: Vec<WheelSpeeds> = self.robots
+            .iter()
+            .map(|robot| robot.wheel_speeds(max_speed_mps))
+            .collect();
+        self.world.step(STEP, &speeds);
+
+        self.elapsed += STEP;
+        self.since_frame += STEP;
+        let frame_period = Duration::from_secs_f32(1.0 / self.world.descriptor().frame_rate_hz);
+        if self.since_frame < frame_period {
+            return;
+        }
+        self.since_frame -= frame_period;
+        self.log_trajectory();
+        for (index, robot) in self.robots.iter_mut().enumerate() {
+            robot.report(self.world.measure(index));
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use super::physics::Pose;
+
+/// The header line of a trajectory log.
+pub const TRAJECTORY_HEADER: &str = "time_s,robot_id,x_m,y_m,theta_rad";
+
+/// Logs the true poses of all robots of a simulation as CSV, one line per
+/// robot and frame:
+///
+/// ```text
+/// time_s,robot_id,x_m,y_m,theta_rad
+/// 0.040,0,0.000000,0.000000,0.000000
+/// 0.040,1,0.250000,0.000000,0.000000
+/// ```
+///
+/// Times are simulated time since the start of the simulation.
+pub struct TrajectoryLog {
+    writer: Box<dyn Write + Send>,
+}
+
+impl TrajectoryLog {
+    /// Creates (or truncates) the file at `path` and writes the log to it.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<TrajectoryLog> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Creates a log writing to `inner`.
+    pub fn new<W: Write + Send + 'static>(inner: W) -> io::Result<TrajectoryLog> {
+        let mut writer = Box::new(inner) as Box<dyn Write + Send>;
+        writeln!(writer, "{}", TRAJECTORY_HEADER)?;
+        Ok(TrajectoryLog { writer })
+    }
+
+    /// Logs `pose` as the pose of `robot_id` at `time`.
+    pub fn write(&mut self, time: Duration, robot_id: u32, pose: Pose) -> io::Result<()> {
+        writeln!(self.writer, "{:.3},{},{:.6},{:.6},{:.6}",
+                 time.as_secs_f64(), robot_id, pose.x, pose.y, pose.theta)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
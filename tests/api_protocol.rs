@@ -0,0 +1,173 @@
+use cocos::controllers::api::errors::ApiError;
+use cocos::controllers::api::ipc_requests::{
+    ApiIpcBodyError, ApiIpcHandshakeRequestBody, ApiIpcRequest, ApiIpcRequestType,
+    ApiIpcVelRequestBody, ValidatesApiIpcBody,
+};
+use cocos::controllers::api::ipc_responses::{ApiIpcPosResponseBody, ApiResponse, ApiStatus};
+use cocos::controllers::api::protocol::{
+    accepts_request, supports_version, ApiEncoding, API_LEGACY_PROTOCOL_VERSION,
+    API_PROTOCOL_VERSION,
+};
+use serde_json::{json, Value};
+
+fn request(message: &str) -> ApiIpcRequest<'_> {
+    ApiIpcRequest::decode(message.as_bytes(), ApiEncoding::Json).unwrap()
+}
+
+#[test]
+fn string_bodies_are_accepted_in_every_version() {
+    let request = request(r#"{"request_type": 1, "body": "{\"l\": 20, \"r\": -20}"}"#);
+    for version in [API_LEGACY_PROTOCOL_VERSION, API_PROTOCOL_VERSION] {
+        let body: ApiIpcVelRequestBody = request.decode_body(version).unwrap();
+        assert_eq!((body.l, body.r), (20, -20));
+    }
+}
+
+#[test]
+fn object_bodies_require_the_new_version() {
+    let request = request(r#"{"request_type": 1, "body": {"l": 20, "r": -20}}"#);
+    let body: ApiIpcVelRequestBody = request.decode_body(API_PROTOCOL_VERSION).unwrap();
+    assert_eq!((body.l, body.r), (20, -20));
+
+    let err = request.decode_body::<ApiIpcVelRequestBody>(API_LEGACY_PROTOCOL_VERSION)
+        .unwrap_err();
+    assert!(matches!(err, ApiIpcBodyError::ObjectBody { version: API_LEGACY_PROTOCOL_VERSION }));
+    assert!(err.to_string().contains("handshake"), "{}", err);
+}
+
+#[test]
+fn the_handshake_declares_the_version() {
+    let object = request(r#"{"request_type": 8, "body": {"version": 2}}"#);
+    assert_eq!(object.request_type, ApiIpcRequestType::Handshake);
+    let body: ApiIpcHandshakeRequestBody = object.decode_body(API_PROTOCOL_VERSION).unwrap();
+    assert_eq!(body.version, 2);
+    assert!(body.encodings.is_empty());
+
+    let legacy = request(
+        r#"{"request_type": 8, "body": "{\"version\": 2, \"encodings\": [\"msgpack\"]}"}"#);
+    let body: ApiIpcHandshakeRequestBody = legacy.decode_body(API_PROTOCOL_VERSION).unwrap();
+    assert_eq!(body.encodings, ["msgpack"]);
+}
+
+#[test]
+fn unknown_versions_are_incompatible() {
+    assert!(supports_version(API_LEGACY_PROTOCOL_VERSION));
+    assert!(supports_version(API_PROTOCOL_VERSION));
+    assert!(!supports_version(0));
+    assert!(!supports_version(API_PROTOCOL_VERSION + 1));
+
+    let message = ApiError::IncompatibleVersion(3).to_string();
+    assert_eq!(message, "the API speaks protocol version 3, but cocos only speaks versions 1 to 2");
+}
+
+#[test]
+fn responses_nest_their_body_in_a_string_for_legacy_apis() {
+    let response = || ApiResponse::new(ApiStatus::Success,
+                                       ApiIpcPosResponseBody { x: 0.5, y: -1.0, theta: 0.0 });
+
+    let legacy: Value =
+        serde_json::from_slice(&response().encode(API_LEGACY_PROTOCOL_VERSION, ApiEncoding::Json))
+            .unwrap();
+    let body: Value = serde_json::from_str(legacy["body"].as_str().unwrap()).unwrap();
+    assert_eq!(legacy["status"], 0);
+    assert_eq!(body, json!({"x": 0.5, "y": -1.0, "theta": 0.0}));
+
+    let current: Value =
+        serde_json::from_slice(&response().encode(API_PROTOCOL_VERSION, ApiEncoding::Json))
+            .unwrap();
+    assert_eq!(current, json!({"status": 0, "body": {"x": 0.5, "y": -1.0, "theta": 0.0}}));
+}
+
+#[test]
+fn every_request_type_is_advertised_by_its_code() {
+    let codes = serde_json::to_value(ApiIpcRequestType::ALL).unwrap();
+    assert_eq!(codes, json!([0, 1, 2, 3, 4, 5, 6, 7, 8]));
+}
+
+#[test]
+fn the_first_known_encoding_offered_is_picked() {
+    let offered = |names: &[&str]| -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    };
+    assert_eq!(ApiEncoding::negotiate(&offered(&["cbor", "msgpack", "json"])),
+               ApiEncoding::MessagePack);
+    assert_eq!(ApiEncoding::negotiate(&offered(&["json", "msgpack"])), ApiEncoding::Json);
+    assert_eq!(ApiEncoding::negotiate(&offered(&["cbor"])), ApiEncoding::Json);
+    assert_eq!(ApiEncoding::negotiate(&[]), ApiEncoding::Json);
+}
+
+#[test]
+fn msgpack_requests_are_validated_like_json_ones() {
+    let encode = |l: i32| {
+        rmp_serde::to_vec_named(&json!({"request_type": 1, "body": {"l": l, "r": -20}})).unwrap()
+    };
+
+    let message = encode(20);
+    let request = ApiIpcRequest::decode(&message, ApiEncoding::MessagePack).unwrap();
+    assert_eq!(request.request_type, ApiIpcRequestType::Vel);
+    let body: ApiIpcVelRequestBody = request.decode_body(API_PROTOCOL_VERSION).unwrap();
+    assert_eq!((body.l, body.r), (20, -20));
+    assert!(body.validate());
+
+    let message = encode(120);
+    let request = ApiIpcRequest::decode(&message, ApiEncoding::MessagePack).unwrap();
+    let body: ApiIpcVelRequestBody = request.decode_body(API_PROTOCOL_VERSION).unwrap();
+    assert!(!body.validate());
+}
+
+#[test]
+fn undecodable_requests_are_rejected_per_encoding() {
+    let message = br#"{"request_type": 2, "body": {}}"#;
+    assert!(matches!(ApiIpcRequest::decode(b"\xff{}", ApiEncoding::Json),
+                     Err(ApiError::DecodeError)));
+    assert!(matches!(ApiIpcRequest::decode(message, ApiEncoding::MessagePack),
+                     Err(ApiError::InvalidRequestHead)));
+    assert!(matches!(ApiIpcRequest::decode(&[0x92, 0x02], ApiEncoding::Json),
+                     Err(ApiError::DecodeError)));
+}
+
+#[test]
+fn msgpack_responses_hold_the_same_fields() {
+    let response = ApiResponse::new(ApiStatus::Success,
+                                    ApiIpcPosResponseBody { x: 0.5, y: -1.0, theta: 0.0 });
+    let encoded = response.encode(API_PROTOCOL_VERSION, ApiEncoding::MessagePack);
+    let decoded: Value = rmp_serde::from_slice(&encoded).unwrap();
+    assert_eq!(decoded, json!({"status": 0, "body": {"x": 0.5, "y": -1.0, "theta": 0.0}}));
+}
+
+#[test]
+fn the_encoding_of_a_request_is_recognized() {
+    let json = br#" {"request_type": 2, "body": {}}"#;
+    let msgpack = rmp_serde::to_vec_named(&json!({"request_type": 2, "body": {}})).unwrap();
+    assert_eq!(ApiEncoding::detect(json), Some(ApiEncoding::Json));
+    assert_eq!(ApiEncoding::detect(&msgpack), Some(ApiEncoding::MessagePack));
+    assert_eq!(ApiEncoding::detect(&[0xde, 0x00, 0x10]), Some(ApiEncoding::MessagePack));
+    assert_eq!(ApiEncoding::detect(&[0x92, 0x02]), None);
+    assert_eq!(ApiEncoding::detect(b""), None);
+}
+
+#[test]
+fn a_handshake_repeated_after_a_lost_response_is_accepted() {
+    // Cocos picked MessagePack, but the response never reached the API, which
+    // still speaks JSON and repeats its handshake.
+    let handshake = br#"{"request_type": 8, "body": {"version": 2, "encodings": ["msgpack"]}}"#;
+    let encoding = ApiEncoding::detect(handshake).unwrap();
+    let request = ApiIpcRequest::decode(handshake, encoding).unwrap();
+    assert_eq!(encoding, ApiEncoding::Json);
+    assert!(accepts_request(ApiEncoding::MessagePack, encoding, request.request_type));
+
+    let body: ApiIpcHandshakeRequestBody = request.decode_body(API_PROTOCOL_VERSION).unwrap();
+    assert_eq!(ApiEncoding::negotiate(&body.encodings), ApiEncoding::MessagePack);
+
+    // Any other request must wait for the switch.
+    let vel = br#"{"request_type": 1, "body": {"l": 20, "r": -20}}"#;
+    let request = ApiIpcRequest::decode(vel, ApiEncoding::detect(vel).unwrap()).unwrap();
+    assert!(!accepts_request(ApiEncoding::MessagePack, ApiEncoding::Json,
+                             request.request_type));
+    assert!(accepts_request(ApiEncoding::MessagePack, ApiEncoding::MessagePack,
+                            request.request_type));
+
+    let err = ApiError::UnexpectedEncoding { received: ApiEncoding::Json,
+                                             negotiated: ApiEncoding::MessagePack };
+    assert_eq!(err.to_string(), "received a request in Json, but MessagePack was negotiated");
+}
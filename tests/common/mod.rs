@@ -0,0 +1,34 @@
+//! Helpers shared by the integration tests.
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// A writer whose output stays readable after it was handed to a sink or a
+/// log.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Returns everything written so far.
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write byte by byte so that torn records would show.
+        match buf.first() {
+            Some(&byte) => {
+                self.0.lock().unwrap().push(byte);
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use cocos::config::{AppConfig, ConfigError};
+
+fn with_endpoint(endpoint: &str) -> Result<AppConfig, ConfigError> {
+    AppConfig::from_toml_str(&format!("[api]\nendpoint = \"{}\"\n", endpoint))
+}
+
+#[test]
+fn the_default_endpoint_is_the_legacy_ipc_socket() {
+    assert_eq!(AppConfig::default().api.endpoint, "ipc:///tmp/cocos-api");
+}
+
+#[test]
+fn ipc_and_tcp_endpoints_are_accepted() {
+    for endpoint in ["ipc:///run/cocos/api-3", "tcp://127.0.0.1:5555", "tcp://localhost:5555"] {
+        assert_eq!(with_endpoint(endpoint).unwrap().api.endpoint, endpoint);
+    }
+}
+
+#[test]
+fn malformed_endpoints_are_rejected() {
+    let long_path = format!("ipc:///tmp/{}", "a".repeat(120));
+    for endpoint in ["/tmp/cocos-api", "ipc://", long_path.as_str(), "tcp://127.0.0.1",
+                     "tcp://*:5555", "tcp://127.0.0.1:0", "tcp://127.0.0.1:http",
+                     "udp://127.0.0.1:5555"] {
+        match with_endpoint(endpoint) {
+            Err(ConfigError::OutOfRange { key: "api.endpoint", .. }) => {}
+            _ => panic!("{} was not rejected", endpoint),
+        }
+    }
+}
+
+#[test]
+fn the_robot_id_is_configurable() {
+    assert_eq!(AppConfig::from_toml_str("robot_id = 12\n").unwrap().robot_id, 12);
+}
+
+#[test]
+fn partial_sections_keep_the_default_values() {
+    let config = AppConfig::from_toml_str("[mot_left]\npin_pwm = 18\n").unwrap();
+    assert_eq!(config.mot_left.pin_pwm, 18);
+    assert_eq!(config.mot_left.pin_in1, AppConfig::default().mot_left.pin_in1);
+    assert_eq!(config.mot_right.pin_pwm, AppConfig::default().mot_right.pin_pwm);
+
+    let config = AppConfig::from_json_str(r#"{"led": {"frequency_hz": 240}}"#).unwrap();
+    assert_eq!(config.led.pin_r_bcm, AppConfig::default().led.pin_r_bcm);
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    match AppConfig::from_toml_str("[mot_left]\npin_pmw = 18\n") {
+        Err(ConfigError::Parse(message)) => assert!(message.contains("pin_pmw"), "{}", message),
+        _ => panic!("the misspelled key was not rejected"),
+    }
+}
+
+#[test]
+fn pins_cannot_be_shared_but_for_the_motor_standby() {
+    match AppConfig::from_toml_str("[led]\npin_g_bcm = 12\n") {
+        Err(ConfigError::PinConflict { pin_bcm: 12, first: "mot_right.pin_pwm",
+                                       second: "led.pin_g_bcm" }) => {}
+        _ => panic!("the shared pin was not rejected"),
+    }
+
+    let contents = "[mot_left]\npin_stdby = 21\n[mot_right]\npin_stdby = 21\n";
+    let config = AppConfig::from_toml_str(contents).unwrap();
+    assert_eq!(config.mot_left.pin_stdby, config.mot_right.pin_stdby);
+}
+
+#[test]
+fn values_out_of_range_are_rejected() {
+    for (contents, rejected_key) in [
+        ("[led]\npin_b_bcm = 28\n", "led.pin_b_bcm"),
+        ("[mot_left]\npwm_frequency_hz = 0\n", "mot_left.pwm_frequency_hz"),
+        ("[nucifera]\nbaud_rate = 0\n", "nucifera.baud_rate"),
+        ("[nucifera]\ndata_bits = 9\n", "nucifera.data_bits"),
+        ("[nucifera]\nstop_bits = 3\n", "nucifera.stop_bits"),
+        ("[nucifera]\nstale_timeout_ms = 0\n", "nucifera.stale_timeout_ms"),
+        ("[sim]\nframe_rate_hz = 120\n", "sim.frame_rate_hz"),
+    ] {
+        match AppConfig::from_toml_str(contents) {
+            Err(ConfigError::OutOfRange { key, .. }) => assert_eq!(key, rejected_key),
+            _ => panic!("{} was not rejected", rejected_key),
+        }
+    }
+}
+
+#[test]
+fn linux_line_offsets_may_exceed_the_pi_header() {
+    let contents = "[io]\nbackend = \"linux\"\n\
+                    pwm_channels = { 26 = 0, 12 = 1, 22 = 2, 23 = 3, 40 = 4 }\n\
+                    [led]\npin_b_bcm = 40\n";
+    assert_eq!(AppConfig::from_toml_str(contents).unwrap().led.pin_b_bcm, 40);
+}
+
+#[test]
+fn pwm_channels_are_taken_as_listed() {
+    let contents = "[io]\npwm_channels = { 26 = 0, 12 = 1 }\n";
+    let channels = AppConfig::from_toml_str(contents).unwrap().io.pwm_channels;
+    assert_eq!(channels.into_iter().collect::<Vec<_>>(), [(12, 1), (26, 0)]);
+}
@@ -0,0 +1,89 @@
+//! Exercises the [CdevGpioDriver] against a simulated GPIO chip. The tests
+//! are ignored by default since they need one of the kernel modules:
+//!
+//! * `gpio-mockup`: `modprobe gpio-mockup gpio_mockup_ranges=-1,8`, then set
+//!   `COCOS_TEST_GPIO_CHIP=/dev/gpiochipN` and
+//!   `COCOS_TEST_GPIO_MOCKUP=/sys/kernel/debug/gpio-mockup/gpiochipN`.
+//! * `gpio-sim`: create a bank with at least 8 lines through configfs, then set
+//!   `COCOS_TEST_GPIO_CHIP=/dev/gpiochipN` and
+//!   `COCOS_TEST_GPIO_SIM=/sys/devices/platform/gpio-sim.0/gpiochipN`.
+//!
+//! and run `cargo test --test linux_gpio -- --ignored`.
+use std::{env, fs, time::Duration};
+
+use cocos::io::interface::gpio::{DrivesGpio, Edge, PullMode};
+use cocos::io::linux::gpio::CdevGpioDriver;
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn driver() -> CdevGpioDriver {
+    let chip = env::var("COCOS_TEST_GPIO_CHIP").expect("COCOS_TEST_GPIO_CHIP is not set");
+    CdevGpioDriver::new(&chip).unwrap()
+}
+
+/// Drives the level the simulated chip presents on the input `line`.
+fn drive_input(line: u8, level: bool) {
+    if let Ok(dir) = env::var("COCOS_TEST_GPIO_SIM") {
+        let pull = if level { "pull-up" } else { "pull-down" };
+        fs::write(format!("{}/sim_gpio{}/pull", dir, line), pull).unwrap();
+    } else if let Ok(dir) = env::var("COCOS_TEST_GPIO_MOCKUP") {
+        fs::write(format!("{}/{}", dir, line), if level { "1" } else { "0" }).unwrap();
+    } else {
+        panic!("neither COCOS_TEST_GPIO_SIM nor COCOS_TEST_GPIO_MOCKUP is set");
+    }
+}
+
+#[test]
+#[ignore = "needs a gpio-mockup or gpio-sim chip"]
+fn outputs_read_back_what_was_written() {
+    let mut gpio = driver();
+    gpio.set_out(0, PullMode::Up).unwrap();
+    assert!(!gpio.read(0).unwrap());
+
+    gpio.set(0).unwrap();
+    assert!(gpio.read(0).unwrap());
+    gpio.clear(0).unwrap();
+    assert!(!gpio.read(0).unwrap());
+}
+
+#[test]
+#[ignore = "needs a gpio-mockup or gpio-sim chip"]
+fn inputs_follow_the_simulated_level() {
+    let mut gpio = driver();
+    gpio.set_inp(1, PullMode::Down).unwrap();
+
+    drive_input(1, true);
+    assert!(gpio.read(1).unwrap());
+    drive_input(1, false);
+    assert!(!gpio.read(1).unwrap());
+}
+
+#[test]
+#[ignore = "needs a gpio-mockup or gpio-sim chip"]
+fn watched_inputs_report_matching_edges() {
+    let mut gpio = driver();
+    drive_input(2, false);
+    let events = gpio.subscribe(2, Edge::Rising).unwrap();
+
+    drive_input(2, true);
+    let event = events.recv_timeout(EVENT_TIMEOUT).unwrap();
+    assert_eq!(event.pin_bcm, 2);
+    assert!(event.level);
+    assert!(gpio.read(2).unwrap());
+
+    drive_input(2, false);
+    assert!(events.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+#[ignore = "needs a gpio-mockup or gpio-sim chip"]
+fn unsubscribing_releases_the_line() {
+    let mut gpio = driver();
+    let events = gpio.subscribe(3, Edge::Both).unwrap();
+    gpio.unsubscribe(3).unwrap();
+
+    // The watcher is gone along with its sender.
+    assert!(events.recv_timeout(EVENT_TIMEOUT).is_err());
+    gpio.set_out(3, PullMode::Down).unwrap();
+    assert!(gpio.read(3).unwrap());
+}
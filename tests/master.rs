@@ -0,0 +1,43 @@
+use std::{
+    io,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use cocos::config::AppConfig;
+use cocos::controllers::master::{MasterController, INIT_FAILURE_EXIT_CODE};
+use cocos::controllers::scheduler::{RealTimeClock, Scheduler};
+use cocos::io::sim_print::{
+    gpio::PrintGpioDriver,
+    pwm::PrintPwmDriver,
+    sink::TraceSink,
+    trace::TraceFormat,
+    uart::PrintUartDriver,
+};
+use cocos::sim::network::SimNetwork;
+
+#[test]
+fn an_init_failure_stops_what_runs_alongside() {
+    let mut app_cfg = AppConfig::default();
+    app_cfg.api.interpreter = String::from("/nonexistent/python3");
+    let trace = TraceSink::new(io::sink(), TraceFormat::Csv, Instant::now()).unwrap();
+    let mut master_controller = MasterController::new(
+        &app_cfg,
+        PrintGpioDriver::new(trace.clone()),
+        PrintPwmDriver::new(trace),
+        PrintUartDriver::new(),
+        SimNetwork::new().attach(),
+    );
+
+    // Stands in for the physics thread of the simulator.
+    let shutdown = master_controller.shutdown_flag();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        Scheduler::new(RealTimeClock::new()).run_until(&shutdown);
+        sender.send(()).unwrap();
+    });
+
+    assert_eq!(master_controller.run(), INIT_FAILURE_EXIT_CODE);
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Ok(()));
+}
@@ -0,0 +1,166 @@
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use cocos::controllers::api::errors::ApiError;
+use cocos::controllers::api::messager::{ApiMessager, ApiMessagerState};
+use cocos::models::api::ApiTickInputMessage;
+use cocos::models::position::Position;
+use serde_json::Value;
+
+/// How long a tick waits for the first request.
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Returns an IPC endpoint of its own for the test `name`.
+fn endpoint(name: &str) -> String {
+    let path = env::temp_dir().join(format!("cocos-messager-{}-{}", std::process::id(), name));
+    format!("ipc://{}", path.display())
+}
+
+fn tick_input() -> ApiTickInputMessage {
+    ApiTickInputMessage {
+        bot_pos: Position::zero(),
+        net_inbox: Vec::new(),
+        motors_locked: false,
+        watchdog_tripped: false,
+        task_stats: Arc::new(Mutex::new(Vec::new())),
+    }
+}
+
+/// Returns a legacy MSG_SEND request broadcasting `msg`.
+fn msg_send(msg: &str) -> String {
+    let body = serde_json::json!({ "msg": msg }).to_string();
+    serde_json::json!({ "request_type": 3, "body": body }).to_string()
+}
+
+/// Connects a socket of `kind` to `endpoint`, the way the API does.
+fn connect(context: &zmq::Context, kind: zmq::SocketType, endpoint: &str) -> zmq::Socket {
+    let sock = context.socket(kind).unwrap();
+    sock.set_linger(0).unwrap();
+    sock.set_rcvtimeo(1000).unwrap();
+    sock.connect(endpoint).unwrap();
+    sock
+}
+
+/// Runs ticks until one returns something to broadcast, or a second passed.
+fn tick_until_sent(messager: &mut ApiMessager) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    loop {
+        let output = messager.run_tick(tick_input()).unwrap();
+        if !output.request_net_send.is_empty() || Instant::now() > deadline {
+            return output.request_net_send;
+        }
+    }
+}
+
+fn status_of(reply: &[u8]) -> u64 {
+    serde_json::from_slice::<Value>(reply).unwrap()["status"].as_u64().unwrap()
+}
+
+#[test]
+fn a_silent_peer_does_not_hold_up_the_tick() {
+    let endpoint = endpoint("silent");
+    let mut messager = ApiMessager::new(endpoint.clone(), POLL_TIMEOUT, 1);
+    messager.start().unwrap();
+    let context = zmq::Context::new();
+
+    // The peer sends a request and goes away before reading the reply.
+    let peer = connect(&context, zmq::REQ, &endpoint);
+    peer.send(msg_send("bye").as_str(), 0).unwrap();
+    assert_eq!(tick_until_sent(&mut messager), [b"bye".to_vec()]);
+    drop(peer);
+
+    // Without requests a tick only waits for the poll timeout.
+    let begin = Instant::now();
+    let output = messager.run_tick(tick_input()).unwrap();
+    assert!(begin.elapsed() < POLL_TIMEOUT * 10, "{:?}", begin.elapsed());
+    assert!(output.request_net_send.is_empty());
+    assert_eq!(messager.state(), ApiMessagerState::Ready);
+
+    // The next peer is served as usual.
+    let peer = connect(&context, zmq::REQ, &endpoint);
+    peer.send(msg_send("hello").as_str(), 0).unwrap();
+    assert_eq!(tick_until_sent(&mut messager), [b"hello".to_vec()]);
+    assert_eq!(status_of(&peer.recv_bytes(0).unwrap()), 0);
+}
+
+#[test]
+fn the_messager_starts_again_after_a_socket_error() {
+    let squatter_context = zmq::Context::new();
+    let squatter = squatter_context.socket(zmq::REP).unwrap();
+    squatter.bind("tcp://127.0.0.1:*").unwrap();
+    let endpoint = squatter.get_last_endpoint().unwrap().unwrap();
+
+    // The endpoint is taken, so binding it fails and leaves nothing behind.
+    let mut messager = ApiMessager::new(endpoint.clone(), POLL_TIMEOUT, 2);
+    assert!(matches!(messager.start(), Err(ApiError::ZMQError(zmq::Error::EADDRINUSE))));
+    assert!(matches!(messager.run_tick(tick_input()), Err(ApiError::SockNotReady)));
+
+    // Terminating the context waits until the port is released.
+    drop(squatter);
+    drop(squatter_context);
+    messager.start().unwrap();
+    assert_eq!(messager.state(), ApiMessagerState::Ready);
+
+    let context = zmq::Context::new();
+    let peer = connect(&context, zmq::REQ, &endpoint);
+    peer.send(msg_send("hello").as_str(), 0).unwrap();
+    assert_eq!(tick_until_sent(&mut messager), [b"hello".to_vec()]);
+    assert_eq!(status_of(&peer.recv_bytes(0).unwrap()), 0);
+}
+
+#[test]
+fn a_request_lost_in_a_restart_can_be_sent_again() {
+    let endpoint = endpoint("restart");
+    let mut messager = ApiMessager::new(endpoint.clone(), POLL_TIMEOUT, 3);
+    messager.start().unwrap();
+    let context = zmq::Context::new();
+
+    // The request is sent while the socket is down, so it never arrives.
+    let peer = connect(&context, zmq::REQ, &endpoint);
+    messager.stop();
+    peer.send(msg_send("lost").as_str(), 0).unwrap();
+    messager.start().unwrap();
+
+    // Like the API, the peer rebuilds its socket once the reply is overdue,
+    // which drops the request it could not deliver.
+    drop(peer);
+    let peer = connect(&context, zmq::REQ, &endpoint);
+    peer.send(msg_send("again").as_str(), 0).unwrap();
+    assert_eq!(tick_until_sent(&mut messager), [b"again".to_vec()]);
+    assert_eq!(status_of(&peer.recv_bytes(0).unwrap()), 0);
+}
+
+#[test]
+fn queued_requests_are_drained_in_one_tick() {
+    let endpoint = endpoint("drain");
+    let mut messager = ApiMessager::new(endpoint.clone(), POLL_TIMEOUT, 4);
+    messager.start().unwrap();
+    let context = zmq::Context::new();
+
+    // A DEALER may queue requests without waiting for the replies.
+    let peer = connect(&context, zmq::DEALER, &endpoint);
+    let sent: Vec<String> = (0..70).map(|i| format!("m{}", i)).collect();
+    for msg in &sent {
+        peer.send_multipart([&b""[..], msg_send(msg).as_bytes()], 0).unwrap();
+    }
+    // Wait for all of them to arrive before the first tick.
+    thread::sleep(Duration::from_millis(200));
+
+    // A tick handles at most 64 requests, the next one takes the rest.
+    let first = messager.run_tick(tick_input()).unwrap().request_net_send;
+    let second = messager.run_tick(tick_input()).unwrap().request_net_send;
+    assert_eq!(first.len(), 64);
+    let received: Vec<Vec<u8>> = first.into_iter().chain(second).collect();
+    let expected: Vec<Vec<u8>> = sent.iter().map(|msg| msg.as_bytes().to_vec()).collect();
+    assert_eq!(received, expected);
+
+    for _ in &sent {
+        let reply = peer.recv_multipart(0).unwrap();
+        assert_eq!(status_of(&reply[1]), 0);
+    }
+    assert_eq!(messager.state(), ApiMessagerState::Ready);
+}
@@ -0,0 +1,241 @@
+use std::{collections::HashMap, error::Error, sync::mpsc::{self, Receiver}};
+
+use cocos::controllers::motor::MotorController;
+use cocos::drivers::motor_driver::{MotorDescriptor, MotorError};
+use cocos::io::interface::{
+    gpio::{DrivesGpio, Edge, GpioError, GpioEvent, GpioOperation, PullMode},
+    pwm::{DrivesPwm, PwmError},
+};
+use cocos::models::motor_power::MotorPower;
+use uom::si::{f32::Frequency, frequency::hertz};
+
+/// Records the last level written to every GPIO pin.
+#[derive(Default)]
+struct RecordingGpio {
+    levels: HashMap<u8, bool>,
+    /// A pin that cannot be set.
+    broken_pin: Option<u8>,
+}
+
+impl RecordingGpio {
+    fn level(&self, pin_bcm: u8) -> bool {
+        self.levels[&pin_bcm]
+    }
+}
+
+impl DrivesGpio for RecordingGpio {
+    fn set(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        if self.broken_pin == Some(pin_bcm) {
+            return Err(GpioError::IO {
+                pin_bcm,
+                operation: GpioOperation::Set,
+                cause: "line busy".into(),
+            });
+        }
+        self.levels.insert(pin_bcm, true);
+        Ok(())
+    }
+
+    fn clear(&mut self, pin_bcm: u8) -> Result<(), GpioError> {
+        self.levels.insert(pin_bcm, false);
+        Ok(())
+    }
+
+    fn set_out(&mut self, _pin_bcm: u8, _pull_mode: PullMode) -> Result<(), GpioError> {
+        Ok(())
+    }
+
+    fn set_inp(&mut self, _pin_bcm: u8, _pull_mode: PullMode) -> Result<(), GpioError> {
+        Ok(())
+    }
+
+    fn read(&mut self, pin_bcm: u8) -> Result<bool, GpioError> {
+        Ok(self.levels.get(&pin_bcm).copied().unwrap_or(false))
+    }
+
+    fn subscribe(&mut self, _pin_bcm: u8, _edge: Edge) -> Result<Receiver<GpioEvent>, GpioError> {
+        Ok(mpsc::channel().1)
+    }
+
+    fn unsubscribe(&mut self, _pin_bcm: u8) -> Result<(), GpioError> {
+        Ok(())
+    }
+}
+
+/// Records the last frequency and duty cycle written to every PWM pin.
+#[derive(Default)]
+struct RecordingPwm {
+    frequencies: HashMap<u8, Frequency>,
+    duty_cycles: HashMap<u8, f32>,
+    /// A pin whose PWM cannot be set.
+    broken_pin: Option<u8>,
+}
+
+impl DrivesPwm for RecordingPwm {
+    fn set_freq_dc(&mut self, frequency: Frequency, duty_cycle: f32,
+                   pin_bcm: u8) -> Result<(), PwmError> {
+        if self.broken_pin == Some(pin_bcm) {
+            return Err(PwmError::IO { pin_bcm, cause: "channel busy".into() });
+        }
+        self.frequencies.insert(pin_bcm, frequency);
+        self.duty_cycles.insert(pin_bcm, duty_cycle);
+        Ok(())
+    }
+}
+
+fn left_motor() -> MotorDescriptor {
+    MotorDescriptor {
+        pin_in1: 1,
+        pin_in2: 2,
+        pin_pwm: 3,
+        pin_stdby: 20,
+        inverted: true,
+        pwm_frequency: Frequency::new::<hertz>(600.0),
+    }
+}
+
+fn right_motor() -> MotorDescriptor {
+    MotorDescriptor {
+        pin_in1: 4,
+        pin_in2: 5,
+        pin_pwm: 6,
+        pin_stdby: 20,
+        inverted: false,
+        pwm_frequency: Frequency::new::<hertz>(600.0),
+    }
+}
+
+/// Returns whether the motor described by `motor` spins clockwise.
+fn is_clockwise(gpio: &RecordingGpio, motor: MotorDescriptor) -> bool {
+    assert_ne!(gpio.level(motor.pin_in1), gpio.level(motor.pin_in2));
+    gpio.level(motor.pin_in1)
+}
+
+fn drive(left: f32, right: f32) -> (RecordingGpio, RecordingPwm) {
+    let controller = MotorController::new(left_motor(), right_motor());
+    let mut gpio = RecordingGpio::default();
+    let mut pwm = RecordingPwm::default();
+    controller
+        .set_vel(MotorPower::new(left, right, false).unwrap(), &mut gpio, &mut pwm)
+        .unwrap();
+    (gpio, pwm)
+}
+
+#[test]
+fn each_wheel_gets_its_own_pwm() {
+    let (_, pwm) = drive(0.25, -0.75);
+    assert_eq!(pwm.duty_cycles[&left_motor().pin_pwm], 0.25);
+    assert_eq!(pwm.duty_cycles[&right_motor().pin_pwm], 0.75);
+}
+
+#[test]
+fn each_wheel_uses_the_pwm_frequency_of_its_descriptor() {
+    let controller = MotorController::new(
+        MotorDescriptor { pwm_frequency: Frequency::new::<hertz>(20_000.0), ..left_motor() },
+        right_motor(),
+    );
+    let mut gpio = RecordingGpio::default();
+    let mut pwm = RecordingPwm::default();
+    controller
+        .set_vel(MotorPower::new(0.5, 0.5, false).unwrap(), &mut gpio, &mut pwm)
+        .unwrap();
+
+    assert_eq!(pwm.frequencies[&left_motor().pin_pwm].get::<hertz>(), 20_000.0);
+    assert_eq!(pwm.frequencies[&right_motor().pin_pwm].get::<hertz>(), 600.0);
+}
+
+#[test]
+fn forward_spins_mirrored_motors_in_opposite_directions() {
+    let (gpio, _) = drive(0.5, 0.5);
+    assert!(!is_clockwise(&gpio, left_motor()));
+    assert!(is_clockwise(&gpio, right_motor()));
+    assert!(gpio.level(left_motor().pin_stdby));
+}
+
+#[test]
+fn every_quadrant_maps_each_wheel_independently() {
+    for (left, right) in [(0.5, 0.5), (0.5, -0.5), (-0.5, 0.5), (-0.5, -0.5)] {
+        let (gpio, _) = drive(left, right);
+        assert_eq!(is_clockwise(&gpio, left_motor()), left < 0.0, "left for ({left}, {right})");
+        assert_eq!(is_clockwise(&gpio, right_motor()), right >= 0.0, "right for ({left}, {right})");
+    }
+}
+
+#[test]
+fn inverting_a_motor_flips_its_direction() {
+    let controller = MotorController::new(
+        MotorDescriptor { inverted: false, ..left_motor() },
+        MotorDescriptor { inverted: true, ..right_motor() },
+    );
+    let mut gpio = RecordingGpio::default();
+    let mut pwm = RecordingPwm::default();
+    controller
+        .set_vel(MotorPower::new(0.5, 0.5, false).unwrap(), &mut gpio, &mut pwm)
+        .unwrap();
+
+    assert!(is_clockwise(&gpio, left_motor()));
+    assert!(!is_clockwise(&gpio, right_motor()));
+}
+
+#[test]
+fn locked_power_blocks_both_motors() {
+    let controller = MotorController::new(left_motor(), right_motor());
+    let mut gpio = RecordingGpio::default();
+    let mut pwm = RecordingPwm::default();
+    controller
+        .set_vel(MotorPower::new(0.5, 0.5, true).unwrap(), &mut gpio, &mut pwm)
+        .unwrap();
+
+    for motor in [left_motor(), right_motor()] {
+        assert!(gpio.level(motor.pin_in1));
+        assert!(gpio.level(motor.pin_in2));
+        assert!(!gpio.level(motor.pin_stdby));
+    }
+    assert!(pwm.duty_cycles.is_empty());
+}
+
+/// Returns the messages of `err` and of all its causes.
+fn error_chain(err: &dyn Error) -> Vec<String> {
+    let mut chain = vec![err.to_string()];
+    let mut source = err.source();
+    while let Some(err) = source {
+        chain.push(err.to_string());
+        source = err.source();
+    }
+    chain
+}
+
+#[test]
+fn gpio_failures_name_the_motor_pin_and_cause() {
+    let controller = MotorController::new(left_motor(), right_motor());
+    let mut gpio = RecordingGpio { broken_pin: Some(right_motor().pin_in1), ..Default::default() };
+    let err = controller.block(&mut gpio).unwrap_err();
+
+    assert_eq!(error_chain(&err), [
+        "right motor: could not set GPIO pin 4: line busy",
+        "could not set GPIO pin 4: line busy",
+        "could not set GPIO pin 4: line busy",
+        "line busy",
+    ]);
+    let cause = err.source().unwrap().downcast_ref::<MotorError>().unwrap();
+    assert!(matches!(cause, MotorError::Gpio(GpioError::IO { pin_bcm: 4, .. })));
+}
+
+#[test]
+fn pwm_failures_name_the_motor_pin_and_cause() {
+    let controller = MotorController::new(left_motor(), right_motor());
+    let mut gpio = RecordingGpio::default();
+    let mut pwm = RecordingPwm { broken_pin: Some(left_motor().pin_pwm), ..Default::default() };
+    let err = controller
+        .set_vel(MotorPower::new(0.5, 0.5, false).unwrap(), &mut gpio, &mut pwm)
+        .unwrap_err();
+
+    assert_eq!(error_chain(&err), [
+        "left motor: could not set the PWM of pin 3: channel busy",
+        "could not set the PWM of pin 3: channel busy",
+        "could not set the PWM of pin 3: channel busy",
+        "channel busy",
+    ]);
+    let cause = err.source().unwrap().downcast_ref::<MotorError>().unwrap();
+    assert!(matches!(cause, MotorError::Pwm(PwmError::IO { pin_bcm: 3, .. })));
+}
@@ -0,0 +1,68 @@
+use std::{
+    net::Ipv4Addr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use cocos::controllers::network::{trim_message, NetMessage, NetworkController, NET_MSG_LEN};
+use cocos::io::{interface::net::NetDescriptor, udp::UdpBroadcastDriver};
+
+type UdpController = NetworkController<UdpBroadcastDriver>;
+
+fn controller(port: u16) -> UdpController {
+    let descriptor = NetDescriptor { port, broadcast_addr: Ipv4Addr::new(127, 255, 255, 255) };
+    NetworkController::new(UdpBroadcastDriver::new(descriptor).unwrap())
+}
+
+/// Polls `controller` until its inbox holds `count` messages.
+fn wait_for_inbox(controller: &mut UdpController, count: usize) -> Vec<NetMessage> {
+    let begin = Instant::now();
+    while begin.elapsed() < Duration::from_secs(2) {
+        controller.poll().unwrap();
+        if controller.inbox().len() >= count {
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    controller.inbox()
+}
+
+#[test]
+fn messages_are_received_by_the_other_coachbots_only() {
+    let mut sender = controller(47_201);
+    let mut receiver = controller(47_201);
+
+    sender.send(b"hello swarm").unwrap();
+    let inbox = wait_for_inbox(&mut receiver, 1);
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0].data, b"hello swarm");
+
+    sender.poll().unwrap();
+    assert!(sender.inbox().is_empty());
+
+    receiver.acknowledge(inbox[0].id);
+    assert!(receiver.inbox().is_empty());
+}
+
+#[test]
+fn long_messages_are_trimmed_to_a_packet() {
+    let mut sender = controller(47_202);
+    let mut receiver = controller(47_202);
+
+    let message = "x".repeat(2 * NET_MSG_LEN);
+    sender.send(message.as_bytes()).unwrap();
+    let inbox = wait_for_inbox(&mut receiver, 1);
+    assert_eq!(inbox[0].data, message.as_bytes()[..NET_MSG_LEN]);
+}
+
+#[test]
+fn text_is_trimmed_on_a_character_boundary() {
+    let short = "kurz";
+    assert_eq!(trim_message(short), short);
+
+    // Every `ö` takes two bytes, so the last one straddles the limit.
+    let message = format!("{}{}", "a", "ö".repeat(NET_MSG_LEN / 2));
+    let trimmed = trim_message(&message);
+    assert_eq!(trimmed.len(), NET_MSG_LEN - 1);
+    assert!(message.starts_with(trimmed));
+}
@@ -0,0 +1,103 @@
+use std::{thread, time::Duration};
+
+use cocos::config::AppConfig;
+use cocos::drivers::nucifera_driver::{
+    NuciferaDescriptor, NuciferaDriver, NuciferaError, NuciferaMessage, NUCIFERA_FRAME_LEN,
+};
+use cocos::io::sim_print::uart::PrintUartDriver;
+use uom::si::length::meter;
+
+/// A frame as captured from Nucifera: sequence 7 at (0.5 m, -1.25 m, 0 rad).
+const CAPTURED_FRAME: [u8; NUCIFERA_FRAME_LEN] = [
+    0xaa, 0x55, 0x07, 0x00, 0x00, 0x00, 0x3f, 0x00,
+    0x00, 0xa0, 0xbf, 0x00, 0x00, 0x00, 0x00, 0x27,
+];
+
+fn descriptor(stale_timeout: Duration) -> NuciferaDescriptor {
+    NuciferaDescriptor { stale_timeout, ..AppConfig::default().nucifera }
+}
+
+fn frame(sequence: u8, x: f32) -> [u8; NUCIFERA_FRAME_LEN] {
+    NuciferaMessage { sequence, x, y: 0.0, theta: 0.0 }.encode()
+}
+
+#[test]
+fn captured_frames_are_decoded() {
+    let message = NuciferaMessage::decode(&CAPTURED_FRAME).unwrap();
+    assert_eq!(message.sequence, 7);
+    assert_eq!((message.x, message.y, message.theta), (0.5, -1.25, 0.0));
+    assert_eq!(message.encode(), CAPTURED_FRAME);
+}
+
+#[test]
+fn frames_with_a_wrong_checksum_are_rejected() {
+    let mut frame = CAPTURED_FRAME;
+    frame[5] ^= 0x01;
+    assert!(matches!(NuciferaMessage::decode(&frame),
+                     Err(NuciferaError::Corrupt { expected: 0x26, received: 0x27 })));
+    assert!(matches!(NuciferaMessage::decode(&frame[..NUCIFERA_FRAME_LEN - 1]),
+                     Err(NuciferaError::Partial)));
+}
+
+#[test]
+fn the_decoder_resyncs_after_garbage_and_corrupt_frames() {
+    let mut uart = PrintUartDriver::new();
+    let feed = uart.feed();
+    let mut driver = NuciferaDriver::new(descriptor(Duration::from_secs(10)));
+
+    let mut corrupt = frame(1, 1.0);
+    corrupt[NUCIFERA_FRAME_LEN - 1] ^= 0xff;
+    feed.push(&[0x13, 0xaa, 0x37, 0x55]);
+    feed.push(&corrupt);
+    feed.push(&[0xaa]);
+    feed.push(&frame(2, 2.0));
+    let position = driver.read_current_position(&mut uart).unwrap();
+    assert_eq!(position.x.get::<meter>(), 2.0);
+
+    feed.push(&corrupt);
+    assert!(matches!(driver.read_current_position(&mut uart),
+                     Err(NuciferaError::Corrupt { .. })));
+}
+
+#[test]
+fn frames_split_across_reads_are_reassembled() {
+    let mut uart = PrintUartDriver::new();
+    let feed = uart.feed();
+    let mut driver = NuciferaDriver::new(descriptor(Duration::from_secs(10)));
+
+    let (first, second) = CAPTURED_FRAME.split_at(9);
+    feed.push(first);
+    assert!(matches!(driver.read_current_position(&mut uart), Err(NuciferaError::Partial)));
+    feed.push(second);
+    assert_eq!(driver.read_current_position(&mut uart).unwrap().x.get::<meter>(), 0.5);
+}
+
+#[test]
+fn the_latest_of_several_frames_wins() {
+    let mut uart = PrintUartDriver::new();
+    let feed = uart.feed();
+    let mut driver = NuciferaDriver::new(descriptor(Duration::from_secs(10)));
+
+    feed.push(&frame(1, 1.0));
+    feed.push(&frame(2, 2.0));
+    feed.push(&frame(3, 3.0));
+    assert_eq!(driver.read_current_position(&mut uart).unwrap().x.get::<meter>(), 3.0);
+}
+
+#[test]
+fn repeated_frames_do_not_refresh_the_position() {
+    let mut uart = PrintUartDriver::new();
+    let feed = uart.feed();
+    let mut driver = NuciferaDriver::new(descriptor(Duration::from_millis(20)));
+
+    feed.push(&frame(4, 1.0));
+    assert!(driver.read_current_position(&mut uart).is_ok());
+
+    thread::sleep(Duration::from_millis(30));
+    feed.push(&frame(4, 1.0));
+    assert!(matches!(driver.read_current_position(&mut uart),
+                     Err(NuciferaError::Stale { .. })));
+
+    feed.push(&frame(5, 1.5));
+    assert_eq!(driver.read_current_position(&mut uart).unwrap().x.get::<meter>(), 1.5);
+}
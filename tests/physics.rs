@@ -0,0 +1,181 @@
+use std::{f32::consts::PI, io, time::{Duration, Instant}};
+
+use cocos::config::AppConfig;
+use cocos::controllers::{interface::HandlesTick10Ms, motor::MotorController};
+use cocos::drivers::nucifera_driver::NuciferaDriver;
+use cocos::io::sim_print::{
+    gpio::PrintGpioDriver,
+    pwm::PrintPwmDriver,
+    sink::TraceSink,
+    trace::TraceFormat,
+    uart::PrintUartDriver,
+};
+use cocos::models::motor_power::MotorPower;
+use cocos::sim::{
+    physics::{PhysicsDescriptor, Pose, WheelSpeeds, World},
+    robot::{PhysicsTask, SimRobot},
+};
+use uom::si::{angle::radian, length::meter};
+
+/// A noiseless world with the default geometry.
+fn noiseless() -> PhysicsDescriptor {
+    PhysicsDescriptor { speed_noise: 0.0, position_noise_m: 0.0, ..PhysicsDescriptor::default() }
+}
+
+fn origin() -> Pose {
+    Pose { x: 0.0, y: 0.0, theta: 0.0 }
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-4, "{} is not close to {}", actual, expected);
+}
+
+/// Steps `world` for `duration` in 10 ms steps.
+fn run(world: &mut World, duration: Duration, speeds: WheelSpeeds) {
+    for _ in 0..duration.as_millis() / 10 {
+        world.step(Duration::from_millis(10), &[speeds]);
+    }
+}
+
+#[test]
+fn equal_wheel_speeds_drive_straight_ahead() {
+    let mut world = World::new(noiseless());
+    let robot = world.add_robot(Pose { theta: PI / 2.0, ..origin() });
+    run(&mut world, Duration::from_secs(2), WheelSpeeds { left: 0.1, right: 0.1 });
+
+    let pose = world.pose(robot);
+    assert_close(pose.x, 0.0);
+    assert_close(pose.y, 0.2);
+    assert_close(pose.theta, PI / 2.0);
+}
+
+#[test]
+fn opposite_wheel_speeds_spin_in_place() {
+    let mut world = World::new(noiseless());
+    let robot = world.add_robot(origin());
+    // Half a turn takes π * b / 2 / v seconds.
+    let speed = 0.08 * PI / 4.0;
+    run(&mut world, Duration::from_secs(2), WheelSpeeds { left: -speed, right: speed });
+
+    let pose = world.pose(robot);
+    assert_close(pose.x, 0.0);
+    assert_close(pose.y, 0.0);
+    assert_close(pose.theta.abs(), PI);
+}
+
+#[test]
+fn a_curve_follows_its_arc() {
+    let mut world = World::new(noiseless());
+    let robot = world.add_robot(origin());
+    // A radius of 0.2 m, driven a quarter turn in 1 s.
+    let omega = PI / 2.0;
+    let (left, right) = (omega * (0.2 - 0.04), omega * (0.2 + 0.04));
+    run(&mut world, Duration::from_secs(1), WheelSpeeds { left, right });
+
+    let pose = world.pose(robot);
+    assert_close(pose.x, 0.2);
+    assert_close(pose.y, 0.2);
+    assert_close(pose.theta, PI / 2.0);
+}
+
+#[test]
+fn robots_stop_at_the_walls_of_the_arena() {
+    let mut world = World::new(noiseless());
+    let robot = world.add_robot(Pose { x: 1.4, ..origin() });
+    run(&mut world, Duration::from_secs(2), WheelSpeeds { left: 0.2, right: 0.2 });
+
+    assert_eq!(world.pose(robot).x, 1.5);
+}
+
+/// Drives a robot along a curve for a second and returns where it ends up.
+fn test_drive(descriptor: PhysicsDescriptor) -> Pose {
+    let mut world = World::new(descriptor);
+    let robot = world.add_robot(origin());
+    run(&mut world, Duration::from_secs(1), WheelSpeeds { left: 0.1, right: 0.15 });
+    world.pose(robot)
+}
+
+#[test]
+fn seeded_noise_is_reproducible() {
+    let seeded = PhysicsDescriptor { seed: Some(7), ..PhysicsDescriptor::default() };
+    assert_eq!(test_drive(seeded), test_drive(seeded));
+    assert_ne!(test_drive(seeded), test_drive(noiseless()));
+}
+
+/// Drives the motors of the default configuration with `power` through the
+/// simulated drivers and returns the decoded wheel speeds.
+fn decode(power: MotorPower) -> WheelSpeeds {
+    let config = AppConfig::default();
+    let trace = TraceSink::new(io::sink(), TraceFormat::Csv, Instant::now()).unwrap();
+    let mut gpio = PrintGpioDriver::new(trace.clone());
+    let mut pwm = PrintPwmDriver::new(trace);
+    let robot = SimRobot::new(0, config.mot_left, config.mot_right, gpio.inputs(), pwm.outputs(),
+                              PrintUartDriver::new().feed());
+
+    let controller = MotorController::new(config.mot_left, config.mot_right);
+    controller.set_vel(power, &mut gpio, &mut pwm).unwrap();
+    robot.wheel_speeds(1.0)
+}
+
+#[test]
+fn positive_power_drives_both_wheels_forward() {
+    let speeds = decode(MotorPower::new(0.5, 0.25, false).unwrap());
+    assert_close(speeds.left, 0.5);
+    assert_close(speeds.right, 0.25);
+}
+
+#[test]
+fn negative_power_drives_the_wheels_backward() {
+    let speeds = decode(MotorPower::new(-0.5, 0.75, false).unwrap());
+    assert_close(speeds.left, -0.5);
+    assert_close(speeds.right, 0.75);
+}
+
+#[test]
+fn locked_motors_do_not_move() {
+    assert_eq!(decode(MotorPower::new(0.5, 0.5, true).unwrap()), WheelSpeeds::default());
+}
+
+#[test]
+fn the_position_is_reported_over_nucifera() {
+    let config = AppConfig::default();
+    let trace = TraceSink::new(io::sink(), TraceFormat::Csv, Instant::now()).unwrap();
+    let mut gpio = PrintGpioDriver::new(trace.clone());
+    let mut pwm = PrintPwmDriver::new(trace);
+    let mut uart = PrintUartDriver::new();
+
+    let mut world = World::new(noiseless());
+    world.add_robot(Pose { x: 0.5, y: -0.25, theta: 0.0 });
+    let robot = SimRobot::new(0, config.mot_left, config.mot_right, gpio.inputs(), pwm.outputs(),
+                              uart.feed());
+    let mut physics = PhysicsTask::new(world, vec![robot]);
+
+    let controller = MotorController::new(config.mot_left, config.mot_right);
+    controller
+        .set_vel(MotorPower::new(0.5, 0.5, false).unwrap(), &mut gpio, &mut pwm)
+        .unwrap();
+    // Poll Nucifera as the positioning task does.
+    let mut nucifera = NuciferaDriver::new(config.nucifera);
+    let mut position = None;
+    let mut tick = |physics: &mut PhysicsTask| {
+        physics.on_tick10();
+        if let Ok(received) = nucifera.read_current_position(&mut uart) {
+            position = Some(received);
+        }
+    };
+    for _ in 0..100 {
+        tick(&mut physics);
+    }
+    // Stop and wait for a frame so that the last frame holds the final pose.
+    controller.set_vel(MotorPower::zero(), &mut gpio, &mut pwm).unwrap();
+    for _ in 0..10 {
+        tick(&mut physics);
+    }
+
+    let expected = physics.world().pose(0);
+    assert!(expected.x > 0.5);
+    let position = position.unwrap();
+    assert_close(position.x.get::<meter>(), expected.x);
+    assert_close(position.y.get::<meter>(), expected.y);
+    assert_close(position.theta.get::<radian>(), expected.theta);
+}
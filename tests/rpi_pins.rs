@@ -0,0 +1,63 @@
+use cocos::io::rpi::pins::{PinClaims, PinOwner, PinRegistryError};
+
+#[test]
+fn a_pin_claimed_again_by_its_owner_stays_claimed() {
+    let mut claims = PinClaims::new();
+    claims.claim(17, PinOwner::Gpio).unwrap();
+    claims.claim(17, PinOwner::Gpio).unwrap();
+
+    assert_eq!(claims.owner(17), Some(PinOwner::Gpio));
+}
+
+#[test]
+fn a_pin_driven_by_a_peripheral_cannot_be_claimed_again() {
+    let mut claims = PinClaims::new();
+    claims.claim_peripheral(12, PinOwner::Pwm).unwrap();
+
+    assert!(matches!(claims.claim_peripheral(12, PinOwner::Pwm),
+                     Err(PinRegistryError::Peripheral { pin_bcm: 12, owner: PinOwner::Pwm })));
+    assert!(matches!(claims.claim(12, PinOwner::Pwm),
+                     Err(PinRegistryError::Peripheral { pin_bcm: 12, owner: PinOwner::Pwm })));
+    assert_eq!(claims.owner(12), Some(PinOwner::Pwm));
+}
+
+#[test]
+fn a_pin_claimed_by_another_driver_is_rejected() {
+    let mut claims = PinClaims::new();
+    claims.claim(17, PinOwner::Gpio).unwrap();
+    claims.claim_peripheral(12, PinOwner::Pwm).unwrap();
+
+    assert!(matches!(claims.claim(17, PinOwner::Pwm), Err(PinRegistryError::Conflict {
+        pin_bcm: 17,
+        owner: PinOwner::Gpio,
+        requested: PinOwner::Pwm,
+    })));
+    assert!(matches!(claims.claim_peripheral(17, PinOwner::Pwm),
+                     Err(PinRegistryError::Conflict { pin_bcm: 17, .. })));
+    assert!(matches!(claims.claim(12, PinOwner::Gpio), Err(PinRegistryError::Conflict {
+        pin_bcm: 12,
+        owner: PinOwner::Pwm,
+        requested: PinOwner::Gpio,
+    })));
+    assert_eq!(claims.owner(17), Some(PinOwner::Gpio));
+}
+
+#[test]
+fn a_released_pin_can_be_claimed_by_another_driver() {
+    let mut claims = PinClaims::new();
+    claims.claim_peripheral(12, PinOwner::Pwm).unwrap();
+    claims.release(12);
+    assert_eq!(claims.owner(12), None);
+
+    claims.claim(12, PinOwner::Gpio).unwrap();
+    assert_eq!(claims.owner(12), Some(PinOwner::Gpio));
+}
+
+#[test]
+fn conflicts_name_both_drivers() {
+    let mut claims = PinClaims::new();
+    claims.claim(17, PinOwner::Gpio).unwrap();
+
+    let err = claims.claim(17, PinOwner::Pwm).unwrap_err();
+    assert_eq!(err.to_string(), "BCM pin 17 is used for GPIO and cannot be used for PWM");
+}
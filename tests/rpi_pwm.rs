@@ -0,0 +1,69 @@
+use cocos::io::rpi::pwm::{hardware_channel, is_routed, PwmChannelMap};
+use rppal::{gpio::Mode, pwm::Channel};
+
+#[test]
+fn header_pins_map_to_their_hardware_channel() {
+    assert_eq!(hardware_channel(12), Some(Channel::Pwm0));
+    assert_eq!(hardware_channel(18), Some(Channel::Pwm0));
+    assert_eq!(hardware_channel(13), Some(Channel::Pwm1));
+    assert_eq!(hardware_channel(19), Some(Channel::Pwm1));
+}
+
+#[test]
+fn other_pins_have_no_hardware_channel() {
+    for pin_bcm in (0..=27).filter(|pin_bcm| ![12, 13, 18, 19].contains(pin_bcm)) {
+        assert_eq!(hardware_channel(pin_bcm), None, "pin {}", pin_bcm);
+    }
+}
+
+#[test]
+fn a_channel_is_assigned_to_a_single_pin() {
+    let mut channels = PwmChannelMap::new();
+    assert_eq!(channels.assign(12), Some(Channel::Pwm0));
+    assert_eq!(channels.assign(18), None);
+    assert_eq!(channels.assign(19), Some(Channel::Pwm1));
+    assert_eq!(channels.assign(13), None);
+
+    assert_eq!(channels.channel(12), Some(Channel::Pwm0));
+    assert_eq!(channels.channel(18), None);
+}
+
+#[test]
+fn assigning_a_pin_twice_keeps_its_channel() {
+    let mut channels = PwmChannelMap::new();
+    assert_eq!(channels.assign(18), Some(Channel::Pwm0));
+    assert_eq!(channels.assign(18), Some(Channel::Pwm0));
+}
+
+#[test]
+fn a_released_channel_can_be_reassigned() {
+    let mut channels = PwmChannelMap::new();
+    assert_eq!(channels.assign(12), Some(Channel::Pwm0));
+    channels.release(12);
+    assert_eq!(channels.channel(12), None);
+    assert_eq!(channels.assign(18), Some(Channel::Pwm0));
+}
+
+#[test]
+fn pins_without_a_channel_fall_back_to_software() {
+    let mut channels = PwmChannelMap::new();
+    assert_eq!(channels.assign(26), None);
+    channels.release(26);
+    assert_eq!(channels.assign(12), Some(Channel::Pwm0));
+}
+
+#[test]
+fn only_routed_pins_use_their_hardware_channel() {
+    assert!(is_routed(12, Mode::Alt0));
+    assert!(is_routed(13, Mode::Alt0));
+    assert!(is_routed(18, Mode::Alt5));
+    assert!(is_routed(19, Mode::Alt5));
+
+    // Without the overlay the pins stay plain GPIOs and fall back to
+    // software PWM.
+    assert!(!is_routed(12, Mode::Output));
+    assert!(!is_routed(18, Mode::Input));
+    assert!(!is_routed(18, Mode::Alt0));
+    assert!(!is_routed(13, Mode::Alt5));
+    assert!(!is_routed(26, Mode::Alt0));
+}
@@ -0,0 +1,210 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use cocos::controllers::{
+    interface::{HandlesTick1000Ms, HandlesTick100Ms, HandlesTick10Ms, HandlesTick1Ms},
+    scheduler::{Clock, Scheduler, VirtualClock},
+    task_stats::EXEC_TIME_BUCKETS_US,
+};
+
+/// Records every tick it receives, tagged with its name.
+struct Recorder {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Recorder {
+    fn record(&self, rate: &str) {
+        self.log.lock().unwrap().push(format!("{}@{}", self.name, rate));
+    }
+}
+
+impl HandlesTick1Ms for Recorder {
+    fn on_tick1(&mut self) {
+        self.record("1");
+    }
+}
+
+impl HandlesTick10Ms for Recorder {
+    fn on_tick10(&mut self) {
+        self.record("10");
+    }
+}
+
+impl HandlesTick100Ms for Recorder {
+    fn on_tick100(&mut self) {
+        self.record("100");
+    }
+}
+
+impl HandlesTick1000Ms for Recorder {
+    fn on_tick1000(&mut self) {
+        self.record("1000");
+    }
+}
+
+/// Counts the ticks of every rate.
+#[derive(Default)]
+struct Counter {
+    tick1: u32,
+    tick10: u32,
+    tick100: u32,
+    tick1000: u32,
+}
+
+impl HandlesTick1Ms for Counter {
+    fn on_tick1(&mut self) {
+        self.tick1 += 1;
+    }
+}
+
+impl HandlesTick10Ms for Counter {
+    fn on_tick10(&mut self) {
+        self.tick10 += 1;
+    }
+}
+
+impl HandlesTick100Ms for Counter {
+    fn on_tick100(&mut self) {
+        self.tick100 += 1;
+    }
+}
+
+impl HandlesTick1000Ms for Counter {
+    fn on_tick1000(&mut self) {
+        self.tick1000 += 1;
+    }
+}
+
+fn counting_scheduler() -> (Scheduler<VirtualClock>, Arc<Mutex<Counter>>) {
+    let counter = Arc::new(Mutex::new(Counter::default()));
+    let mut scheduler = Scheduler::new(VirtualClock::new());
+    scheduler.add_tick1("counter 1", Arc::clone(&counter));
+    scheduler.add_tick10("counter 10", Arc::clone(&counter));
+    scheduler.add_tick100("counter 100", Arc::clone(&counter));
+    scheduler.add_tick1000("counter 1000", Arc::clone(&counter));
+    (scheduler, counter)
+}
+
+#[test]
+fn tick_zero_is_due_immediately() {
+    let (mut scheduler, counter) = counting_scheduler();
+    assert_eq!(scheduler.run_pending(), 1);
+
+    let counter = counter.lock().unwrap();
+    assert_eq!((counter.tick1, counter.tick10, counter.tick100, counter.tick1000), (1, 1, 1, 1));
+}
+
+#[test]
+fn one_second_runs_every_rate_the_expected_number_of_times() {
+    let (mut scheduler, counter) = counting_scheduler();
+    scheduler.run_pending();
+    assert_eq!(scheduler.advance(Duration::from_millis(999)), 999);
+
+    let counter = counter.lock().unwrap();
+    assert_eq!(counter.tick1, 1000);
+    assert_eq!(counter.tick10, 100);
+    assert_eq!(counter.tick100, 10);
+    assert_eq!(counter.tick1000, 1);
+}
+
+#[test]
+fn small_steps_match_one_large_step() {
+    let (mut stepped, stepped_counter) = counting_scheduler();
+    for _ in 0..2500 {
+        stepped.advance(Duration::from_micros(400));
+    }
+
+    let (mut jumped, jumped_counter) = counting_scheduler();
+    jumped.advance(Duration::from_millis(1000));
+
+    assert_eq!(stepped.ticks(), jumped.ticks());
+    let stepped_counter = stepped_counter.lock().unwrap();
+    let jumped_counter = jumped_counter.lock().unwrap();
+    assert_eq!(stepped_counter.tick1, jumped_counter.tick1);
+    assert_eq!(stepped_counter.tick10, jumped_counter.tick10);
+    assert_eq!(stepped_counter.tick100, jumped_counter.tick100);
+    assert_eq!(stepped_counter.tick1000, jumped_counter.tick1000);
+}
+
+#[test]
+fn sub_tick_advances_run_nothing() {
+    let (mut scheduler, counter) = counting_scheduler();
+    scheduler.run_pending();
+    assert_eq!(scheduler.advance(Duration::from_micros(999)), 0);
+    assert_eq!(counter.lock().unwrap().tick1, 1);
+}
+
+#[test]
+fn handlers_run_fastest_first_in_registration_order() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut scheduler = Scheduler::new(VirtualClock::new());
+    scheduler.add_tick1000("a", Recorder { name: "a", log: Arc::clone(&log) });
+    scheduler.add_tick10("b", Recorder { name: "b", log: Arc::clone(&log) });
+    scheduler.add_tick10("c", Recorder { name: "c", log: Arc::clone(&log) });
+    scheduler.add_tick1("d", Recorder { name: "d", log: Arc::clone(&log) });
+
+    scheduler.advance(Duration::from_millis(10));
+
+    let mut expected = vec!["d@1", "b@10", "c@10", "a@1000"];
+    expected.extend(["d@1"; 9]);
+    expected.extend(["d@1", "b@10", "c@10"]);
+    assert_eq!(*log.lock().unwrap(), expected);
+}
+
+#[test]
+fn run_for_advances_a_virtual_clock_without_sleeping() {
+    let (mut scheduler, counter) = counting_scheduler();
+    scheduler.run_for(Duration::from_secs(60));
+
+    assert_eq!(scheduler.ticks(), 60_000);
+    assert_eq!(scheduler.clock().now(), Duration::from_millis(59_999));
+    assert_eq!(counter.lock().unwrap().tick1000, 60);
+}
+
+/// Takes longer than its 1ms period.
+struct Sleeper;
+
+impl HandlesTick1Ms for Sleeper {
+    fn on_tick1(&mut self) {
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+#[test]
+fn stats_count_runs_per_task() {
+    let (mut scheduler, _counter) = counting_scheduler();
+    scheduler.advance(Duration::from_millis(1999));
+
+    let stats = scheduler.stats();
+    let stats = stats.lock().unwrap();
+    let runs: Vec<_> = stats.iter().map(|stats| (stats.name(), stats.runs())).collect();
+    assert_eq!(runs, [("counter 1", 2000), ("counter 10", 200), ("counter 100", 20),
+                      ("counter 1000", 2)]);
+    assert_eq!(stats[1].period(), Duration::from_millis(10));
+    for task in stats.iter() {
+        assert_eq!(task.histogram().len(), EXEC_TIME_BUCKETS_US.len() + 1);
+        assert_eq!(task.histogram().iter().sum::<u64>(), task.runs());
+        // A virtual clock never runs late.
+        assert_eq!(task.max_jitter(), Duration::ZERO);
+    }
+}
+
+#[test]
+fn stats_record_overruns() {
+    let mut scheduler = Scheduler::new(VirtualClock::new());
+    scheduler.add_tick1("sleeper", Sleeper);
+    scheduler.advance(Duration::from_millis(2));
+
+    let stats = scheduler.stats();
+    let stats = &stats.lock().unwrap()[0];
+    assert_eq!(stats.runs(), 3);
+    assert_eq!(stats.overruns(), 3);
+    assert!(stats.worst() >= Duration::from_millis(2));
+    assert!(stats.mean() <= stats.worst());
+    // All runs land in the 2.5ms bucket or above.
+    assert_eq!(stats.histogram()[..5].iter().sum::<u64>(), 0);
+}
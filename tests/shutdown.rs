@@ -0,0 +1,36 @@
+use std::{
+    sync::{atomic::Ordering, mpsc},
+    time::Duration,
+};
+
+use cocos::controllers::shutdown::{on_shutdown_signal, signal_exit_code, ShutdownHandle};
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    low_level::raise,
+};
+
+#[test]
+fn signals_map_to_the_shell_exit_codes() {
+    assert_eq!(signal_exit_code(SIGINT), 130);
+    assert_eq!(signal_exit_code(SIGTERM), 143);
+}
+
+#[test]
+fn the_first_signal_is_remembered() {
+    let handle = ShutdownHandle::new();
+    assert_eq!(handle.signal(), None);
+
+    assert!(!handle.request(SIGTERM));
+    assert!(handle.request(SIGINT));
+    assert_eq!(handle.signal(), Some(SIGTERM));
+    assert!(handle.flag().load(Ordering::Relaxed));
+}
+
+#[test]
+fn received_signals_reach_the_handler() {
+    let (sender, receiver) = mpsc::channel();
+    on_shutdown_signal(move |signal| sender.send(signal).unwrap()).unwrap();
+
+    raise(SIGHUP).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Ok(SIGHUP));
+}
@@ -0,0 +1,77 @@
+use std::{io, sync::mpsc::{Receiver, TryRecvError}, time::Instant};
+
+use cocos::io::interface::gpio::{DrivesGpio, Edge, GpioEvent, PullMode};
+use cocos::io::sim_print::{gpio::PrintGpioDriver, sink::TraceSink, trace::TraceFormat};
+
+const PIN: u8 = 17;
+
+fn driver() -> PrintGpioDriver {
+    PrintGpioDriver::new(TraceSink::new(io::sink(), TraceFormat::Csv, Instant::now()).unwrap())
+}
+
+/// Drives `PIN` through `levels` and returns the levels of the events sent.
+fn edges(driver: &PrintGpioDriver, events: &Receiver<GpioEvent>, levels: &[bool]) -> Vec<bool> {
+    let inputs = driver.inputs();
+    for &level in levels {
+        inputs.set_level(PIN, level);
+    }
+    events.try_iter().map(|event| {
+        assert_eq!(event.pin_bcm, PIN);
+        event.level
+    }).collect()
+}
+
+#[test]
+fn rising_edges_are_reported() {
+    let mut driver = driver();
+    let events = driver.subscribe(PIN, Edge::Rising).unwrap();
+    assert_eq!(edges(&driver, &events, &[true, true, false, true]), [true, true]);
+}
+
+#[test]
+fn falling_edges_are_reported() {
+    let mut driver = driver();
+    let events = driver.subscribe(PIN, Edge::Falling).unwrap();
+    assert_eq!(edges(&driver, &events, &[false, true, false, false]), [false]);
+}
+
+#[test]
+fn both_edges_are_reported() {
+    let mut driver = driver();
+    let events = driver.subscribe(PIN, Edge::Both).unwrap();
+    assert_eq!(edges(&driver, &events, &[true, false, false, true]), [true, false, true]);
+}
+
+#[test]
+fn unsubscribing_closes_the_channel() {
+    let mut driver = driver();
+    let events = driver.subscribe(PIN, Edge::Both).unwrap();
+    driver.unsubscribe(PIN).unwrap();
+    driver.inputs().set_level(PIN, true);
+    assert!(matches!(events.try_recv(), Err(TryRecvError::Disconnected)));
+
+    // Subscribing again replaces the previous channel.
+    let first = driver.subscribe(PIN, Edge::Both).unwrap();
+    let second = driver.subscribe(PIN, Edge::Both).unwrap();
+    assert!(matches!(first.try_recv(), Err(TryRecvError::Disconnected)));
+    assert_eq!(edges(&driver, &second, &[false]), [false]);
+}
+
+#[test]
+fn configuring_a_watched_pin_keeps_it_watched() {
+    let mut driver = driver();
+    let events = driver.subscribe(PIN, Edge::Rising).unwrap();
+    driver.set_inp(PIN, PullMode::Down).unwrap();
+    assert_eq!(edges(&driver, &events, &[true]), [true]);
+}
+
+#[test]
+fn pulled_up_inputs_read_high_until_driven() {
+    let mut driver = driver();
+    driver.set_inp(PIN, PullMode::Up).unwrap();
+    assert!(driver.read(PIN).unwrap());
+
+    driver.inputs().set_level(PIN, false);
+    driver.set_inp(PIN, PullMode::Up).unwrap();
+    assert!(!driver.read(PIN).unwrap());
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use cocos::controllers::api::supervisor::{
+    exit_code, ApiExitAction, ApiExitPolicy, ApiSupervisor,
+};
+use subprocess::ExitStatus;
+
+fn restarting(max_restarts: u32) -> ApiSupervisor {
+    ApiSupervisor::new(ApiExitPolicy::Restart {
+        max_restarts,
+        backoff: Duration::from_millis(500),
+    })
+}
+
+#[test]
+fn the_restart_backoff_doubles_with_every_attempt() {
+    let mut supervisor = restarting(3);
+    for delay_ms in [500, 1000, 2000] {
+        assert_eq!(supervisor.on_exit(ExitStatus::Exited(1)),
+                   ApiExitAction::Restart(Duration::from_millis(delay_ms)));
+    }
+    assert_eq!(supervisor.restarts(), 3);
+}
+
+#[test]
+fn exhausted_restarts_leave_cocos_idle() {
+    let mut supervisor = restarting(1);
+    assert!(matches!(supervisor.on_exit(ExitStatus::Exited(1)), ApiExitAction::Restart(_)));
+    assert_eq!(supervisor.on_exit(ExitStatus::Exited(1)), ApiExitAction::Idle);
+    assert_eq!(supervisor.on_exit(ExitStatus::Signaled(9)), ApiExitAction::Idle);
+    assert_eq!(supervisor.restarts(), 1);
+
+    assert_eq!(restarting(0).on_exit(ExitStatus::Exited(0)), ApiExitAction::Idle);
+}
+
+#[test]
+fn the_backoff_stops_growing() {
+    let mut supervisor = restarting(20);
+    let delays: Vec<ApiExitAction> =
+        (0..10).map(|_| supervisor.on_exit(ExitStatus::Exited(1))).collect();
+    assert_eq!(delays[6], ApiExitAction::Restart(Duration::from_millis(500 * 64)));
+    assert_eq!(delays[9], delays[6]);
+}
+
+#[test]
+fn the_exit_policy_forwards_the_exit_code() {
+    let mut supervisor = ApiSupervisor::new(ApiExitPolicy::Exit);
+    assert_eq!(supervisor.on_exit(ExitStatus::Exited(3)), ApiExitAction::Exit(3));
+    assert_eq!(exit_code(ExitStatus::Signaled(15)), 143);
+
+    let mut supervisor = ApiSupervisor::new(ApiExitPolicy::default());
+    assert_eq!(supervisor.on_exit(ExitStatus::Exited(3)), ApiExitAction::Idle);
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use std::{io, time::Instant};
+
+use cocos::config::AppConfig;
+use cocos::controllers::interface::HandlesTick10Ms;
+use cocos::io::interface::net::{BroadcastsData, ListensForData};
+use cocos::io::sim_print::{
+    gpio::PrintGpioDriver,
+    pwm::PrintPwmDriver,
+    sink::TraceSink,
+    trace::TraceFormat,
+    uart::PrintUartDriver,
+};
+use cocos::sim::{
+    network::SimNetwork,
+    physics::{grid_poses, PhysicsDescriptor, Pose, World},
+    robot::{PhysicsTask, SimRobot},
+    trajectory::{TrajectoryLog, TRAJECTORY_HEADER},
+};
+
+use common::SharedBuffer;
+
+#[test]
+fn grid_poses_are_centered_and_spaced() {
+    let center = Pose { x: 1.0, y: -1.0, theta: 0.5 };
+    let poses = grid_poses(center, 4, 0.5);
+    let positions: Vec<(f32, f32)> = poses.iter().map(|pose| (pose.x, pose.y)).collect();
+
+    assert_eq!(positions, [(0.75, -1.25), (1.25, -1.25), (0.75, -0.75), (1.25, -0.75)]);
+    assert!(poses.iter().all(|pose| pose.theta == 0.5));
+}
+
+#[test]
+fn a_single_robot_starts_at_the_center() {
+    let center = Pose { x: 0.3, y: 0.2, theta: 0.0 };
+    assert_eq!(grid_poses(center, 1, 0.5), [center]);
+}
+
+#[test]
+fn broadcasts_reach_every_other_robot() {
+    let network = SimNetwork::new();
+    let mut drivers: Vec<_> = (0..3).map(|_| network.attach()).collect();
+    drivers[1].send_bytes(b"hello").unwrap();
+
+    let mut buffer = [0u8; 16];
+    assert_eq!(drivers[0].recv_bytes(&mut buffer).unwrap(), Some(5));
+    assert_eq!(&buffer[..5], b"hello");
+    assert_eq!(drivers[2].recv_bytes(&mut buffer).unwrap(), Some(5));
+    assert_eq!(drivers[1].recv_bytes(&mut buffer).unwrap(), None);
+    assert_eq!(drivers[0].recv_bytes(&mut buffer).unwrap(), None);
+}
+
+#[test]
+fn the_trajectory_of_every_robot_is_logged() {
+    let config = AppConfig::default();
+    let trace = TraceSink::new(io::sink(), TraceFormat::Csv, Instant::now()).unwrap();
+    let descriptor = PhysicsDescriptor { frame_rate_hz: 25.0, ..PhysicsDescriptor::default() };
+    let mut world = World::new(descriptor);
+    let mut robots = Vec::new();
+    let mut drivers = Vec::new();
+    for (robot_id, pose) in (7..).zip(grid_poses(Pose { x: 0.0, y: 0.0, theta: 0.0 }, 2, 0.5)) {
+        let gpio = PrintGpioDriver::new(trace.clone());
+        let pwm = PrintPwmDriver::new(trace.clone());
+        let uart = PrintUartDriver::new();
+        world.add_robot(pose);
+        robots.push(SimRobot::new(robot_id, config.mot_left, config.mot_right, gpio.inputs(),
+                                  pwm.outputs(), uart.feed()));
+        drivers.push((gpio, pwm, uart));
+    }
+
+    let buffer = SharedBuffer::default();
+    let mut physics = PhysicsTask::new(world, robots)
+        .logged(TrajectoryLog::new(buffer.clone()).unwrap());
+    for _ in 0..8 {
+        physics.on_tick10();
+    }
+    drop(physics);
+
+    let log = String::from_utf8(buffer.contents()).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines, [
+        TRAJECTORY_HEADER,
+        "0.040,7,-0.250000,0.000000,0.000000",
+        "0.040,8,0.250000,0.000000,0.000000",
+        "0.080,7,-0.250000,0.000000,0.000000",
+        "0.080,8,0.250000,0.000000,0.000000",
+    ]);
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use std::{
+    io::{BufWriter, Cursor},
+    thread,
+    time::{Duration, Instant},
+};
+
+use cocos::io::interface::pwm::DrivesPwm;
+use cocos::io::sim_print::{pwm::PrintPwmDriver, sink::TraceSink};
+use cocos::io::sim_print::trace::{
+    TraceDirection, TraceError, TraceEvent, TraceFormat, TracePull, TraceReader, TraceRecord,
+    TraceWriter, TRACE_VERSION,
+};
+
+use common::SharedBuffer;
+use uom::si::{f32::Frequency, frequency::hertz};
+
+fn records() -> Vec<TraceRecord> {
+    vec![
+        TraceRecord::new(Duration::ZERO, TraceEvent::Configure {
+            pin_bcm: 19,
+            direction: TraceDirection::Output,
+            pull: TracePull::Down,
+        }),
+        TraceRecord::new(Duration::from_nanos(1), TraceEvent::Configure {
+            pin_bcm: 4,
+            direction: TraceDirection::Input,
+            pull: TracePull::Up,
+        }),
+        TraceRecord::new(Duration::new(1, 500_000_000), TraceEvent::Level {
+            pin_bcm: 19,
+            level: true,
+        }),
+        TraceRecord::new(Duration::new(1, 999_999_999), TraceEvent::Level {
+            pin_bcm: 19,
+            level: false,
+        }),
+        TraceRecord::new(Duration::new(2, 123_456_789), TraceEvent::Pwm {
+            pin_bcm: 12,
+            frequency_hz: 600.0,
+            duty_cycle: 0.333_333_34,
+        }),
+        TraceRecord::new(Duration::new(3600, 0), TraceEvent::UartTx {
+            data: vec![0x00, 0x7f, 0xff, 0x10],
+        }),
+        TraceRecord::new(Duration::new(3600, 1), TraceEvent::UartTx { data: vec![] }),
+    ]
+}
+
+fn write(format: TraceFormat, records: &[TraceRecord]) -> Vec<u8> {
+    let mut writer = TraceWriter::new(Vec::new(), format).unwrap();
+    for record in records {
+        writer.write(record).unwrap();
+    }
+    writer.into_inner()
+}
+
+fn read(trace: &[u8]) -> Result<Vec<TraceRecord>, TraceError> {
+    TraceReader::new(Cursor::new(trace))?.collect()
+}
+
+#[test]
+fn csv_round_trips() {
+    let trace = write(TraceFormat::Csv, &records());
+    let reader = TraceReader::new(Cursor::new(&trace)).unwrap();
+    assert_eq!(reader.format(), TraceFormat::Csv);
+    assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), records());
+}
+
+#[test]
+fn binary_round_trips() {
+    let trace = write(TraceFormat::Binary, &records());
+    let reader = TraceReader::new(Cursor::new(&trace)).unwrap();
+    assert_eq!(reader.format(), TraceFormat::Binary);
+    assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), records());
+}
+
+#[test]
+fn long_uart_transmissions_are_split_in_binary() {
+    let data: Vec<u8> = (0..70_000u32).map(|i| i as u8).collect();
+    let record = TraceRecord::new(Duration::from_millis(5), TraceEvent::UartTx { data: data.clone() });
+    let read_back = read(&write(TraceFormat::Binary, &[record])).unwrap();
+
+    assert_eq!(read_back.len(), 2);
+    let mut joined = Vec::new();
+    for record in read_back {
+        assert_eq!(record.time, Duration::from_millis(5));
+        match record.event {
+            TraceEvent::UartTx { data } => joined.extend(data),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    assert_eq!(joined, data);
+}
+
+#[test]
+fn csv_is_documented_line_per_record() {
+    let trace = String::from_utf8(write(TraceFormat::Csv, &records()[2..])).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines, [
+        format!("#cocos-trace,{}", TRACE_VERSION).as_str(),
+        "1.500000000,S,19,1",
+        "1.999999999,S,19,0",
+        "2.123456789,P,12,600,0.33333334",
+        "3600.000000000,T,007fff10",
+        "3600.000000001,T,",
+    ]);
+}
+
+#[test]
+fn csv_accepts_short_times_and_empty_lines() {
+    let trace = b"#cocos-trace,1\n\n0.5,S,3,1\n12,C,3,O,U\n";
+    assert_eq!(read(trace).unwrap(), [
+        TraceRecord::new(Duration::from_millis(500), TraceEvent::Level { pin_bcm: 3, level: true }),
+        TraceRecord::new(Duration::from_secs(12), TraceEvent::Configure {
+            pin_bcm: 3,
+            direction: TraceDirection::Output,
+            pull: TracePull::Up,
+        }),
+    ]);
+}
+
+#[test]
+fn unknown_headers_are_rejected() {
+    assert!(matches!(read(b""), Err(TraceError::Header)));
+    assert!(matches!(read(b"0.1,1,S,3,1 ; PrintGpioDriver\n"), Err(TraceError::Header)));
+    assert!(matches!(read(b"#something-else,1\n"), Err(TraceError::Header)));
+    assert!(matches!(read(b"CCT"), Err(TraceError::Header)));
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    assert!(matches!(read(b"#cocos-trace,2\n"), Err(TraceError::UnsupportedVersion(2))));
+    let mut binary = b"CCTR".to_vec();
+    binary.extend_from_slice(&7u16.to_le_bytes());
+    assert!(matches!(read(&binary), Err(TraceError::UnsupportedVersion(7))));
+}
+
+#[test]
+fn malformed_csv_records_are_reported_by_number() {
+    for line in ["0.1,S,3,2", "0.1,S,300,1", "0.1,X,3", "0.1,C,3,O", "0.1,C,3,Q,U",
+                 "0.1,P,3,fast,0.5", "0.1,T,abc", "x,S,3,1", "0.1234567891,S,3,1"] {
+        let trace = format!("#cocos-trace,1\n0,S,1,1\n{}\n", line);
+        match read(trace.as_bytes()) {
+            Err(TraceError::Malformed { record: 2, .. }) => {}
+            other => panic!("{:?} read as {:?}", line, other),
+        }
+    }
+}
+
+#[test]
+fn truncated_binary_records_are_reported() {
+    let mut trace = write(TraceFormat::Binary, &records()[..3]);
+    trace.truncate(trace.len() - 1);
+    let results: Vec<_> = TraceReader::new(Cursor::new(&trace)).unwrap().collect();
+
+    assert_eq!(results.len(), 3);
+    assert!(results[..2].iter().all(Result::is_ok));
+    assert!(matches!(results[2], Err(TraceError::Malformed { record: 3, .. })));
+}
+
+#[test]
+fn sink_keeps_concurrent_records_whole_and_ordered() {
+    let buffer = SharedBuffer::default();
+    let sink = TraceSink::new(buffer.clone(), TraceFormat::Csv, Instant::now()).unwrap();
+
+    let threads: Vec<_> = (0..4u8)
+        .map(|pin_bcm| {
+            let sink = sink.clone();
+            thread::spawn(move || {
+                for i in 0..250 {
+                    sink.record(TraceEvent::Level { pin_bcm, level: i % 2 == 0 }).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    sink.flush().unwrap();
+
+    let records = read(&buffer.contents()).unwrap();
+    assert_eq!(records.len(), 1000);
+    assert!(records.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    for pin_bcm in 0..4u8 {
+        let levels: Vec<bool> = records
+            .iter()
+            .filter_map(|record| match record.event {
+                TraceEvent::Level { pin_bcm: pin, level } if pin == pin_bcm => Some(level),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(levels, (0..250).map(|i| i % 2 == 0).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn sink_writes_out_buffered_records_without_further_records() {
+    let buffer = SharedBuffer::default();
+    let sink = TraceSink::new(BufWriter::new(buffer.clone()), TraceFormat::Csv,
+                              Instant::now()).unwrap();
+    sink.record(TraceEvent::Level { pin_bcm: 5, level: true }).unwrap();
+    assert!(buffer.contents().is_empty());
+
+    thread::sleep(Duration::from_millis(250));
+    assert_eq!(read(&buffer.contents()).unwrap().len(), 1);
+}
+
+#[test]
+fn pwm_settings_are_traced_once_per_change() {
+    let buffer = SharedBuffer::default();
+    let sink = TraceSink::new(buffer.clone(), TraceFormat::Csv, Instant::now()).unwrap();
+    let mut pwm = PrintPwmDriver::new(sink.clone());
+    let frequency = Frequency::new::<hertz>(600.0);
+
+    pwm.set_freq_dc(frequency, 0.5, 18).unwrap();
+    pwm.set_freq_dc(frequency, 0.5, 18).unwrap();
+    pwm.set_freq_dc(frequency, 0.25, 18).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    sink.flush().unwrap();
+
+    let events: Vec<TraceEvent> = read(&buffer.contents())
+        .unwrap()
+        .into_iter()
+        .map(|record| record.event)
+        .collect();
+    assert_eq!(events, [
+        TraceEvent::Pwm { pin_bcm: 18, frequency_hz: 600.0, duty_cycle: 0.5 },
+        TraceEvent::Pwm { pin_bcm: 18, frequency_hz: 600.0, duty_cycle: 0.25 },
+    ]);
+    assert_eq!(pwm.outputs().duty_cycle(18), 0.25);
+}
@@ -0,0 +1,89 @@
+use std::{io::Cursor, time::Duration};
+
+use cocos::io::interface::uart::{
+    DrivesUart, UartDescriptor, UartError, UartParity, UartRingBuffer,
+};
+use cocos::io::sim_print::uart::PrintUartDriver;
+
+const FAST_LINE: UartDescriptor = UartDescriptor {
+    baud_rate: 1_000_000,
+    parity: UartParity::None,
+    data_bits: 8,
+    stop_bits: 1,
+};
+
+fn pop_all(ring: &mut UartRingBuffer) -> Vec<u8> {
+    let mut into = vec![0u8; ring.capacity()];
+    let count = ring.pop_into(&mut into);
+    into.truncate(count);
+    into
+}
+
+#[test]
+fn bytes_wrap_around_the_end_of_the_buffer() {
+    let mut ring = UartRingBuffer::new(4);
+    ring.push(&[1, 2, 3]);
+    let mut into = [0u8; 2];
+    assert_eq!(ring.pop_into(&mut into), 2);
+    assert_eq!(into, [1, 2]);
+
+    ring.push(&[4, 5, 6]);
+    assert_eq!(ring.len(), 4);
+    assert_eq!(ring.free(), 0);
+    assert_eq!(pop_all(&mut ring), [3, 4, 5, 6]);
+    assert!(ring.is_empty());
+    assert_eq!(ring.take_dropped(), 0);
+}
+
+#[test]
+fn overflowing_bytes_overwrite_the_oldest_ones() {
+    let mut ring = UartRingBuffer::new(4);
+    ring.push(&[1, 2, 3]);
+    ring.push(&[4, 5, 6, 7, 8, 9]);
+    assert_eq!(ring.take_dropped(), 5);
+    assert_eq!(ring.take_dropped(), 0);
+    assert_eq!(pop_all(&mut ring), [6, 7, 8, 9]);
+
+    let mut empty = UartRingBuffer::new(0);
+    empty.push(&[1, 2]);
+    assert_eq!(empty.take_dropped(), 2);
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn clearing_discards_the_stored_bytes() {
+    let mut ring = UartRingBuffer::new(4);
+    ring.push(&[1, 2, 3]);
+    ring.clear();
+    assert!(ring.is_empty());
+    ring.push(&[4]);
+    assert_eq!(pop_all(&mut ring), [4]);
+}
+
+#[test]
+fn replayed_bytes_arrive_in_order() {
+    let sent: Vec<u8> = (0..=255).cycle().take(5000).collect();
+    let mut uart = PrintUartDriver::replay(Cursor::new(sent.clone()), FAST_LINE);
+
+    let mut received = Vec::new();
+    let mut chunk = [0u8; 256];
+    while received.len() < sent.len() {
+        let count = uart.read_bytes_timeout(&mut chunk, Duration::from_secs(1)).unwrap();
+        received.extend_from_slice(&chunk[..count]);
+    }
+    assert_eq!(received, sent);
+
+    assert!(matches!(uart.read_bytes_timeout(&mut chunk, Duration::from_millis(20)),
+                     Err(UartError::Timeout)));
+}
+
+#[test]
+fn an_idle_line_reads_nothing() {
+    let mut uart = PrintUartDriver::new();
+    let mut chunk = [0u8; 16];
+    assert_eq!(uart.read_bytes(&mut chunk).unwrap(), 0);
+
+    uart.feed().push(&[0xaa, 0x55]);
+    assert_eq!(uart.read_bytes(&mut chunk).unwrap(), 2);
+    assert_eq!(chunk[..2], [0xaa, 0x55]);
+}
@@ -0,0 +1,143 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use cocos::controllers::api::{
+    user_log::{forward_stream, level_from_python, USER_STDERR_TARGET, USER_STDOUT_TARGET},
+    ApiController, ApiDescriptor,
+};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Remembers every record as `(target, level, message)`.
+struct CapturingLogger {
+    records: Mutex<Vec<(String, Level, String)>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records.lock().unwrap().push((
+            record.target().to_string(),
+            record.level(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger { records: Mutex::new(Vec::new()) };
+
+/// Returns the records emitted for robot `robot_id`. The tests run in
+/// parallel, so each one uses a robot id of its own.
+fn records_of(robot_id: u32) -> Vec<(String, Level, String)> {
+    let tag = format!("[robot {}] ", robot_id);
+    LOGGER.records.lock().unwrap().iter()
+        .filter(|(_, _, message)| message.starts_with(&tag))
+        .cloned()
+        .collect()
+}
+
+/// Installs the capturing logger, which may only be done once per process.
+fn capture() {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(LevelFilter::Trace);
+}
+
+/// Forwards `output` as if the user script of robot `robot_id` had written it
+/// to the stream of `target`, returning once all of it was emitted.
+fn forward(output: &[u8], target: &'static str, level: Level, robot_id: u32) {
+    capture();
+    let path = env::temp_dir().join(format!("cocos-user-log-{}-{}", std::process::id(), robot_id));
+    File::create(&path).unwrap().write_all(output).unwrap();
+    forward_stream(File::open(&path).unwrap(), target, level, robot_id).join().unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn python_levels_map_to_the_closest_level() {
+    assert_eq!(level_from_python(50), Level::Error);
+    assert_eq!(level_from_python(40), Level::Error);
+    assert_eq!(level_from_python(35), Level::Warn);
+    assert_eq!(level_from_python(30), Level::Warn);
+    assert_eq!(level_from_python(20), Level::Info);
+    assert_eq!(level_from_python(10), Level::Debug);
+    assert_eq!(level_from_python(5), Level::Trace);
+    assert_eq!(level_from_python(0), Level::Trace);
+}
+
+#[test]
+fn stdout_is_forwarded_line_by_line() {
+    forward(b"hello\r\nworld\nno newline", USER_STDOUT_TARGET, Level::Info, 1);
+
+    let records = records_of(1);
+    let expected = ["[robot 1] hello", "[robot 1] world", "[robot 1] no newline"];
+    assert_eq!(records.len(), expected.len());
+    for ((target, level, message), expected) in records.iter().zip(expected) {
+        assert_eq!(target, USER_STDOUT_TARGET);
+        assert_eq!(*level, Level::Info);
+        assert_eq!(message, expected);
+    }
+}
+
+#[test]
+fn stderr_is_forwarded_at_its_own_level() {
+    forward(b"Traceback (most recent call last):\n", USER_STDERR_TARGET, Level::Warn, 2);
+
+    assert_eq!(records_of(2), [(
+        USER_STDERR_TARGET.to_string(),
+        Level::Warn,
+        "[robot 2] Traceback (most recent call last):".to_string(),
+    )]);
+}
+
+#[test]
+fn invalid_utf8_is_forwarded_lossily() {
+    forward(b"caf\xe9\n", USER_STDOUT_TARGET, Level::Info, 3);
+
+    let records = records_of(3);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].2, "[robot 3] caf\u{FFFD}");
+}
+
+#[test]
+fn the_output_of_the_api_process_is_forwarded() {
+    capture();
+    let robot_id = 4;
+    let dir = env::temp_dir();
+    let prefix = format!("cocos-user-log-{}-{}", std::process::id(), robot_id);
+
+    // The interpreter ignores its arguments and just talks.
+    let interpreter = dir.join(format!("{}.sh", prefix));
+    fs::write(&interpreter, "#!/bin/sh\necho printed\necho raised >&2\n").unwrap();
+    fs::set_permissions(&interpreter, fs::Permissions::from_mode(0o755)).unwrap();
+    let mut api_controller = ApiController::new(ApiDescriptor {
+        interpreter: interpreter.to_string_lossy().into_owned(),
+        endpoint: format!("ipc://{}", dir.join(format!("{}.ipc", prefix)).display()),
+        ..ApiDescriptor::python3()
+    }, robot_id);
+    api_controller.restart_api().unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while records_of(robot_id).len() < 2 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    api_controller.shutdown().unwrap();
+    fs::remove_file(&interpreter).unwrap();
+
+    let mut records = records_of(robot_id);
+    records.sort();
+    assert_eq!(records, [
+        (USER_STDERR_TARGET.to_string(), Level::Warn, "[robot 4] raised".to_string()),
+        (USER_STDOUT_TARGET.to_string(), Level::Info, "[robot 4] printed".to_string()),
+    ]);
+}
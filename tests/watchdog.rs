@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+use cocos::controllers::watchdog::{CommandWatchdog, WatchdogDescriptor, WatchdogEvent};
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+fn watchdog(enabled: bool, timeout: Duration) -> CommandWatchdog {
+    CommandWatchdog::new(WatchdogDescriptor { enabled, timeout })
+}
+
+#[test]
+fn the_watchdog_is_armed_by_the_first_command() {
+    let start = Instant::now();
+    let mut watchdog = watchdog(true, TIMEOUT);
+    assert_eq!(watchdog.poll(start + 10 * TIMEOUT), None);
+
+    assert_eq!(watchdog.feed(start), None);
+    assert_eq!(watchdog.poll(start + TIMEOUT), None);
+    assert!(!watchdog.is_tripped());
+}
+
+#[test]
+fn missing_commands_trip_the_watchdog_once() {
+    let start = Instant::now();
+    let mut watchdog = watchdog(true, TIMEOUT);
+    watchdog.feed(start);
+
+    let late = start + TIMEOUT + Duration::from_millis(1);
+    assert_eq!(watchdog.poll(late), Some(WatchdogEvent::Tripped));
+    assert!(watchdog.is_tripped());
+    assert_eq!(watchdog.poll(late + TIMEOUT), None);
+    assert!(watchdog.is_tripped());
+}
+
+#[test]
+fn a_fresh_command_rearms_the_watchdog() {
+    let start = Instant::now();
+    let mut watchdog = watchdog(true, TIMEOUT);
+    watchdog.feed(start);
+    let late = start + 2 * TIMEOUT;
+    assert_eq!(watchdog.poll(late), Some(WatchdogEvent::Tripped));
+
+    assert_eq!(watchdog.feed(late), Some(WatchdogEvent::Released));
+    assert!(!watchdog.is_tripped());
+    assert_eq!(watchdog.feed(late), None);
+    assert_eq!(watchdog.poll(late + TIMEOUT), None);
+    assert_eq!(watchdog.poll(late + 2 * TIMEOUT), Some(WatchdogEvent::Tripped));
+}
+
+#[test]
+fn a_zero_timeout_trips_as_soon_as_time_passes() {
+    let start = Instant::now();
+    let mut enabled = watchdog(true, Duration::ZERO);
+    enabled.feed(start);
+    assert_eq!(enabled.poll(start), None);
+    assert_eq!(enabled.poll(start + Duration::from_nanos(1)), Some(WatchdogEvent::Tripped));
+
+    let mut disabled = watchdog(false, Duration::ZERO);
+    disabled.feed(start);
+    assert_eq!(disabled.poll(start + TIMEOUT), None);
+    assert!(!disabled.is_tripped());
+}